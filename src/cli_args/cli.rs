@@ -1,8 +1,45 @@
+use std::path::Path;
+
 use clap::{Arg, ArgAction, ArgGroup, Command, crate_name, crate_version};
-use std::ops::RangeInclusive;
+use regex::Regex;
+
+use super::{Percentile, SortKey};
+use crate::collate::{Collation, Compare, SortOrder, human_size};
+use crate::columns::{ColumnList, ColumnPart, Delimiter, Quoting};
+use crate::format::OutputFormat;
+use crate::hyperloglog::PRECISIONS;
+use crate::ranges::{PatternBound, RangeBound, RangePart, RangeSpec};
+use crate::reduce::MAX_BINS;
+use crate::schema::Schema;
 
-use crate::columns::ColumnList;
-use crate::ranges::{RangeBound, RangeSpec};
+/// The options asking for a summary; `--group-by` splits any of them.
+const SUMMARIES: [&str; 15] = [
+    "count",
+    "sum",
+    "avg",
+    "min",
+    "max",
+    "stddev",
+    "var",
+    "skew",
+    "median",
+    "percentile",
+    "distinct",
+    "first",
+    "last",
+    "mode",
+    "collect",
+];
+
+/// The options appending a running column, which `--group-by` restarts.
+const RUNNING: [&str; 6] = [
+    "cumsum",
+    "delta",
+    "rank",
+    "moving-avg",
+    "moving-min",
+    "moving-max",
+];
 
 pub fn cli() -> Command {
     Command::new(crate_name!())
@@ -12,8 +49,14 @@ pub fn cli() -> Command {
             "Examples:\n\
              \x20 ft -R 1-5 -C 2-4 input.txt             keep rows 1-5, columns 2-4\n\
              \x20 ft -R '~10-~1' input.txt               the last ten rows, like tail\n\
+             \x20 ft -R 0~10 input.txt                   every tenth row\n\
+             \x20 ft -R '/BEGIN/+1-/END/-1' a.conf      what lies between the markers\n\
              \x20 ft -F , -C 3,1 data.csv                CSV fields 3 and 1, in that order\n\
+             \x20 ft -F , -C '~1' data.csv               the last field of every line\n\
              \x20 ft -F , --group-by 1 --sum 2 data.csv  sum field 2 per distinct field 1\n\
+             \x20 ft --jsonl -C .user.id,.ms a.jsonl     members of JSON objects, by key path\n\
+             \x20 ft -F , --count --group-by 1 --output-format table a.csv\n\
+             \x20                                        a summary, aligned like column -t\n\
              \x20 ft -g ERROR -d -i --backup .bak a.log  delete matching rows in place,\n\
              \x20                                        keeping a backup\n\
              \n\
@@ -29,7 +72,23 @@ pub fn cli() -> Command {
                 .required(false)
                 .allow_hyphen_values(true)
                 .value_parser(parse_row_ranges)
-                .help("Rows to process: e.g. 3, 2-5, 10-, -5, ~10-~1, or a list like 1-5,10-20"),
+                .help("Rows to process: e.g. 3, 2-5, 10-, -5, ~10-~1, /BEGIN/-/END/, 1~2, 2-100:5, or a list like 1-5,10-20"),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .required(false)
+                .conflicts_with("rows")
+                .value_parser(parse_row_bound)
+                .help("Rows to process start at this bound: a row, ~N, or /regex/ (+N/-N shifts it)"),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .required(false)
+                .conflicts_with("rows")
+                .value_parser(parse_row_bound)
+                .help("Rows to process end at this bound: a row, ~N, or /regex/ (+N/-N shifts it)"),
         )
         .arg(
             Arg::new("columns")
@@ -38,7 +97,7 @@ pub fn cli() -> Command {
                 .required(false)
                 .allow_hyphen_values(true)
                 .value_parser(parse_column_list)
-                .help("Columns to process: e.g. 3, 2-5, 10-, -5, or a list like 1,3,5-7 (order kept: 3,1,2 permutes)"),
+                .help("Columns to process: e.g. 3, 2-5, 10-, -5, ~1 (the last), or a list like 1,3,5-7 (order kept: 3,1,2 permutes); names with --header or --schema, key paths like .user.id with --jsonl"),
         )
         .arg(
             Arg::new("fields")
@@ -46,11 +105,43 @@ pub fn cli() -> Command {
                 .long("fields")
                 .required(false)
                 .requires("column-ranges")
-                .value_parser(parse_delimiter)
-                .help("Treat the column ranges as fields separated by this delimiter (requires a column range)"),
+                .value_parser(parse_field_delimiter)
+                .help("Treat the column ranges as fields separated by this delimiter; ' ' splits on runs of whitespace, like awk (requires a column range)"),
+        )
+        .arg(
+            Arg::new("fields-regex")
+                .long("fields-regex")
+                .required(false)
+                .conflicts_with("fields")
+                .requires("column-ranges")
+                .value_parser(parse_fields_regex)
+                .help("Treat the column ranges as fields separated by matches of this regex (requires a column range)"),
+        )
+        //the options refining field mode take either way of splitting
+        .group(
+            ArgGroup::new("field-mode")
+                .args(["fields", "fields-regex"])
+                .multiple(false),
+        )
+        .arg(
+            Arg::new("jsonl")
+                .long("jsonl")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                //a JSON object names its members itself
+                .conflicts_with_all(["field-mode", "header", "schema"])
+                .help("Read every line as a JSON object (JSON Lines): column lists address its members by key path (.user.id) or position"),
+        )
+        //the columns are delimited values rather than chars, which is what
+        //the options on how values are written out need
+        .group(
+            ArgGroup::new("value-mode")
+                .args(["fields", "fields-regex", "jsonl"])
+                .multiple(false),
         )
         //--fields needs some column range to interpret, but any of the
-        //three will do, so they form one group it can require
+        //three will do, so they form one group it can require; writing
+        //the fields as objects reads every one of them without a range
         .group(
             ArgGroup::new("column-ranges")
                 .args([
@@ -61,7 +152,25 @@ pub fn cli() -> Command {
                     "avg",
                     "min",
                     "max",
+                    "stddev",
+                    "var",
+                    "skew",
+                    "median",
+                    "percentile",
+                    "distinct",
+                    "first",
+                    "last",
+                    "mode",
+                    "collect",
+                    "cumsum",
+                    "delta",
+                    "rank",
+                    "moving-avg",
+                    "moving-min",
+                    "moving-max",
+                    "histogram",
                     "group-by",
+                    "output-format",
                 ])
                 .multiple(true),
         )
@@ -70,16 +179,68 @@ pub fn cli() -> Command {
                 .long("quoted")
                 .required(false)
                 .action(ArgAction::SetTrue)
-                .requires("fields")
-                .help("Respect \"quoted\" fields: a delimiter inside quotes does not split (requires --fields)"),
+                .requires("field-mode")
+                .help("Respect \"quoted\" fields: a delimiter or newline inside quotes does not split (requires --fields or --fields-regex)"),
+        )
+        .arg(
+            Arg::new("quote")
+                .long("quote")
+                .required(false)
+                .requires("field-mode")
+                .value_parser(parse_quoting)
+                .help("Write the selected or rewritten fields as CSV, quoting minimal (as needed), always or never (requires --fields or --fields-regex)"),
+        )
+        .arg(
+            Arg::new("unquote")
+                .long("unquote")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .requires("quoted")
+                .conflicts_with("quote")
+                .help("Strip the quotes from the fields written, like --quote never (requires --quoted)"),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .help("The first row is a header: it passes through, and with --fields column lists may use its names"),
+        )
+        .arg(
+            Arg::new("schema")
+                .long("schema")
+                .required(false)
+                //a schema lays out chars, which field mode does not count
+                .conflicts_with("field-mode")
+                .value_parser(parse_schema)
+                .help("Name fixed-width columns from a schema file (a field per line: name, from-to, optionally text or number); column lists may use the names"),
+        )
+        .arg(
+            Arg::new("delimit")
+                .long("delimit")
+                .required(false)
+                .requires("schema")
+                .conflicts_with("delete")
+                .value_parser(parse_delimiter)
+                .help("Turn the fixed-width records into fields joined by this: the --cols columns, else every schema field, padding trimmed (requires --schema)"),
         )
         .arg(
             Arg::new("output-delimiter")
                 .long("output-delimiter")
                 .required(false)
-                .requires("fields")
+                .requires("value-mode")
                 .value_parser(parse_delimiter)
-                .help("Join the selected fields with this delimiter instead of the input one (requires --fields)"),
+                .help("Join the selected fields with this delimiter instead of the input one, or a tab with --jsonl (requires --fields, --fields-regex or --jsonl)"),
+        )
+        .arg(
+            Arg::new("output-format")
+                .long("output-format")
+                .required(false)
+                //the format takes rows of fields: a join leaves one row of
+                //everything, and context comes with `--` lines between
+                .conflicts_with_all(["join", "context", "before-context", "after-context"])
+                .value_parser(parse_output_format)
+                .help("Write the rows (or the summary) as json, jsonl, markdown, html, tsv, csv, or an aligned table, named by the header's names (fields split on the output delimiter, a tab in char mode)"),
         )
         .arg(
             Arg::new("sort-key")
@@ -87,8 +248,9 @@ pub fn cli() -> Command {
                 .required(false)
                 .allow_hyphen_values(true)
                 .requires("sort")
-                .value_parser(parse_column_list)
-                .help("Columns keying --sort, instead of --cols (requires --sort)"),
+                .action(ArgAction::Append)
+                .value_parser(parse_sort_key)
+                .help("Columns keying --sort, instead of --cols, as <columns>[:nVhfr] with modifiers numeric, natural, human size, case-folded, reverse; repeat to break ties (requires --sort)"),
         )
         .arg(
             Arg::new("unique-key")
@@ -124,6 +286,71 @@ pub fn cli() -> Command {
                 .requires("sort")
                 .help("Sort in descending order (requires --sort)"),
         )
+        //-V and -h, as `sort` spells them, stay --version and --help
+        .arg(
+            Arg::new("version-sort")
+                .long("version-sort")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .requires("compared")
+                .conflicts_with("numeric")
+                .help("Compare sort keys, --min and --max naturally, digit runs as numbers: file2 before file10, 1.9.3 before 1.10.0 (requires --sort, --min or --max)"),
+        )
+        .arg(
+            Arg::new("human-numeric-sort")
+                .long("human-numeric-sort")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .requires("compared")
+                .conflicts_with_all(["numeric", "version-sort"])
+                .help("Compare sort keys, --min and --max as sizes like 1.5K or 2M (requires --sort, --min or --max)"),
+        )
+        .group(
+            ArgGroup::new("compared")
+                .args(["sort", "min", "max"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("collate")
+                .long("collate")
+                .required(false)
+                .requires("collated")
+                .value_parser(parse_collation)
+                .help("Compare text as fold (ignoring case), accents (ignoring case and accents) or uca (the Unicode Collation Algorithm), for --sort and --unique (requires --sort or --unique)"),
+        )
+        .group(
+            ArgGroup::new("collated")
+                .args(["sort", "unique"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("top")
+                .long("top")
+                .required(false)
+                .requires("sort")
+                //the heaps would fill before --unique drops a row, so
+                //the rows a duplicate pushed out would never come back
+                .conflicts_with("unique")
+                .value_parser(parse_top)
+                .help("Keep only the first this many sorted rows, per --group-by key if given (requires --sort)"),
+        )
+        .arg(
+            Arg::new("buffer-size")
+                .long("buffer-size")
+                .required(false)
+                .requires("sort")
+                //the best `--top` rows are all it keeps anyway
+                .conflicts_with("top")
+                .value_parser(parse_buffer_size)
+                .help("Sort in runs of about this much memory, e.g. 1G or 512M, spilled to disk and merged, for inputs larger than memory (requires --sort)"),
+        )
+        .arg(
+            Arg::new("temp-dir")
+                .long("temp-dir")
+                .required(false)
+                .requires("buffer-size")
+                .help("Directory the --buffer-size runs are spilled to, instead of the system's temporary directory (requires --buffer-size)"),
+        )
         .arg(
             Arg::new("tac")
                 .long("tac")
@@ -173,6 +400,33 @@ pub fn cli() -> Command {
                 .requires("grep")
                 .help("Invert the --grep match, like grep -v (requires --grep)"),
         )
+        .arg(
+            Arg::new("after-context")
+                .short('A')
+                .long("after-context")
+                .required(false)
+                .requires("grep")
+                .value_parser(parse_context)
+                .help("Take this many rows after each --grep match along with it (requires --grep)"),
+        )
+        .arg(
+            Arg::new("before-context")
+                .short('B')
+                .long("before-context")
+                .required(false)
+                .requires("grep")
+                .value_parser(parse_context)
+                .help("Take this many rows before each --grep match along with it (requires --grep)"),
+        )
+        //-C is taken by --cols, so unlike grep's this one is long only
+        .arg(
+            Arg::new("context")
+                .long("context")
+                .required(false)
+                .requires("grep")
+                .value_parser(parse_context)
+                .help("Take this many rows on either side of each --grep match (requires --grep)"),
+        )
         .arg(
             Arg::new("upper")
                 .long("upper")
@@ -223,6 +477,88 @@ pub fn cli() -> Command {
                 .conflicts_with_all(["delete", "sort", "tac", "shuffle"])
                 .help("Number the output rows, like nl"),
         )
+        .arg(
+            Arg::new("cumsum")
+                .long("cumsum")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Append the running total of the numbers in these columns to each row"),
+        )
+        .arg(
+            Arg::new("delta")
+                .long("delta")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Append the difference from the previous row's number in these columns to each row"),
+        )
+        .arg(
+            Arg::new("rank")
+                .long("rank")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Append each row's rank by the value of these columns, equal rows sharing one (the rows must come sorted by it, or be sorted with --sort)"),
+        )
+        .arg(
+            Arg::new("moving-avg")
+                .long("moving-avg")
+                .required(false)
+                .allow_hyphen_values(true)
+                .requires("window")
+                .value_parser(parse_column_list)
+                .help("Append the mean of the last --window numbers in these columns to each row"),
+        )
+        .arg(
+            Arg::new("moving-min")
+                .long("moving-min")
+                .required(false)
+                .allow_hyphen_values(true)
+                .requires("window")
+                .value_parser(parse_column_list)
+                .help("Append the smallest of the last --window numbers in these columns to each row"),
+        )
+        .arg(
+            Arg::new("moving-max")
+                .long("moving-max")
+                .required(false)
+                .allow_hyphen_values(true)
+                .requires("window")
+                .value_parser(parse_column_list)
+                .help("Append the largest of the last --window numbers in these columns to each row"),
+        )
+        .arg(
+            Arg::new("window")
+                .long("window")
+                .required(false)
+                .requires("moving")
+                .value_parser(parse_window)
+                .help("How many numbers the moving statistics cover (requires --moving-avg, --moving-min or --moving-max)"),
+        )
+        .group(
+            ArgGroup::new("moving")
+                .args(["moving-avg", "moving-min", "moving-max"])
+                .multiple(true),
+        )
+        //like the row numbers, running columns follow the rows in the
+        //order they are processed: reordering them afterwards would
+        //scramble the totals, and a summary or join leaves no rows to
+        //append to
+        .group(
+            ArgGroup::new("running")
+                .args(RUNNING)
+                .multiple(true)
+                .conflicts_with_all(["summary", "join", "delete", "tac", "shuffle"]),
+        )
+        //but a rank wants the rows sorted, so it is given to them as
+        //they come out of a sort instead
+        .group(
+            ArgGroup::new("unsorted")
+                .args(RUNNING.iter().filter(|arg| **arg != "rank"))
+                .multiple(true)
+                .conflicts_with("sort"),
+        )
         .arg(
             Arg::new("split-on")
                 .long("split-on")
@@ -238,7 +574,7 @@ pub fn cli() -> Command {
                 //a reducer consumes the rows, and there is only one set of
                 //them: --join and a summary cannot both have it. Numbering
                 //them would number rows nobody ever sees
-                .conflicts_with_all(["count", "sum", "avg", "min", "max", "delete", "number"])
+                .conflicts_with_all(["summary", "delete", "number"])
                 .value_parser(parse_delimiter)
                 .help("Join every row into a single row, separated by this (like paste -s)"),
         )
@@ -281,14 +617,140 @@ pub fn cli() -> Command {
                 .value_parser(parse_column_list)
                 .help("Summarize: the largest number in these columns"),
         )
+        .arg(
+            Arg::new("stddev")
+                .long("stddev")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Summarize: the standard deviation of the numbers in these columns"),
+        )
+        .arg(
+            Arg::new("var")
+                .long("var")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Summarize: the variance of the numbers in these columns"),
+        )
+        .arg(
+            Arg::new("skew")
+                .long("skew")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Summarize: the skewness of the numbers in these columns"),
+        )
+        .arg(
+            Arg::new("population")
+                .long("population")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .requires("spreads")
+                .help("Treat the rows as the whole population rather than a sample when computing --stddev, --var and --skew"),
+        )
+        .group(
+            ArgGroup::new("spreads")
+                .args(["stddev", "var", "skew"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("median")
+                .long("median")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Summarize: the median of the numbers in these columns"),
+        )
+        .arg(
+            Arg::new("percentile")
+                .long("percentile")
+                .required(false)
+                .action(ArgAction::Append)
+                .allow_hyphen_values(true)
+                .value_parser(parse_percentile)
+                .help("Summarize: the value this percentage of the numbers lie below, as <percent>:<columns>, e.g. 95:2 (repeatable)"),
+        )
+        .arg(
+            Arg::new("approximate")
+                .long("approximate")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .requires("estimable")
+                .help("Estimate the median and percentiles (a t-digest) and distinct counts (a HyperLogLog) in bounded memory instead of keeping every value (requires --median, --percentile or --distinct)"),
+        )
+        .group(
+            ArgGroup::new("estimable")
+                .args(["median", "percentile", "distinct"])
+                .multiple(true),
+        )
+        .arg(
+            Arg::new("precision")
+                .long("precision")
+                .required(false)
+                .requires("approximate")
+                .requires("distinct")
+                .value_parser(parse_precision)
+                .help("Use 2^P registers to estimate --distinct, for a standard error of about 1.04/sqrt(2^P) (4-18, default 14: 0.8% in 16 KiB per group; requires --approximate)"),
+        )
+        .arg(
+            Arg::new("distinct")
+                .long("distinct")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Summarize: how many different values these columns hold"),
+        )
+        .arg(
+            Arg::new("first")
+                .long("first")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Summarize: the first value in these columns"),
+        )
+        .arg(
+            Arg::new("last")
+                .long("last")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Summarize: the last value in these columns"),
+        )
+        .arg(
+            Arg::new("mode")
+                .long("mode")
+                .required(false)
+                .allow_hyphen_values(true)
+                .value_parser(parse_column_list)
+                .help("Summarize: the most common value in these columns"),
+        )
+        //the columns are parsed into a list with the rest of the config;
+        //a value parser sees each of the two values alone
+        .arg(
+            Arg::new("collect")
+                .long("collect")
+                .required(false)
+                .num_args(2)
+                .value_names(["COLS", "SEP"])
+                .allow_hyphen_values(true)
+                .help("Summarize: every value in these columns, joined by the separator"),
+        )
         .arg(
             Arg::new("group-by")
                 .long("group-by")
                 .required(false)
                 .allow_hyphen_values(true)
-                .requires("summary")
+                .requires("grouped")
                 .value_parser(parse_column_list)
-                .help("Summarize once per distinct value of these columns (requires a summary)"),
+                .help("Summarize, restart the running columns, or keep a --top, once per distinct value of these columns (requires a summary, a running column or --top)"),
+        )
+        .group(
+            ArgGroup::new("grouped")
+                .args(SUMMARIES)
+                .args(RUNNING)
+                .arg("top")
+                .multiple(true),
         )
         //a summary replaces the rows it summarizes, so there is nothing
         //left for --delete to remove, nor any rows to number. Reordering
@@ -296,10 +758,47 @@ pub fn cli() -> Command {
         //`--sort --group-by` reports the groups in sorted order
         .group(
             ArgGroup::new("summary")
-                .args(["count", "sum", "avg", "min", "max"])
+                .args(SUMMARIES)
                 .multiple(true)
                 .conflicts_with_all(["delete", "number"]),
         )
+        //a histogram is a reducer too: it consumes the rows like a summary
+        //or --join, and so goes with neither, nor with what a summary
+        //refuses
+        .arg(
+            Arg::new("histogram")
+                .long("histogram")
+                .required(false)
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["summary", "join", "running", "delete", "number"])
+                .value_parser(parse_column_list)
+                .help("Write how often each value of these columns appears instead of the rows, most frequent first, with its share of the total"),
+        )
+        .arg(
+            Arg::new("bins")
+                .long("bins")
+                .required(false)
+                .requires("histogram")
+                .value_parser(parse_bins)
+                .help("Count the numbers of the --histogram in this many equal bins from the smallest to the largest"),
+        )
+        .arg(
+            Arg::new("bin-width")
+                .long("bin-width")
+                .required(false)
+                .requires("histogram")
+                .conflicts_with("bins")
+                .value_parser(parse_bin_width)
+                .help("Count the numbers of the --histogram in bins of this width"),
+        )
+        .arg(
+            Arg::new("bar")
+                .long("bar")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .requires("histogram")
+                .help("Draw each --histogram count as a bar of #"),
+        )
         .arg(
             Arg::new("wrap")
                 .long("wrap")
@@ -402,37 +901,203 @@ pub fn cli() -> Command {
 }
 
 /// Parse a row specification: a comma-separated list of range parts.
+/// A comma inside a `/pattern/` belongs to the pattern.
 fn parse_row_ranges(input: &str) -> Result<RangeSpec, String> {
-    let parts = input
-        .split(',')
-        .map(parse_range_part)
+    let parts = split_row_parts(input)
+        .into_iter()
+        .map(parse_row_part)
         .collect::<Result<Vec<_>, String>>()?;
     Ok(RangeSpec::new(parts))
 }
 
+/// Parse one part of a row specification: a range part, stepped or
+/// not. `<range>:<step>` takes every step-th row of the range, counted
+/// from its start; `<first>~<step>`, as in sed, every step-th row from
+/// `first` to the end — so `0~4` is rows 4, 8, 12, …
+fn parse_row_part(part: &str) -> Result<RangePart, String> {
+    //`~N` alone counts from the end: only a number before the `~`
+    //makes it a step
+    if let Some((first, step)) = part.split_once('~')
+        && !first.is_empty()
+        && first
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+    {
+        let first: usize = first
+            .parse()
+            .map_err(|_| format!("Range value `{first}` isn't a number"))?;
+        return Ok(RangePart {
+            from: RangeBound::FromStart(first),
+            to: RangeBound::FromStart(usize::MAX),
+            step: parse_step(step)?,
+        });
+    }
+
+    //a `:` inside a pattern is followed by its closing `/`, which no
+    //step is
+    let (range, step) = match part.rsplit_once(':') {
+        Some((range, step)) if !step.contains('/') => (range, parse_step(step)?),
+        _ => (part, 1),
+    };
+    let (from, to) = parse_range_part(range)?;
+    Ok(RangePart { from, to, step })
+}
+
+fn parse_step(value: &str) -> Result<usize, String> {
+    let step: usize = value
+        .parse()
+        .map_err(|_| format!("Step `{value}` isn't a number"))?;
+    if step < 1 {
+        return Err("The step must be at least 1".to_owned());
+    }
+    Ok(step)
+}
+
+/// Split a row specification at the commas that lie outside patterns.
+fn split_row_parts(input: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0usize;
+    let mut inside_pattern = false;
+    let mut escaped = false;
+
+    for (offset, character) in input.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if inside_pattern => escaped = true,
+            '/' => inside_pattern = !inside_pattern,
+            ',' if !inside_pattern => {
+                parts.push(&input[start..offset]);
+                start = offset + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+
+    parts
+}
+
+/// Parse a single row bound, as `--from` and `--to` take it.
+fn parse_row_bound(input: &str) -> Result<RangeBound, String> {
+    match take_bound(input)? {
+        (Some(bound), "") => Ok(bound),
+        _ => Err(format!(
+            "Invalid row bound `{input}`, expected a row, ~N or /regex/"
+        )),
+    }
+}
+
+/// `--percentile 95:2`: the percentage, then the columns it reads.
+fn parse_percentile(input: &str) -> Result<Percentile, String> {
+    let invalid =
+        || format!("Invalid percentile `{input}`, expected <percent>:<columns>, e.g. 95:2");
+    let (rank, columns) = input
+        .split_once(':')
+        .ok_or_else(invalid)?;
+    let rank: f64 = rank.parse().map_err(|_| invalid())?;
+    if !(0.0..=100.0).contains(&rank) {
+        return Err(format!(
+            "Invalid percentile `{input}`, the percentage must lie between 0 and 100"
+        ));
+    }
+    Ok(Percentile {
+        rank,
+        columns: parse_column_list(columns)?,
+    })
+}
+
+/// Parse a sort key: a column list, optionally followed by `:` and the
+/// modifiers it sorts by — `n` numeric, `V` natural (digit runs compare
+/// as numbers, as in versions), `h` human-readable sizes, `f`
+/// case-folded, `r` descending. A key
+/// with modifiers takes none from `--numeric` or `--reverse`, as with
+/// `sort -k`.
+fn parse_sort_key(input: &str) -> Result<SortKey, String> {
+    let Some((columns, modifiers)) = input.rsplit_once(':') else {
+        return Ok(SortKey::from(parse_column_list(input)?));
+    };
+    //a key with modifiers leaves -n and the others behind, so a colon
+    //with none after it would drop them unseen
+    if modifiers.is_empty() {
+        return Err(format!(
+            "Invalid sort key `{input}`, expected modifiers after the `:`"
+        ));
+    }
+    let mut order = SortOrder::default();
+    for modifier in modifiers.chars() {
+        let compare = match modifier {
+            'n' => Compare::Numeric,
+            'V' => Compare::Natural,
+            'h' => Compare::Human,
+            'f' => {
+                order.collation = Collation::Fold;
+                continue;
+            }
+            'r' => {
+                order.reverse = true;
+                continue;
+            }
+            _ => {
+                return Err(format!(
+                    "Invalid sort key modifier `{modifier}` in `{input}`, expected n, V, h, f or r"
+                ));
+            }
+        };
+        if order.compare != Compare::Text && order.compare != compare {
+            return Err(format!(
+                "Invalid sort key `{input}`, n, V and h are different orders"
+            ));
+        }
+        order.compare = compare;
+    }
+    Ok(SortKey {
+        columns: parse_column_list(columns)?,
+        order: Some(order),
+    })
+}
+
 /// Parse a column specification: a comma-separated list of range parts,
 /// kept in the order written so that reading operations can permute the
-/// columns. Columns have no end-relative (`~`) bounds: a line's length
-/// is known as it is read, but the parts must be comparable before that.
-fn parse_column_list(input: &str) -> Result<ColumnList, String> {
+/// columns. A `~N` bound counts from the end of each line (`~1` is its
+/// last column), so `~2-` is the last two columns however long the line.
+/// A part that does not start like a range (a digit, `-`, `~` or `/`)
+/// names a column instead, for `--header` or `--schema` to look up, or
+/// is a key path with `--jsonl` — so a name may contain a `-` of its own
+/// (`unit-price`), just not a `,`.
+pub fn parse_column_list(input: &str) -> Result<ColumnList, String> {
     let parts = input
         .split(',')
-        .map(parse_column_part)
+        .map(|part| match part.chars().next() {
+            Some(first) if !first.is_ascii_digit() && !"-~/".contains(first) => {
+                Ok(ColumnPart::Name(part.to_owned()))
+            }
+            _ => parse_column_part(part),
+        })
         .collect::<Result<Vec<_>, String>>()?;
-    Ok(ColumnList::new(parts))
+    Ok(ColumnList::with_names(parts))
 }
 
-fn parse_column_part(part: &str) -> Result<RangeInclusive<usize>, String> {
+fn parse_column_part(part: &str) -> Result<ColumnPart, String> {
     match parse_range_part(part)? {
-        (RangeBound::FromStart(from), RangeBound::FromStart(to)) => Ok(from..=to),
-        _ => Err("Columns do not support end-relative (~) values".to_owned()),
+        (RangeBound::FromStart(from), RangeBound::FromStart(to)) => {
+            Ok(ColumnPart::Range(from..=to))
+        }
+        (RangeBound::Pattern(_), _) | (_, RangeBound::Pattern(_)) => {
+            Err("Columns cannot be addressed by a /pattern/".to_owned())
+        }
+        (from, to) => Ok(ColumnPart::FromEnd(from, to)),
     }
 }
 
 /// Parse one range part: `<from>-<to>`, `<from>-` (to the end),
 /// `-<to>` (from 1) or a single number. A bound prefixed with `~`
-/// counts from the end of the input (`~1` is the last row).
+/// counts from the end of the input (`~1` is the last row); a bound
+/// written `/regex/` is a line matching it.
 fn parse_range_part(part: &str) -> Result<(RangeBound, RangeBound), String> {
+    if part.contains('/') {
+        return parse_pattern_part(part);
+    }
+
     let (from, to) = match *part
         .split('-')
         .collect::<Vec<&str>>()
@@ -440,7 +1105,7 @@ fn parse_range_part(part: &str) -> Result<(RangeBound, RangeBound), String> {
     {
         [single] => {
             let value = parse_bound(single)?;
-            (value, value)
+            (value.clone(), value)
         }
         [from, ""] => (parse_bound(from)?, RangeBound::FromStart(usize::MAX)),
         ["", to] => (RangeBound::FromStart(1), parse_bound(to)?),
@@ -450,7 +1115,7 @@ fn parse_range_part(part: &str) -> Result<(RangeBound, RangeBound), String> {
 
     //an inverted range is only detectable when both bounds count from
     //the same side; mixed bounds are checked once the input length is known
-    let inverted = match (from, to) {
+    let inverted = match (&from, &to) {
         (RangeBound::FromStart(a), RangeBound::FromStart(b)) => a > b,
         (RangeBound::FromEnd(a), RangeBound::FromEnd(b)) => a < b,
         _ => false,
@@ -462,6 +1127,166 @@ fn parse_range_part(part: &str) -> Result<(RangeBound, RangeBound), String> {
     Ok((from, to))
 }
 
+/// Parse a range part with a pattern in it. The pattern may contain a
+/// `-` of its own, so the part cannot simply be split on it: the bounds
+/// are read one after the other instead. An offset belongs to the
+/// pattern right before it, so `/END/-1` is the line before each `END`,
+/// not a range from `END` to row 1. A lone pattern is the lines
+/// matching it, like a lone number is that one row.
+fn parse_pattern_part(part: &str) -> Result<(RangeBound, RangeBound), String> {
+    let (from, rest) = take_bound(part)?;
+    match (from, rest.strip_prefix('-')) {
+        (Some(single), None) if rest.is_empty() => Ok((single.clone(), single)),
+        (from, Some(rest)) => match take_bound(rest)? {
+            (to, "") => Ok((
+                from.unwrap_or(RangeBound::FromStart(1)),
+                to.unwrap_or(RangeBound::FromStart(usize::MAX)),
+            )),
+            _ => Err(format!("Invalid range `{part}`, expected <from>-<to>")),
+        },
+        _ => Err(format!("Invalid range `{part}`, expected <from>-<to>")),
+    }
+}
+
+/// Read one bound off the front of `input`, returning it (`None` when
+/// it is left out, as in `-5`) and whatever follows it.
+fn take_bound(input: &str) -> Result<(Option<RangeBound>, &str), String> {
+    if input.starts_with('/') {
+        let (pattern, rest) = take_pattern(input)?;
+        let (offset, rest) = take_offset(rest)?;
+        return Ok((
+            Some(RangeBound::Pattern(PatternBound { pattern, offset })),
+            rest,
+        ));
+    }
+
+    let end = input.find('-').unwrap_or(input.len());
+    let (value, rest) = input.split_at(end);
+    match value {
+        "" => Ok((None, rest)),
+        value => Ok((Some(parse_bound(value)?), rest)),
+    }
+}
+
+/// Read a `/regex/` off the front of `input`. A `/` inside the pattern
+/// is written `\/`.
+fn take_pattern(input: &str) -> Result<(Regex, &str), String> {
+    let mut source = String::new();
+    let mut chars = input.char_indices().skip(1);
+
+    while let Some((offset, character)) = chars.next() {
+        match character {
+            '/' => {
+                let pattern = Regex::new(&source)
+                    .map_err(|e| format!("Invalid row pattern `{source}`: {e}"))?;
+                return Ok((pattern, &input[offset + 1..]));
+            }
+            '\\' => match chars.next() {
+                Some((_, '/')) => source.push('/'),
+                Some((_, escaped)) => {
+                    source.push('\\');
+                    source.push(escaped);
+                }
+                None => source.push('\\'),
+            },
+            other => source.push(other),
+        }
+    }
+
+    Err(format!(
+        "Unterminated row pattern `{input}`, expected /regex/"
+    ))
+}
+
+/// Read the offset shifting a pattern (`+2`, `-1`), if one follows it.
+/// A `-` not followed by a digit separates the bounds instead.
+fn take_offset(input: &str) -> Result<(isize, &str), String> {
+    let sign = match input.chars().next() {
+        Some('+') => 1,
+        Some('-') if input[1..].starts_with(|c: char| c.is_ascii_digit()) => -1,
+        _ => return Ok((0, input)),
+    };
+    let digits = &input[1..];
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let offset: isize = digits[..end]
+        .parse()
+        .map_err(|_| format!("Pattern offset `{input}` isn't a number"))?;
+    Ok((sign * offset, &digits[end..]))
+}
+
+/// Parse the `--fields` delimiter. A single space is awk's default
+/// field separator, and means what it does there: runs of whitespace.
+fn parse_field_delimiter(input: &str) -> Result<Delimiter, String> {
+    match input {
+        " " => Ok(Delimiter::Whitespace),
+        _ => parse_delimiter(input).map(Delimiter::Literal),
+    }
+}
+
+fn parse_fields_regex(input: &str) -> Result<Delimiter, String> {
+    let regex = Regex::new(input).map_err(|e| format!("Invalid field regex `{input}`: {e}"))?;
+    Ok(Delimiter::Pattern(regex))
+}
+
+fn parse_schema(input: &str) -> Result<Schema, String> {
+    Schema::load(Path::new(input))
+}
+
+fn parse_quoting(input: &str) -> Result<Quoting, String> {
+    match input {
+        "minimal" => Ok(Quoting::Minimal),
+        "always" => Ok(Quoting::Always),
+        "never" => Ok(Quoting::Never),
+        _ => Err(format!(
+            "Unknown quoting `{input}`, expected minimal, always or never"
+        )),
+    }
+}
+
+fn parse_collation(input: &str) -> Result<Collation, String> {
+    match input {
+        "fold" => Ok(Collation::Fold),
+        "accents" => Ok(Collation::Accents),
+        "uca" => Ok(Collation::Uca),
+        _ => Err(format!(
+            "Unknown collation `{input}`, expected fold, accents or uca"
+        )),
+    }
+}
+
+fn parse_output_format(input: &str) -> Result<OutputFormat, String> {
+    match input {
+        "json" => Ok(OutputFormat::Json),
+        "jsonl" => Ok(OutputFormat::Jsonl),
+        "markdown" => Ok(OutputFormat::Markdown),
+        "html" => Ok(OutputFormat::Html),
+        "tsv" => Ok(OutputFormat::Tsv),
+        "csv" => Ok(OutputFormat::Csv),
+        "table" => Ok(OutputFormat::Table),
+        _ => Err(format!(
+            "Unknown output format `{input}`, expected json, jsonl, markdown, html, tsv, csv or table"
+        )),
+    }
+}
+
+/// Parse a HyperLogLog precision: the bits of each hash that pick its
+/// register.
+fn parse_precision(input: &str) -> Result<u8, String> {
+    input
+        .parse()
+        .ok()
+        .filter(|precision| PRECISIONS.contains(precision))
+        .ok_or_else(|| {
+            format!(
+                "Invalid precision `{input}`, expected a number from {} to {}",
+                PRECISIONS.start(),
+                PRECISIONS.end()
+            )
+        })
+}
+
 fn parse_delimiter(input: &str) -> Result<String, String> {
     if input.is_empty() {
         return Err("The field delimiter cannot be empty".to_owned());
@@ -490,6 +1315,67 @@ fn parse_width(input: &str) -> Result<usize, String> {
     Ok(width)
 }
 
+/// Parse a `--top` count: a top of none would keep no rows at all.
+fn parse_top(input: &str) -> Result<usize, String> {
+    match input.parse::<usize>() {
+        Ok(count) if count >= 1 => Ok(count),
+        _ => Err(format!(
+            "Invalid top `{input}`, expected a number of at least 1"
+        )),
+    }
+}
+
+/// Parse a sort's memory budget: a number of bytes, or a size such as
+/// `1G` with the suffixes `--human-numeric-sort` reads.
+fn parse_buffer_size(input: &str) -> Result<usize, String> {
+    match human_size(input) {
+        Some(bytes) if bytes >= 1.0 && bytes <= usize::MAX as f64 => Ok(bytes as usize),
+        _ => Err(format!(
+            "Invalid buffer size `{input}`, expected a size of at least 1 byte, e.g. 512M or 1G"
+        )),
+    }
+}
+
+/// Parse a window size: a window of no numbers has no statistic.
+fn parse_window(input: &str) -> Result<usize, String> {
+    let size: usize = input
+        .parse()
+        .map_err(|_| format!("Window `{input}` isn't a number"))?;
+    if size < 1 {
+        return Err("The window must hold at least 1 number".to_owned());
+    }
+    Ok(size)
+}
+
+/// Parse a number of histogram bins: each one is a row of the table.
+fn parse_bins(input: &str) -> Result<usize, String> {
+    match input.parse::<usize>() {
+        Ok(bins) if (1..=MAX_BINS).contains(&bins) => Ok(bins),
+        _ => Err(format!(
+            "Invalid bins `{input}`, expected a number from 1 to {MAX_BINS}"
+        )),
+    }
+}
+
+/// Parse a histogram bin width: a number above zero, or there would be
+/// no end of bins.
+fn parse_bin_width(input: &str) -> Result<f64, String> {
+    match input.parse::<f64>() {
+        Ok(width) if width > 0.0 && width.is_finite() => Ok(width),
+        _ => Err(format!(
+            "Invalid bin width `{input}`, expected a number above 0"
+        )),
+    }
+}
+
+/// Parse a number of context rows; 0 is fine, and still separates
+/// the groups of matches.
+fn parse_context(input: &str) -> Result<usize, String> {
+    input
+        .parse()
+        .map_err(|_| format!("Context `{input}` isn't a number"))
+}
+
 fn parse_bound(value: &str) -> Result<RangeBound, String> {
     let (make_bound, digits): (fn(usize) -> RangeBound, &str) = match value.strip_prefix('~') {
         Some(rest) => (RangeBound::FromEnd, rest),
@@ -600,6 +1486,211 @@ mod tests {
         assert!(parse_row_ranges("1-2,").is_err());
     }
 
+    fn stepped(from: RangeBound, to: RangeBound, step: usize) -> RangePart {
+        RangePart { from, to, step }
+    }
+
+    #[test]
+    fn parses_stepped_ranges() {
+        assert_eq!(
+            parse_row_part("2-100:5").unwrap(),
+            stepped(FromStart(2), FromStart(100), 5)
+        );
+        assert_eq!(
+            parse_row_part("~10-~1:2").unwrap(),
+            stepped(FromEnd(10), FromEnd(1), 2)
+        );
+        assert_eq!(
+            parse_row_part("3-").unwrap(),
+            stepped(FromStart(3), FromStart(usize::MAX), 1)
+        );
+    }
+
+    #[test]
+    fn parses_sed_style_steps() {
+        assert_eq!(
+            parse_row_part("1~2").unwrap(),
+            stepped(FromStart(1), FromStart(usize::MAX), 2)
+        );
+        //a first row of 0 only sets where the counting starts
+        let spec = parse_row_ranges("0~4").unwrap();
+        let rows = spec.resolve(13);
+        let selected: Vec<usize> = (1..=13)
+            .filter(|row| rows.contains(*row))
+            .collect();
+        assert_eq!(selected, [4, 8, 12]);
+        //a lone ~N still counts from the end
+        assert_eq!(
+            parse_row_part("~3").unwrap(),
+            stepped(FromEnd(3), FromEnd(3), 1)
+        );
+    }
+
+    #[test]
+    fn a_colon_inside_a_pattern_is_no_step() {
+        let part = parse_row_part("/a:5/").unwrap();
+        assert_eq!(part.step, 1);
+        assert_eq!(part.from, pattern("a:5", 0));
+
+        let part = parse_row_part("/a:5/-/b/:3").unwrap();
+        assert_eq!(part.step, 3);
+    }
+
+    #[test]
+    fn rejects_invalid_steps() {
+        assert!(parse_row_part("1-10:0").is_err());
+        assert!(parse_row_part("1~0").is_err());
+        assert!(parse_row_part("1-10:").is_err());
+        assert!(parse_row_part("1-10:x").is_err());
+        assert!(parse_row_part("1~2:3").is_err());
+        assert!(parse_row_part("10-1:2").is_err());
+        //columns are not stepped
+        assert!(parse_column_list("1-10:2").is_err());
+        assert!(parse_column_list("1~2").is_err());
+    }
+
+    fn pattern(source: &str, offset: isize) -> RangeBound {
+        RangeBound::Pattern(PatternBound {
+            pattern: Regex::new(source).unwrap(),
+            offset,
+        })
+    }
+
+    #[test]
+    fn parses_pattern_bounds() {
+        assert_eq!(
+            parse_range_part("/BEGIN/-/END/").unwrap(),
+            (pattern("BEGIN", 0), pattern("END", 0))
+        );
+        //a lone pattern is the lines matching it
+        assert_eq!(
+            parse_range_part("/x/").unwrap(),
+            (pattern("x", 0), pattern("x", 0))
+        );
+        //open on either side, or mixed with a position
+        assert_eq!(
+            parse_range_part("/BEGIN/-").unwrap(),
+            (pattern("BEGIN", 0), FromStart(usize::MAX))
+        );
+        assert_eq!(
+            parse_range_part("-/END/").unwrap(),
+            (FromStart(1), pattern("END", 0))
+        );
+        assert_eq!(
+            parse_range_part("3-/END/").unwrap(),
+            (FromStart(3), pattern("END", 0))
+        );
+    }
+
+    #[test]
+    fn parses_pattern_offsets() {
+        assert_eq!(
+            parse_range_part("/BEGIN/+1-/END/-1").unwrap(),
+            (pattern("BEGIN", 1), pattern("END", -1))
+        );
+        //the offset belongs to the pattern, it is not a range to row 2
+        assert_eq!(
+            parse_range_part("/x/-2").unwrap(),
+            (pattern("x", -2), pattern("x", -2))
+        );
+    }
+
+    #[test]
+    fn a_pattern_may_contain_the_separators() {
+        //`-` and `,` inside the slashes belong to the pattern, and `\/`
+        //is a slash
+        assert_eq!(
+            parse_range_part("/a-b/-/c\\/d/").unwrap(),
+            (pattern("a-b", 0), pattern("c/d", 0))
+        );
+        let spec = parse_row_ranges("/a{1,2}/,3").unwrap();
+        assert_eq!(
+            spec,
+            RangeSpec::new(vec![
+                (pattern("a{1,2}", 0), pattern("a{1,2}", 0)),
+                (FromStart(3), FromStart(3)),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_patterns() {
+        assert!(parse_range_part("/unterminated").is_err());
+        assert!(parse_range_part("/[/").is_err());
+        assert!(parse_range_part("/a/+").is_err());
+        assert!(parse_range_part("/a/x").is_err());
+        assert!(parse_range_part("/a/-/b/-/c/").is_err());
+    }
+
+    #[test]
+    fn parses_a_single_row_bound() {
+        assert_eq!(parse_row_bound("/END/-1").unwrap(), pattern("END", -1));
+        assert_eq!(parse_row_bound("~3").unwrap(), FromEnd(3));
+        assert!(parse_row_bound("1-2").is_err());
+    }
+
+    #[test]
+    fn a_single_space_delimiter_splits_on_whitespace() {
+        assert_eq!(parse_field_delimiter(" ").unwrap(), Delimiter::Whitespace);
+        assert_eq!(parse_field_delimiter("  ").unwrap(), Delimiter::from("  "));
+        assert_eq!(parse_field_delimiter(",").unwrap(), Delimiter::from(","));
+        assert!(parse_field_delimiter("").is_err());
+    }
+
+    #[test]
+    fn fields_regex_must_compile() {
+        assert!(parse_fields_regex("[;,]").is_ok());
+        assert!(parse_fields_regex("[").is_err());
+    }
+
+    #[test]
+    fn fields_regex_conflicts_with_fields() {
+        assert!(
+            cli()
+                .try_get_matches_from(["ft", "-F", ",", "--fields-regex", ";", "-C", "1", "x"])
+                .is_err()
+        );
+        //and refines like it
+        assert!(
+            cli()
+                .try_get_matches_from(["ft", "--fields-regex", ";", "-C", "1", "--quoted", "x"])
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn quoting_names_a_style() {
+        assert_eq!(parse_quoting("minimal").unwrap(), Quoting::Minimal);
+        assert_eq!(parse_quoting("always").unwrap(), Quoting::Always);
+        assert_eq!(parse_quoting("never").unwrap(), Quoting::Never);
+        assert!(parse_quoting("some").is_err());
+    }
+
+    #[test]
+    fn unquote_requires_quoted_and_conflicts_with_quote() {
+        assert!(
+            cli()
+                .try_get_matches_from(["ft", "-F", ",", "-C", "1", "--unquote", "x"])
+                .is_err()
+        );
+        assert!(
+            cli()
+                .try_get_matches_from([
+                    "ft",
+                    "-F",
+                    ",",
+                    "-C",
+                    "1",
+                    "--quoted",
+                    "--unquote",
+                    "--quote",
+                    "always",
+                    "x"
+                ])
+                .is_err()
+        );
+    }
+
     #[test]
     fn column_list_parses_a_single_range() {
         assert_eq!(
@@ -618,14 +1709,62 @@ mod tests {
     }
 
     #[test]
-    fn column_list_rejects_end_relative_bounds() {
-        assert!(parse_column_list("~5").is_err());
-        assert!(parse_column_list("1-~2").is_err());
-        assert!(parse_column_list("1,~2").is_err());
+    fn column_list_takes_end_relative_bounds() {
+        assert_eq!(
+            parse_column_list("1,~2-").unwrap(),
+            ColumnList::with_names(vec![
+                ColumnPart::Range(1..=1),
+                ColumnPart::FromEnd(FromEnd(2), FromStart(usize::MAX)),
+            ])
+        );
+        assert_eq!(
+            parse_column_list("2-~2").unwrap(),
+            ColumnList::with_names(vec![ColumnPart::FromEnd(FromStart(2), FromEnd(2))])
+        );
+        assert!(parse_column_list("~1-~2").is_err());
+    }
+
+    #[test]
+    fn column_list_rejects_patterns() {
+        assert!(parse_column_list("/x/").is_err());
     }
 
     #[test]
     fn column_list_rejects_an_empty_part() {
         assert!(parse_column_list("1,").is_err());
     }
+
+    #[test]
+    fn column_list_takes_names_for_the_header() {
+        let list = parse_column_list("price,2,unit-price").unwrap();
+        assert_eq!(
+            list,
+            ColumnList::with_names(vec![
+                ColumnPart::Name("price".to_owned()),
+                ColumnPart::Range(2..=2),
+                ColumnPart::Name("unit-price".to_owned()),
+            ])
+        );
+        //a part starting like a range is still read as one
+        assert!(parse_column_list("2x").is_err());
+    }
+
+    #[test]
+    fn sort_key_takes_modifiers_after_a_colon() {
+        let key = parse_sort_key("2-3:nr").unwrap();
+        assert_eq!(key.columns, ColumnList::from(2..=3));
+        assert_eq!(
+            key.order,
+            Some(SortOrder {
+                compare: Compare::Numeric,
+                collation: Collation::Bytes,
+                reverse: true,
+            })
+        );
+        //without them, the key sorts as --numeric and --reverse say
+        assert_eq!(parse_sort_key("name").unwrap().order, None);
+        assert!(parse_sort_key("2:x").is_err());
+        assert!(parse_sort_key("2:nV").is_err());
+        assert!(parse_sort_key("2:").is_err());
+    }
 }
@@ -1,16 +1,25 @@
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 
 use clap::ArgMatches;
 use regex::{Regex, RegexBuilder};
 
 use super::ConfigError;
-use crate::columns::{ColumnList, ColumnSpan, FieldSpan};
-use crate::ranges::RangeSpec;
+use super::cli::parse_column_list;
+use crate::collate::{Collation, Compare, SortOrder};
+use crate::columns::{
+    ColumnList, ColumnSpan, Delimiter, FieldSpan, MemberSpan, Quoting, field_values,
+};
+use crate::format::OutputFormat;
+use crate::ranges::{RangeBound, RangeSpec};
+use crate::reduce::Binning;
+use crate::schema::Schema;
+use crate::text;
 
 /// What `--find` matches: a literal substring, or a regular expression
 /// when `--regex` is given. The regex is compiled (and therefore
 /// validated) while building the `Config`.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum FindPattern {
     Literal(String),
     Regex(Regex),
@@ -19,7 +28,7 @@ pub enum FindPattern {
 /// One `--find`/`--replace` pair. Pairing the two arguments up while
 /// building the `Config` makes their positional correspondence
 /// structural instead of an invariant to re-validate downstream.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Replacement {
     pub find: FindPattern,
     pub replace: String,
@@ -59,20 +68,82 @@ impl Input {
 /// key. One struct rather than loose flags, so the summary options
 /// travel together.
 #[cfg_attr(test, derive(Default))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SummarySpec {
     pub count: bool,
     pub sum: Option<ColumnList>,
     pub avg: Option<ColumnList>,
     pub min: Option<ColumnList>,
     pub max: Option<ColumnList>,
+    pub stddev: Option<ColumnList>,
+    pub var: Option<ColumnList>,
+    pub skew: Option<ColumnList>,
+    //divide by n rather than n - 1 (`--population`)
+    pub population: bool,
+    pub median: Option<ColumnList>,
+    pub percentiles: Vec<Percentile>,
+    //estimate the median, percentiles and distinct counts from a sketch
+    //(`--approximate`)
+    pub approximate: bool,
+    //the HyperLogLog's precision for an estimated distinct count
+    //(`--precision`)
+    pub precision: Option<u8>,
+    pub distinct: Option<ColumnList>,
+    pub first: Option<ColumnList>,
+    pub last: Option<ColumnList>,
+    pub mode: Option<ColumnList>,
+    pub collect: Option<Collect>,
     pub group_by: Option<ColumnList>,
 }
 
+/// One `--sort-key`: the columns it reads, and how they compare when
+/// given modifiers of their own (`None` sorts as `--numeric` and
+/// `--reverse` say).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub columns: ColumnList,
+    pub order: Option<SortOrder>,
+}
+
+impl From<ColumnList> for SortKey {
+    fn from(columns: ColumnList) -> SortKey {
+        SortKey {
+            columns,
+            order: None,
+        }
+    }
+}
+
+/// One `--percentile`: the percentage of the numbers (0 to 100) the value
+/// lies above, and the columns the numbers are read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Percentile {
+    pub rank: f64,
+    pub columns: ColumnList,
+}
+
+/// The frequency table asked for instead of the processed rows
+/// (`--histogram`): the columns tabulated, how their numbers are binned
+/// if at all (`--bins`, `--bin-width`), and whether to draw bars (`--bar`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSpec {
+    pub columns: ColumnList,
+    pub binning: Option<Binning>,
+    pub bar: bool,
+}
+
+/// `--collect`: the columns whose values are gathered, and the separator
+/// they are joined with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collect {
+    pub columns: ColumnList,
+    pub separator: String,
+}
+
 /// How `--in-place` edits the input files. Present only when
 /// `--in-place` was given, so a `--backup` suffix or `--dry-run`
 /// without it is unrepresentable rather than merely rejected.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InPlace {
     /// `Some` keeps a copy of each edited file, named after it plus
     /// this suffix (`--backup`).
@@ -84,21 +155,39 @@ pub struct InPlace {
 //the derived Default (no ranges, no operations, stdin to stdout) is a
 //test-only base configuration; real configs always come from TryFrom
 #[cfg_attr(test, derive(Default))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Config {
     pub rows: Option<RangeSpec>,
     pub cols: Option<ColumnList>,
-    //per-operation column ranges: `None` falls back to `cols`, so an
-    //operation only needs its own range when it must differ from it
-    pub sort_key: Option<ColumnList>,
+    //per-operation column ranges: `None` (or no `--sort-key` at all)
+    //falls back to `cols`, so an operation only needs its own range when
+    //it must differ from it
+    pub sort_keys: Vec<SortKey>,
     pub unique_key: Option<ColumnList>,
     //`Some` switches the column ranges from counting chars to
-    //delimited fields
-    pub field_delimiter: Option<String>,
+    //delimited fields (`--fields` or `--fields-regex`)
+    pub field_delimiter: Option<Delimiter>,
     //`Some` joins selected fields with something else than the input
     //delimiter (`--output-delimiter`)
     pub output_delimiter: Option<String>,
+    //every line is a JSON object, whose members the column lists
+    //address (`--jsonl`)
+    pub jsonl: bool,
+    //`Some` writes the rows in another format (`--output-format`)
+    pub output_format: Option<OutputFormat>,
     pub reorder: Option<ReorderMode>,
+    //`Some` compares sort keys and `--min`/`--max` values as versions or
+    //sizes (`--version-sort`, `--human-numeric-sort`)
+    pub compare: Option<Compare>,
+    //how sort keys and `--unique` keys compare as text (`--collate`)
+    pub collation: Collation,
+    //keep only the first this many sorted rows, per `summary.group_by`
+    //key (`--top`)
+    pub top: Option<usize>,
+    //sort in runs of about this many bytes spilled to disk, in this
+    //directory or else the system's (`--buffer-size`, `--temp-dir`)
+    pub buffer_size: Option<usize>,
+    pub temp_dir: Option<PathBuf>,
     pub delete: bool,
     pub ignore_case: bool,
     pub upper: bool,
@@ -108,8 +197,34 @@ pub struct Config {
     pub squeeze: bool,
     //number the output rows, like `nl`
     pub number: bool,
+    //append a running total, a difference from the previous row, or a
+    //rank, restarting per `summary.group_by` key (`--cumsum`, `--delta`,
+    //`--rank`)
+    pub cumsum: Option<ColumnList>,
+    pub delta: Option<ColumnList>,
+    pub rank: Option<ColumnList>,
+    //append a statistic of the last `window` numbers (`--moving-avg`,
+    //`--moving-min`, `--moving-max`, `--window`)
+    pub moving_avg: Option<ColumnList>,
+    pub moving_min: Option<ColumnList>,
+    pub moving_max: Option<ColumnList>,
+    pub window: Option<usize>,
+    pub histogram: Option<HistogramSpec>,
     //--fields splits on the delimiter even inside quotes unless set
     pub quoted: bool,
+    //`Some` writes the fields as CSV values, quoted in this style
+    //(`--quote`, or `--unquote` for never)
+    pub quoting: Option<Quoting>,
+    //the first row names the fields (`--header`): it passes through, and
+    //the column lists may name columns, resolved per input with
+    //`with_header`
+    pub header: bool,
+    //a fixed-width record layout (`--schema`): the column lists may name
+    //its fields, resolved as the config is built
+    pub schema: Option<Schema>,
+    //`Some` turns the fixed-width records into fields joined by this,
+    //laid out by the schema (`--delimit`)
+    pub delimit: Option<String>,
     //`Some` splits every line at each occurrence of the separator
     pub split_on: Option<String>,
     //`Some` wraps every line into chunks of that many chars
@@ -121,6 +236,10 @@ pub struct Config {
     pub summary: SummarySpec,
     pub grep: Option<Regex>,
     pub invert: bool,
+    //rows around each --grep match taken along with it; `Some` even
+    //when 0, which still separates the groups of matches
+    pub before_context: Option<usize>,
+    pub after_context: Option<usize>,
     //answer only whether anything matched: write nothing, and say it
     //with the exit code
     pub quiet: bool,
@@ -150,8 +269,9 @@ impl Config {
             .unwrap_or_else(ColumnList::full)
     }
 
-    /// How the column range addresses lines: char positions, or fields
-    /// separated by a delimiter when `--fields` was given.
+    /// How the column range addresses lines: char positions, fields
+    /// separated by a delimiter when `--fields` was given, or members of
+    /// a JSON object with `--jsonl`.
     pub fn col_span(&self) -> ColumnSpan {
         self.span_of(None)
     }
@@ -170,26 +290,183 @@ impl Config {
     pub fn output_separator(&self) -> String {
         self.output_delimiter
             .clone()
-            .or_else(|| self.field_delimiter.clone())
+            .or_else(|| {
+                self.field_delimiter
+                    .as_ref()
+                    .map(|delimiter| delimiter.joiner().to_owned())
+            })
             .unwrap_or_else(|| "\t".to_owned())
     }
 
     /// Turn a column list into a span, falling back to `--cols` (and
     /// then to every column) when the operation has no list of its own.
-    /// `--fields` applies to every column list alike.
+    /// `--fields` and `--jsonl` apply to every column list alike.
     fn span_of(&self, columns: Option<ColumnList>) -> ColumnSpan {
         let columns = columns.unwrap_or_else(|| self.cols_or_full());
+        if self.jsonl {
+            return ColumnSpan::Members(MemberSpan {
+                output_delimiter: self.output_delimiter.clone(),
+                members: columns,
+            });
+        }
         match &self.field_delimiter {
             Some(delimiter) => ColumnSpan::Fields(FieldSpan {
                 delimiter: delimiter.clone(),
                 output_delimiter: self.output_delimiter.clone(),
                 quoted: self.quoted,
+                quoting: self.quoting,
                 fields: columns,
             }),
             None => ColumnSpan::Chars(columns),
         }
     }
 
+    /// This configuration with the column names resolved against the
+    /// header row of one input: a named part becomes the position of the
+    /// field of that name.
+    pub fn with_header(&self, header: &str) -> Result<Config, ConfigError> {
+        let mut config = self.clone();
+        let (header, _) = text::split_line_terminator(header);
+        //validated: only field mode names columns
+        let Some(delimiter) = &self.field_delimiter else {
+            return Ok(config);
+        };
+        let names = field_values(header, delimiter, self.quoted);
+
+        //a name stands for the first field bearing it
+        config.resolve_names(
+            |name| {
+                names
+                    .iter()
+                    .position(|field| field == name)
+                    .map(|index| index + 1..=index + 1)
+            },
+            ConfigError::UnknownColumn,
+        )?;
+        Ok(config)
+    }
+
+    /// Resolve the names in every column list through `lookup`, failing
+    /// on the first name it does not know.
+    fn resolve_names<F>(
+        &mut self,
+        lookup: F,
+        unknown: fn(String) -> ConfigError,
+    ) -> Result<(), ConfigError>
+    where
+        F: Fn(&str) -> Option<RangeInclusive<usize>>,
+    {
+        for list in self.column_lists_mut() {
+            *list = list.resolve(&lookup).map_err(unknown)?;
+        }
+        Ok(())
+    }
+
+    /// Every column list the options were given.
+    fn column_lists(&self) -> Vec<&ColumnList> {
+        let summary = &self.summary;
+        [
+            &self.cols,
+            &self.unique_key,
+            &self.cumsum,
+            &self.delta,
+            &self.rank,
+            &self.moving_avg,
+            &self.moving_min,
+            &self.moving_max,
+            &summary.sum,
+            &summary.avg,
+            &summary.min,
+            &summary.max,
+            &summary.stddev,
+            &summary.var,
+            &summary.skew,
+            &summary.median,
+            &summary.distinct,
+            &summary.first,
+            &summary.last,
+            &summary.mode,
+            &summary.group_by,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(
+            self.sort_keys
+                .iter()
+                .map(|key| &key.columns),
+        )
+        .chain(
+            summary
+                .percentiles
+                .iter()
+                .map(|percentile| &percentile.columns),
+        )
+        .chain(
+            summary
+                .collect
+                .iter()
+                .map(|collect| &collect.columns),
+        )
+        .chain(
+            self.histogram
+                .iter()
+                .map(|histogram| &histogram.columns),
+        )
+        .collect()
+    }
+
+    fn column_lists_mut(&mut self) -> Vec<&mut ColumnList> {
+        let summary = &mut self.summary;
+        [
+            &mut self.cols,
+            &mut self.unique_key,
+            &mut self.cumsum,
+            &mut self.delta,
+            &mut self.rank,
+            &mut self.moving_avg,
+            &mut self.moving_min,
+            &mut self.moving_max,
+            &mut summary.sum,
+            &mut summary.avg,
+            &mut summary.min,
+            &mut summary.max,
+            &mut summary.stddev,
+            &mut summary.var,
+            &mut summary.skew,
+            &mut summary.median,
+            &mut summary.distinct,
+            &mut summary.first,
+            &mut summary.last,
+            &mut summary.mode,
+            &mut summary.group_by,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(
+            self.sort_keys
+                .iter_mut()
+                .map(|key| &mut key.columns),
+        )
+        .chain(
+            summary
+                .percentiles
+                .iter_mut()
+                .map(|percentile| &mut percentile.columns),
+        )
+        .chain(
+            summary
+                .collect
+                .iter_mut()
+                .map(|collect| &mut collect.columns),
+        )
+        .chain(
+            self.histogram
+                .iter_mut()
+                .map(|histogram| &mut histogram.columns),
+        )
+        .collect()
+    }
+
     /// The files among the inputs, in order. With `--in-place` these are
     /// all of them (validated), and each is edited on its own.
     pub fn input_files(&self) -> impl Iterator<Item = &Path> {
@@ -234,6 +511,18 @@ impl TryFrom<ArgMatches> for Config {
                 }
             })
             .collect::<Result<Vec<FindPattern>, ConfigError>>()?;
+        let collect = match matches.get_many::<String>("collect") {
+            Some(mut values) => {
+                let (Some(columns), Some(separator)) = (values.next(), values.next()) else {
+                    unreachable!("--collect takes exactly two values")
+                };
+                Some(Collect {
+                    columns: parse_column_list(columns).map_err(ConfigError::InvalidColumns)?,
+                    separator: separator.clone(),
+                })
+            }
+            None => None,
+        };
         let replace_strings: Vec<String> = matches
             .get_many::<String>("replace")
             .into_iter()
@@ -273,26 +562,42 @@ impl TryFrom<ArgMatches> for Config {
             None
         };
 
-        let config = Config {
-            rows: matches
-                .get_one::<RangeSpec>("rows")
-                .cloned(),
+        let mut config = Config {
+            rows: rows(&matches),
             cols: matches
                 .get_one::<ColumnList>("columns")
                 .cloned(),
-            sort_key: matches
-                .get_one::<ColumnList>("sort-key")
-                .cloned(),
+            sort_keys: matches
+                .get_many::<SortKey>("sort-key")
+                .unwrap_or_default()
+                .cloned()
+                .collect(),
             unique_key: matches
                 .get_one::<ColumnList>("unique-key")
                 .cloned(),
             field_delimiter: matches
-                .get_one::<String>("fields")
+                .get_one::<Delimiter>("fields")
+                .or_else(|| matches.get_one::<Delimiter>("fields-regex"))
                 .cloned(),
             output_delimiter: matches
                 .get_one::<String>("output-delimiter")
                 .cloned(),
+            jsonl: matches.get_flag("jsonl"),
+            output_format: matches
+                .get_one::<OutputFormat>("output-format")
+                .copied(),
             reorder,
+            collation: matches
+                .get_one::<Collation>("collate")
+                .copied()
+                .unwrap_or_default(),
+            compare: if matches.get_flag("version-sort") {
+                Some(Compare::Natural)
+            } else if matches.get_flag("human-numeric-sort") {
+                Some(Compare::Human)
+            } else {
+                None
+            },
             delete: matches.get_flag("delete"),
             ignore_case,
             upper: matches.get_flag("upper"),
@@ -301,7 +606,62 @@ impl TryFrom<ArgMatches> for Config {
             title_case: matches.get_flag("title-case"),
             squeeze: matches.get_flag("squeeze"),
             number: matches.get_flag("number"),
+            cumsum: matches
+                .get_one::<ColumnList>("cumsum")
+                .cloned(),
+            delta: matches
+                .get_one::<ColumnList>("delta")
+                .cloned(),
+            rank: matches
+                .get_one::<ColumnList>("rank")
+                .cloned(),
+            moving_avg: matches
+                .get_one::<ColumnList>("moving-avg")
+                .cloned(),
+            moving_min: matches
+                .get_one::<ColumnList>("moving-min")
+                .cloned(),
+            moving_max: matches
+                .get_one::<ColumnList>("moving-max")
+                .cloned(),
+            window: matches
+                .get_one::<usize>("window")
+                .copied(),
+            top: matches.get_one::<usize>("top").copied(),
+            buffer_size: matches
+                .get_one::<usize>("buffer-size")
+                .copied(),
+            temp_dir: matches
+                .get_one::<String>("temp-dir")
+                .map(PathBuf::from),
+            histogram: matches
+                .get_one::<ColumnList>("histogram")
+                .map(|columns| HistogramSpec {
+                    columns: columns.clone(),
+                    binning: matches
+                        .get_one::<usize>("bins")
+                        .map(|bins| Binning::Count(*bins))
+                        .or_else(|| {
+                            matches
+                                .get_one::<f64>("bin-width")
+                                .map(|width| Binning::Width(*width))
+                        }),
+                    bar: matches.get_flag("bar"),
+                }),
             quoted: matches.get_flag("quoted"),
+            quoting: match matches.get_flag("unquote") {
+                true => Some(Quoting::Never),
+                false => matches
+                    .get_one::<Quoting>("quote")
+                    .copied(),
+            },
+            header: matches.get_flag("header"),
+            schema: matches
+                .get_one::<Schema>("schema")
+                .cloned(),
+            delimit: matches
+                .get_one::<String>("delimit")
+                .cloned(),
             split_on: matches
                 .get_one::<String>("split-on")
                 .cloned(),
@@ -326,12 +686,51 @@ impl TryFrom<ArgMatches> for Config {
                 max: matches
                     .get_one::<ColumnList>("max")
                     .cloned(),
+                stddev: matches
+                    .get_one::<ColumnList>("stddev")
+                    .cloned(),
+                var: matches
+                    .get_one::<ColumnList>("var")
+                    .cloned(),
+                skew: matches
+                    .get_one::<ColumnList>("skew")
+                    .cloned(),
+                population: matches.get_flag("population"),
+                median: matches
+                    .get_one::<ColumnList>("median")
+                    .cloned(),
+                percentiles: matches
+                    .get_many::<Percentile>("percentile")
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect(),
+                approximate: matches.get_flag("approximate"),
+                precision: matches
+                    .get_one::<u8>("precision")
+                    .copied(),
+                distinct: matches
+                    .get_one::<ColumnList>("distinct")
+                    .cloned(),
+                first: matches
+                    .get_one::<ColumnList>("first")
+                    .cloned(),
+                last: matches
+                    .get_one::<ColumnList>("last")
+                    .cloned(),
+                mode: matches
+                    .get_one::<ColumnList>("mode")
+                    .cloned(),
+                collect,
                 group_by: matches
                     .get_one::<ColumnList>("group-by")
                     .cloned(),
             },
             grep,
             invert: matches.get_flag("invert"),
+            //like grep, -A and -B win over --context for their own side
+            before_context: context(&matches, "before-context"),
+            after_context: context(&matches, "after-context"),
             quiet: matches.get_flag("quiet"),
             unique: matches.get_flag("unique"),
             inputs: inputs(&matches),
@@ -366,6 +765,23 @@ impl TryFrom<ArgMatches> for Config {
             return Err(ConfigError::DeleteWithReorder);
         }
 
+        //a schema is known up front, so its names resolve right away
+        if let Some(schema) = config.schema.clone() {
+            config.resolve_names(|name| schema.lookup(name), ConfigError::UnknownSchemaField)?;
+        }
+
+        //other names are looked up in the fields of the header row, so
+        //without one they name nothing — unless they are key paths
+        if (!config.header || config.field_delimiter.is_none())
+            && !config.jsonl
+            && config
+                .column_lists()
+                .into_iter()
+                .any(ColumnList::has_names)
+        {
+            return Err(ConfigError::ColumnNamesWithoutHeader);
+        }
+
         if config.ignore_case && config.replacements.is_empty() && config.grep.is_none() {
             return Err(ConfigError::IgnoreCaseWithoutPattern);
         }
@@ -385,6 +801,35 @@ impl TryFrom<ArgMatches> for Config {
     }
 }
 
+/// The rows to process: `--rows`, or the single part `--from` and
+/// `--to` spell out — from row 1 and to the end unless given.
+fn rows(matches: &ArgMatches) -> Option<RangeSpec> {
+    if let Some(rows) = matches.get_one::<RangeSpec>("rows") {
+        return Some(rows.clone());
+    }
+
+    let from = matches.get_one::<RangeBound>("from");
+    let to = matches.get_one::<RangeBound>("to");
+    if from.is_none() && to.is_none() {
+        return None;
+    }
+    Some(RangeSpec::new(vec![(
+        from.cloned()
+            .unwrap_or(RangeBound::FromStart(1)),
+        to.cloned()
+            .unwrap_or(RangeBound::FromStart(usize::MAX)),
+    )]))
+}
+
+/// The context rows on one side of a match: its own argument, or else
+/// `--context`.
+fn context(matches: &ArgMatches, side: &str) -> Option<usize> {
+    matches
+        .get_one::<usize>(side)
+        .or_else(|| matches.get_one::<usize>("context"))
+        .copied()
+}
+
 /// The inputs named on the command line, in order. No argument (or a
 /// lone `-`) means standard input, so the list is never empty.
 fn inputs(matches: &ArgMatches) -> Vec<Input> {
@@ -423,6 +868,7 @@ mod tests {
         match span {
             ColumnSpan::Chars(list) => list.written().to_vec(),
             ColumnSpan::Fields(spec) => spec.fields.written().to_vec(),
+            ColumnSpan::Members(spec) => spec.members.written().to_vec(),
         }
     }
 
@@ -518,6 +964,32 @@ mod tests {
         assert!(config_from(&["ft", "-i", "a.txt", "b.txt"]).is_ok());
     }
 
+    #[test]
+    fn from_and_to_spell_out_one_row_part() {
+        let config = config_from(&["ft", "--from", "/BEGIN/", "--to", "/END/", "a.txt"]).unwrap();
+        let spec = cli()
+            .try_get_matches_from(["ft", "-R", "/BEGIN/-/END/"])
+            .unwrap()
+            .get_one::<RangeSpec>("rows")
+            .cloned();
+        assert_eq!(config.rows, spec);
+
+        //either may be left out: from row 1, or to the end
+        let config = config_from(&["ft", "--from", "3", "a.txt"]).unwrap();
+        assert_eq!(config.rows, Some(RangeSpec::from(3..=usize::MAX)));
+        let config = config_from(&["ft", "--to", "3", "a.txt"]).unwrap();
+        assert_eq!(config.rows, Some(RangeSpec::from(1..=3)));
+    }
+
+    #[test]
+    fn from_and_to_conflict_with_rows() {
+        assert!(
+            cli()
+                .try_get_matches_from(["ft", "-R", "2", "--from", "/a/", "input.txt"])
+                .is_err()
+        );
+    }
+
     #[test]
     fn missing_ranges_fall_back_to_full_range() {
         let config = config_from(&["ft", "input.txt"]).unwrap();
@@ -530,13 +1002,30 @@ mod tests {
     fn fields_delimiter_switches_the_span_to_field_mode() {
         let config = config_from(&["ft", "-F", ",", "-C", "2-3", "input.txt"]).unwrap();
 
-        assert_eq!(config.field_delimiter.as_deref(), Some(","));
+        assert_eq!(config.field_delimiter, Some(Delimiter::from(",")));
         assert!(matches!(config.col_span(), ColumnSpan::Fields { .. }));
 
         let config = config_from(&["ft", "-C", "2-3", "input.txt"]).unwrap();
         assert!(matches!(config.col_span(), ColumnSpan::Chars(_)));
     }
 
+    #[test]
+    fn unquote_is_quoting_never() {
+        let config =
+            config_from(&["ft", "-F", ",", "--quoted", "-C", "1", "--unquote", "x"]).unwrap();
+        assert_eq!(config.quoting, Some(Quoting::Never));
+
+        let config = config_from(&["ft", "-F", ",", "-C", "1", "--quote", "always", "x"]).unwrap();
+        assert_eq!(config.quoting, Some(Quoting::Always));
+        assert!(matches!(
+            config.col_span(),
+            ColumnSpan::Fields(FieldSpan {
+                quoting: Some(Quoting::Always),
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn key_ranges_override_cols_per_operation() {
         let config = config_from(&[
@@ -553,7 +1042,7 @@ mod tests {
         ])
         .unwrap();
 
-        assert_eq!(config.sort_key, Some(ColumnList::from(1..=2)));
+        assert_eq!(config.sort_keys, [ColumnList::from(1..=2).into()]);
         assert_eq!(config.unique_key, Some(ColumnList::from(3..=4)));
         assert_eq!(written(&config.col_span()), [5..=6]);
     }
@@ -565,7 +1054,7 @@ mod tests {
 
         //the parts keep the order written, so reading them permutes
         assert_eq!(written(&config.col_span()), [3..=3, 1..=1]);
-        let sort_key = config.sort_key.clone().unwrap();
+        let sort_key = config.sort_keys[0].columns.clone();
         assert_eq!(written(&config.span_for(sort_key)), [2..=2, 4..=5]);
     }
 
@@ -603,7 +1092,7 @@ mod tests {
     #[test]
     fn key_ranges_use_field_mode_too() {
         let config = config_from(&["ft", "-F", ",", "-s", "--sort-key", "2", "input.txt"]).unwrap();
-        let sort_key = config.sort_key.clone().unwrap();
+        let sort_key = config.sort_keys[0].columns.clone();
         assert!(matches!(
             config.span_for(sort_key),
             ColumnSpan::Fields { .. }
@@ -803,6 +1292,141 @@ mod tests {
         assert!(config.invert);
     }
 
+    #[test]
+    fn context_sides_fall_back_to_context() {
+        let config =
+            config_from(&["ft", "-g", "a", "--context", "2", "-A", "1", "input.txt"]).unwrap();
+        assert_eq!(config.before_context, Some(2));
+        assert_eq!(config.after_context, Some(1));
+
+        let config = config_from(&["ft", "-g", "a", "-B", "3", "input.txt"]).unwrap();
+        assert_eq!(config.before_context, Some(3));
+        assert_eq!(config.after_context, None);
+    }
+
+    #[test]
+    fn context_requires_grep() {
+        for flag in ["-A", "-B", "--context"] {
+            assert!(
+                cli()
+                    .try_get_matches_from(["ft", flag, "1", "input.txt"])
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn column_names_require_header_and_fields() {
+        let error = config_from(&["ft", "-F", ",", "-C", "price", "input.txt"]).unwrap_err();
+        assert!(matches!(error, ConfigError::ColumnNamesWithoutHeader));
+
+        let error = config_from(&["ft", "--header", "-C", "price", "input.txt"]).unwrap_err();
+        assert!(matches!(error, ConfigError::ColumnNamesWithoutHeader));
+    }
+
+    #[test]
+    fn with_header_resolves_every_column_list() {
+        let config = config_from(&[
+            "ft",
+            "--header",
+            "-F",
+            ",",
+            "-C",
+            "name",
+            "--sum",
+            "qty",
+            "--group-by",
+            "name,id",
+            "--percentile",
+            "95:qty",
+            "--stddev",
+            "qty",
+            "--collect",
+            "name",
+            ";",
+            "input.txt",
+        ])
+        .unwrap();
+        let resolved = config
+            .with_header("id,name,qty\n")
+            .unwrap();
+
+        assert_eq!(resolved.cols, Some(ColumnList::from(2..=2)));
+        assert_eq!(resolved.summary.sum, Some(ColumnList::from(3..=3)));
+        assert_eq!(resolved.summary.stddev, Some(ColumnList::from(3..=3)));
+        assert_eq!(
+            resolved.summary.collect,
+            Some(Collect {
+                columns: ColumnList::from(2..=2),
+                separator: ";".to_owned()
+            })
+        );
+        assert_eq!(
+            resolved.summary.percentiles,
+            [Percentile {
+                rank: 95.0,
+                columns: ColumnList::from(3..=3)
+            }]
+        );
+        assert_eq!(
+            resolved.summary.group_by,
+            Some(ColumnList::new(vec![2..=2, 1..=1]))
+        );
+    }
+
+    #[test]
+    fn jsonl_columns_are_key_paths() {
+        let config = config_from(&["ft", "--jsonl", "-C", ".user.id,2", "input.txt"]).unwrap();
+        assert!(
+            config
+                .cols
+                .as_ref()
+                .is_some_and(ColumnList::has_names)
+        );
+        //the path is looked up on each line; only the position is known
+        let span = config.col_span();
+        assert!(matches!(span, ColumnSpan::Members(_)));
+        assert_eq!(written(&span), [2..=2]);
+    }
+
+    #[test]
+    fn output_format_is_a_known_format() {
+        assert!(
+            cli()
+                .try_get_matches_from(["ft", "--output-format", "csv", "--join", ",", "input.txt"])
+                .is_err()
+        );
+        assert!(
+            cli()
+                .try_get_matches_from(["ft", "--output-format", "xml", "--jsonl", "input.txt"])
+                .is_err()
+        );
+        let config =
+            config_from(&["ft", "-F", ",", "--output-format", "jsonl", "input.txt"]).unwrap();
+        assert_eq!(config.output_format, Some(OutputFormat::Jsonl));
+        let config = config_from(&["ft", "--output-format", "markdown", "input.txt"]).unwrap();
+        assert_eq!(config.output_format, Some(OutputFormat::Markdown));
+    }
+
+    #[test]
+    fn delimit_requires_a_schema() {
+        assert!(
+            cli()
+                .try_get_matches_from(["ft", "--delimit", ",", "input.txt"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn with_header_rejects_an_unknown_name() {
+        let config =
+            config_from(&["ft", "--header", "-F", ",", "-C", "price", "input.txt"]).unwrap();
+        let error = config
+            .with_header("id,name\n")
+            .unwrap_err();
+        assert!(matches!(error, ConfigError::UnknownColumn(name) if name == "price"));
+    }
+
     #[test]
     fn accepts_delete_with_grep_only() {
         assert!(config_from(&["ft", "-d", "-g", "foo", "input.txt"]).is_ok());
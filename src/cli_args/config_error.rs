@@ -12,6 +12,10 @@ pub enum ConfigError {
     IgnoreCaseWithoutPattern,
     InPlaceWithoutFile,
     InvalidRegex(String),
+    InvalidColumns(String),
+    ColumnNamesWithoutHeader,
+    UnknownColumn(String),
+    UnknownSchemaField(String),
 }
 
 impl fmt::Display for ConfigError {
@@ -57,6 +61,19 @@ impl fmt::Display for ConfigError {
                 )
             }
             ConfigError::InvalidRegex(error) => write!(f, "Invalid regular expression: {error}"),
+            ConfigError::InvalidColumns(error) => write!(f, "{error}"),
+            ConfigError::ColumnNamesWithoutHeader => {
+                write!(
+                    f,
+                    "Columns can only be named with --schema, by key path with --jsonl, or with --header and --fields, which read the names from the first row"
+                )
+            }
+            ConfigError::UnknownColumn(name) => {
+                write!(f, "No column named `{name}` in the header")
+            }
+            ConfigError::UnknownSchemaField(name) => {
+                write!(f, "No field named `{name}` in the schema")
+            }
         }
     }
 }
@@ -3,5 +3,8 @@ pub mod config;
 pub mod config_error;
 
 pub use cli::cli;
-pub use config::{Config, FindPattern, InPlace, Input, ReorderMode, Replacement, SummarySpec};
+pub use config::{
+    Collect, Config, FindPattern, HistogramSpec, InPlace, Input, Percentile, ReorderMode,
+    Replacement, SortKey, SummarySpec,
+};
 pub use config_error::ConfigError;
@@ -0,0 +1,391 @@
+//! How sort keys compare. A key's text is turned once into a value whose
+//! `Ord` is the order wanted — the text itself, the number or size it
+//! holds, or its natural chunks — so sorting by it is a plain
+//! `sort_by_cached_key`, and `--top`, `--min` and `--max` rank values by
+//! the very same ones.
+//!
+//! Text compares byte by byte unless a collation says otherwise: folded
+//! to lowercase, stripped of accents as well, or by the Unicode Collation
+//! Algorithm, which alone puts every letter where a dictionary would.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+
+use feruca::Collator;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// How one sort key compares: as text, as a number or naturally, its
+/// text collated how, ascending or descending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortOrder {
+    pub compare: Compare,
+    pub collation: Collation,
+    pub reverse: bool,
+}
+
+/// How text compares (`--collate`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Collation {
+    /// Byte by byte, so `B` comes before `a` and `Ł` after `z`.
+    #[default]
+    Bytes,
+    /// Byte by byte once lowercased, so case makes no difference.
+    Fold,
+    /// Byte by byte once lowercased and stripped of accents, so `É`, `e`
+    /// and `é` make no difference either.
+    Accents,
+    /// By the Unicode Collation Algorithm (the CLDR root order): letters
+    /// in alphabetical order whatever their script or accents, and case
+    /// and accents only deciding between otherwise equal texts.
+    Uca,
+}
+
+impl Collation {
+    /// The text with what this collation ignores taken out of it, so
+    /// that texts it holds equal are equal: how `--unique` compares keys.
+    /// The Unicode Collation Algorithm holds no two different texts equal.
+    pub fn fold(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Collation::Bytes | Collation::Uca => Cow::Borrowed(text),
+            Collation::Fold => Cow::Owned(text.to_lowercase()),
+            Collation::Accents => Cow::Owned(
+                text.nfd()
+                    .filter(|c| !is_combining_mark(*c))
+                    .map(unstroked)
+                    .flat_map(char::to_lowercase)
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// The letter under a stroke or bar, which no decomposition takes apart
+/// from it the way it does an accent.
+fn unstroked(c: char) -> char {
+    match c {
+        'Ł' => 'L',
+        'ł' => 'l',
+        'Ø' => 'O',
+        'ø' => 'o',
+        'Đ' => 'D',
+        'đ' => 'd',
+        'Ħ' => 'H',
+        'ħ' => 'h',
+        'Ŧ' => 'T',
+        'ŧ' => 't',
+        'ı' => 'i',
+        _ => c,
+    }
+}
+
+thread_local! {
+    //the collator keeps scratch buffers between comparisons, so each
+    //thread has one to borrow rather than building one per comparison
+    static COLLATOR: RefCell<Collator> = RefCell::new(Collator::default());
+}
+
+/// Text as its collation compares it: folded already, or kept as read
+/// for the Unicode Collation Algorithm to compare.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Collated {
+    text: String,
+    uca: bool,
+}
+
+impl Collated {
+    fn new(text: &str, collation: Collation) -> Collated {
+        Collated {
+            text: collation.fold(text).into_owned(),
+            uca: collation == Collation::Uca,
+        }
+    }
+}
+
+impl Ord for Collated {
+    fn cmp(&self, other: &Collated) -> Ordering {
+        //texts of one key share its collation; as a last resort the
+        //algorithm compares the bytes, so only equal texts are equal
+        match self.uca {
+            true => COLLATOR.with(|collator| {
+                collator
+                    .borrow_mut()
+                    .collate(self.text.as_str(), other.text.as_str())
+            }),
+            false => self.text.cmp(&other.text),
+        }
+    }
+}
+
+impl PartialOrd for Collated {
+    fn partial_cmp(&self, other: &Collated) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// What a sort key's text is compared as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compare {
+    /// Byte by byte (`--sort`).
+    #[default]
+    Text,
+    /// As the number it holds (`--numeric`).
+    Numeric,
+    /// As text whose runs of digits compare as numbers, so `file2` comes
+    /// before `file10` and `1.9.3` before `1.10.0` (`--version-sort`).
+    Natural,
+    /// As a size with an optional binary suffix, so `900K` comes before
+    /// `1.5M` (`--human-numeric-sort`).
+    Human,
+}
+
+/// A sort key's text, in the form its order compares.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortValue {
+    Text(Collated),
+    Numeric(NumericKey),
+    Natural(Vec<Chunk>),
+}
+
+impl SortValue {
+    pub fn new(text: &str, order: SortOrder) -> SortValue {
+        match order.compare {
+            Compare::Text => SortValue::Text(Collated::new(text, order.collation)),
+            Compare::Numeric => SortValue::Numeric(NumericKey::parse(text)),
+            Compare::Natural => SortValue::Natural(chunks(text, order.collation)),
+            Compare::Human => SortValue::Numeric(NumericKey(human_size(text))),
+        }
+    }
+
+    /// Whether the text held no number where one was wanted. Such a
+    /// value sorts first, but takes no part in a `--min` or `--max`.
+    pub fn is_missing(&self) -> bool {
+        matches!(self, SortValue::Numeric(NumericKey(None)))
+    }
+}
+
+/// The suffixes of a human-readable size, each 1024 times the last.
+const UNITS: &str = "KMGTPEZY";
+
+/// The number of bytes a human-readable size stands for, as `du -h` and
+/// `ls -lh` write them: a number, then optionally a suffix from `K` up
+/// to `Y` in either case, then optionally `iB` or `B` (`1.5K`, `2MiB`,
+/// `512B`). `None` when the text is no such size.
+pub fn human_size(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let text = text.strip_suffix('B').unwrap_or(text);
+    let text = text
+        .strip_suffix('i')
+        .filter(|rest| rest.ends_with(|c: char| UNITS.contains(c.to_ascii_uppercase())))
+        .unwrap_or(text);
+    let unit = text
+        .chars()
+        .last()
+        .and_then(|last| UNITS.find(last.to_ascii_uppercase()));
+    let (number, power) = match unit {
+        //a unit is one ASCII letter, one byte long
+        Some(index) => (&text[..text.len() - 1], index as i32 + 1),
+        None => (text, 0),
+    };
+    number
+        .parse()
+        .ok()
+        .filter(|value: &f64| !value.is_nan())
+        .map(|value| value * 1024f64.powi(power))
+}
+
+/// An `Ord` wrapper around the parsed numeric sort key. Lines that do
+/// not parse as a number (`None`, including `NaN`) sort before all
+/// numbers — a sentinel value would collide with an actual `-inf` key.
+#[derive(Debug)]
+pub struct NumericKey(Option<f64>);
+
+impl NumericKey {
+    pub fn parse(text: &str) -> NumericKey {
+        NumericKey(
+            text.trim()
+                .parse()
+                .ok()
+                .filter(|value: &f64| !value.is_nan()),
+        )
+    }
+}
+
+impl PartialEq for NumericKey {
+    fn eq(&self, other: &NumericKey) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for NumericKey {}
+
+impl PartialOrd for NumericKey {
+    fn partial_cmp(&self, other: &NumericKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NumericKey {
+    fn cmp(&self, other: &NumericKey) -> Ordering {
+        match (&self.0, &other.0) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(this), Some(that)) => this.total_cmp(that),
+        }
+    }
+}
+
+/// One run of a natural sort key. A number comes before text where the
+/// two meet (`a1` before `ab`), as a digit does before a letter.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Chunk {
+    Number(Digits),
+    Text(Collated),
+}
+
+/// A run of digits, compared as the number it spells however long: its
+/// leading zeros dropped, a shorter run is the smaller number, and runs
+/// of one length compare digit by digit.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Digits(String);
+
+impl Ord for Digits {
+    fn cmp(&self, other: &Digits) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for Digits {
+    fn partial_cmp(&self, other: &Digits) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Split text into its runs of digits and of everything else.
+fn chunks(text: &str, collation: Collation) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (run, after) = rest.split_at(end);
+        chunks.push(match digits {
+            true => Chunk::Number(Digits(run.trim_start_matches('0').to_owned())),
+            false => Chunk::Text(Collated::new(run, collation)),
+        });
+        rest = after;
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(order: SortOrder, texts: &[&str]) -> Vec<String> {
+        let mut texts: Vec<&str> = texts.to_vec();
+        texts.sort_by_cached_key(|text| SortValue::new(text, order));
+        texts
+            .into_iter()
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn natural_order_compares_digit_runs_as_numbers() {
+        let natural = SortOrder {
+            compare: Compare::Natural,
+            ..SortOrder::default()
+        };
+        assert_eq!(
+            sorted(natural, &["file10", "file2", "file1", "file"]),
+            ["file", "file1", "file2", "file10"]
+        );
+        assert_eq!(
+            sorted(natural, &["1.10.0", "1.9.3", "1.9", "1.09.4"]),
+            ["1.9", "1.9.3", "1.09.4", "1.10.0"]
+        );
+        //far beyond any integer type
+        assert_eq!(
+            sorted(
+                natural,
+                &["v100000000000000000000", "v99999999999999999999"]
+            ),
+            ["v99999999999999999999", "v100000000000000000000"]
+        );
+    }
+
+    #[test]
+    fn human_sizes_compare_by_the_bytes_they_stand_for() {
+        let human = SortOrder {
+            compare: Compare::Human,
+            ..SortOrder::default()
+        };
+        assert_eq!(
+            sorted(human, &["2M", "1.5K", "900", "1G", "size", "3k"]),
+            ["size", "900", "1.5K", "3k", "2M", "1G"]
+        );
+        assert_eq!(human_size("2MiB"), Some(2.0 * 1024.0 * 1024.0));
+        assert_eq!(human_size("512B"), Some(512.0));
+        assert_eq!(human_size("1.5iB"), None);
+        assert_eq!(human_size("K"), None);
+    }
+
+    #[test]
+    fn folded_case_compares_letters_alike() {
+        let folded = SortOrder {
+            collation: Collation::Fold,
+            ..SortOrder::default()
+        };
+        assert_eq!(sorted(folded, &["b", "C", "a", "B"]), ["a", "b", "B", "C"]);
+        assert_eq!(
+            sorted(SortOrder::default(), &["b", "C", "a"]),
+            ["C", "a", "b"]
+        );
+    }
+
+    #[test]
+    fn accents_and_strokes_are_stripped_along_with_case() {
+        let accents = SortOrder {
+            collation: Collation::Accents,
+            ..SortOrder::default()
+        };
+        assert_eq!(
+            sorted(accents, &["Zofia", "Łukasz", "Émile", "ernie", "lena"]),
+            ["Émile", "ernie", "lena", "Łukasz", "Zofia"]
+        );
+        assert_eq!(Collation::Accents.fold("Müller"), "muller");
+        assert_eq!(Collation::Fold.fold("Müller"), "müller");
+    }
+
+    #[test]
+    fn the_collation_algorithm_orders_letters_alphabetically() {
+        let uca = SortOrder {
+            collation: Collation::Uca,
+            ..SortOrder::default()
+        };
+        assert_eq!(
+            sorted(
+                uca,
+                &["Zofia", "Łukasz", "über", "Ulm", "lena", "Peña", "Peng"]
+            ),
+            ["lena", "Łukasz", "Peña", "Peng", "über", "Ulm", "Zofia"]
+        );
+        //the text between the numbers of a natural key is collated too
+        let natural = SortOrder {
+            compare: Compare::Natural,
+            ..uca
+        };
+        assert_eq!(
+            sorted(natural, &["Zeit 1", "Ärger 10", "Ärger 9"]),
+            ["Ärger 9", "Ärger 10", "Zeit 1"]
+        );
+    }
+}
@@ -9,37 +9,177 @@
 //! written, so `3,1,2` permutes; operations that write into the line
 //! (delete, case/trim, find/replace) work on the normalized set, where
 //! the parts are sorted and merged and order carries no meaning.
+//!
+//! With `--header`, a part may also name a column: the name stands for
+//! the field the header row gives it, once that row has been read.
+//!
+//! Fields are separated by a literal delimiter, by runs of whitespace
+//! (awk's default splitting), or by whatever a regex matches. On output
+//! they are written back as the text they were read as, or, with a
+//! [`Quoting`], one by one as CSV values.
+//!
+//! A bound may count from the end of the line (`~1` is the last column).
+//! Lines differ in length, so such a part resolves on each line anew,
+//! against that line's char count, or its field count in field mode.
+//!
+//! In JSON mode (`--jsonl`) the columns are the members of the object
+//! each line holds: a name is a key path, a range counts the top-level
+//! members. They too resolve on each line, to the chars of their values.
 
 use std::borrow::Cow;
 use std::ops::RangeInclusive;
 
-use crate::ranges::RangeSet;
+use regex::Regex;
+
+use crate::json::{self, Member};
+use crate::ranges::{RangeBound, RangeSet};
 use crate::text;
 
+/// One part of a column list as written: a range of positions, a range
+/// with a bound counted from the end of the line, or the name of a
+/// column, looked up in the header row (`--header`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnPart {
+    Range(RangeInclusive<usize>),
+    FromEnd(RangeBound, RangeBound),
+    Name(String),
+}
+
+impl ColumnPart {
+    /// The positions the part covers on a line of `count` columns, or
+    /// `None` when it covers none there (a name covers none until it is
+    /// resolved). A bound counted from before the first column is
+    /// clamped to it, so `~5-~1` on a three-column line is all three.
+    fn resolve(&self, count: usize) -> Option<RangeInclusive<usize>> {
+        match self {
+            ColumnPart::Range(range) => Some(range.clone()),
+            ColumnPart::FromEnd(from, to) => {
+                let from = from.resolve(count)?.max(1);
+                let to = to.resolve(count)?;
+                (from <= to).then_some(from..=to)
+            }
+            ColumnPart::Name(_) => None,
+        }
+    }
+
+    /// What each column the part covers goes by: a name, or each of its
+    /// positions. `None` when the positions depend on the line.
+    fn labels(&self) -> Option<Vec<String>> {
+        match self {
+            ColumnPart::Range(range) if *range.end() == usize::MAX => None,
+            ColumnPart::Range(range) => Some(
+                range
+                    .clone()
+                    .map(|position| position.to_string())
+                    .collect(),
+            ),
+            ColumnPart::FromEnd(..) => None,
+            ColumnPart::Name(name) => Some(vec![name.clone()]),
+        }
+    }
+}
+
 /// Column parts as written on the command line, together with their
 /// normalized form. Both are needed: reading honours the written order,
 /// writing needs sorted, non-overlapping parts.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct ColumnList {
+    //the parts, names included; a name stands for nothing until the
+    //list is resolved against a header
+    parts: Vec<ColumnPart>,
+    //the parts as the user wrote them, names kept through resolving:
+    //what the columns are labelled by on output
+    labelled: Vec<ColumnPart>,
     written: Vec<RangeInclusive<usize>>,
     normalized: RangeSet,
+    //whether a part counts from the end of the line, so `written` and
+    //`normalized` only hold until the line is known
+    from_end: bool,
 }
 
 impl ColumnList {
     pub fn new(written: Vec<RangeInclusive<usize>>) -> ColumnList {
+        ColumnList::with_names(
+            written
+                .into_iter()
+                .map(ColumnPart::Range)
+                .collect(),
+        )
+    }
+
+    /// A list whose parts may name columns. Until the list is
+    /// [resolved](ColumnList::resolve), only its ranges address anything.
+    pub fn with_names(parts: Vec<ColumnPart>) -> ColumnList {
+        let written: Vec<RangeInclusive<usize>> = parts
+            .iter()
+            .filter_map(|part| match part {
+                ColumnPart::Range(range) => Some(range.clone()),
+                _ => None,
+            })
+            .collect();
         let normalized = RangeSet::new(written.clone());
+        let from_end = parts
+            .iter()
+            .any(|part| matches!(part, ColumnPart::FromEnd(..)));
         ColumnList {
+            labelled: parts.clone(),
+            parts,
             written,
             normalized,
+            from_end,
         }
     }
 
+    /// Whether some part is a column name.
+    pub fn has_names(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, ColumnPart::Name(_)))
+    }
+
+    /// The list with every name replaced by the columns `lookup` gives
+    /// it — the field of a header, the chars of a schema — the parts
+    /// kept in their order. `Err` is a name `lookup` does not know.
+    pub fn resolve<F>(&self, lookup: F) -> Result<ColumnList, String>
+    where
+        F: Fn(&str) -> Option<RangeInclusive<usize>>,
+    {
+        let parts = self
+            .parts
+            .iter()
+            .map(|part| match part {
+                ColumnPart::Name(name) => lookup(name)
+                    .map(ColumnPart::Range)
+                    .ok_or_else(|| name.clone()),
+                part => Ok(part.clone()),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(ColumnList {
+            labelled: self.labelled.clone(),
+            ..ColumnList::with_names(parts)
+        })
+    }
+
+    /// What the columns the list reads go by, in order: a named column
+    /// by its name, any other by its position. `None` when the columns
+    /// are only known on a line (an open or end-relative range).
+    pub fn labels(&self) -> Option<Vec<String>> {
+        let labels = self
+            .labelled
+            .iter()
+            .map(ColumnPart::labels)
+            .collect::<Option<Vec<_>>>()?;
+        Some(labels.concat())
+    }
+
     /// The list covering every column.
     pub fn full() -> ColumnList {
         ColumnList::from(1..=usize::MAX)
     }
 
     /// The parts in the order written; a part may repeat or overlap.
+    /// A part counted from the end of the line is missing: it has no
+    /// position until [resolved](ColumnList::written_for) on a line.
     pub fn written(&self) -> &[RangeInclusive<usize>] {
         &self.written
     }
@@ -48,65 +188,372 @@ impl ColumnList {
     pub fn normalized(&self) -> &[RangeInclusive<usize>] {
         self.normalized.parts()
     }
+
+    /// The parts in the order written, on a line of `count` columns.
+    /// Only a list counting from the end of the line has to resolve
+    /// anything; any other reads the same on every line.
+    pub fn written_for(&self, count: usize) -> Cow<'_, [RangeInclusive<usize>]> {
+        if !self.from_end {
+            return Cow::Borrowed(self.written());
+        }
+        Cow::Owned(
+            self.parts
+                .iter()
+                .filter_map(|part| part.resolve(count))
+                .collect(),
+        )
+    }
+
+    /// The normalized set on a line of `count` columns.
+    pub fn normalized_for(&self, count: usize) -> Cow<'_, [RangeInclusive<usize>]> {
+        if !self.from_end {
+            return Cow::Borrowed(self.normalized());
+        }
+        let resolved = RangeSet::new(self.written_for(count).into_owned());
+        Cow::Owned(resolved.parts().to_vec())
+    }
+}
+
+//two lists are the same when they address the same columns, whatever
+//names they were written with
+impl PartialEq for ColumnList {
+    fn eq(&self, other: &ColumnList) -> bool {
+        self.parts == other.parts
+    }
 }
 
+impl Eq for ColumnList {}
+
 impl From<RangeInclusive<usize>> for ColumnList {
     fn from(range: RangeInclusive<usize>) -> ColumnList {
         ColumnList::new(vec![range])
     }
 }
 
+/// What separates one field from the next.
+#[derive(Debug, Clone)]
+pub enum Delimiter {
+    /// A string, each occurrence of which splits (`-F ,`).
+    Literal(String),
+    /// A run of whitespace, as awk splits by default (`-F ' '`): a run
+    /// is one delimiter however long, and the whitespace before the
+    /// first field and after the last separates nothing.
+    Whitespace,
+    /// Every non-empty match of a regex (`--fields-regex`).
+    Pattern(Regex),
+}
+
+impl Delimiter {
+    /// What joins fields split by this delimiter back together: the
+    /// literal itself, or a single space when the delimiter has no one
+    /// spelling, as awk's output separator is.
+    pub fn joiner(&self) -> &str {
+        match self {
+            Delimiter::Literal(literal) => literal,
+            Delimiter::Whitespace | Delimiter::Pattern(_) => " ",
+        }
+    }
+}
+
+impl From<&str> for Delimiter {
+    fn from(literal: &str) -> Delimiter {
+        Delimiter::Literal(literal.to_owned())
+    }
+}
+
+impl From<String> for Delimiter {
+    fn from(literal: String) -> Delimiter {
+        Delimiter::Literal(literal)
+    }
+}
+
+//a compiled regex has no equality of its own; two delimiters are the
+//same when they were written the same
+impl PartialEq for Delimiter {
+    fn eq(&self, other: &Delimiter) -> bool {
+        match (self, other) {
+            (Delimiter::Literal(a), Delimiter::Literal(b)) => a == b,
+            (Delimiter::Whitespace, Delimiter::Whitespace) => true,
+            (Delimiter::Pattern(a), Delimiter::Pattern(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Delimiter {}
+
+/// Which fields are put in quotes when written as CSV (`--quote`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quoting {
+    /// Only those that would not read back otherwise: a field holding
+    /// the delimiter, a quote or a line break.
+    Minimal,
+    /// Every field.
+    Always,
+    /// None: the values are written bare, their quotes stripped
+    /// (`--unquote`).
+    Never,
+}
+
+impl Quoting {
+    /// A value as a field between `delimiter`s: quoted when the style
+    /// asks for it, with any quote inside doubled (RFC 4180).
+    pub fn quote<'a>(self, value: &'a str, delimiter: &str) -> Cow<'a, str> {
+        let quoted = match self {
+            Quoting::Minimal => {
+                value.contains(['"', '\n', '\r'])
+                    || (!delimiter.is_empty() && value.contains(delimiter))
+            }
+            Quoting::Always => true,
+            Quoting::Never => false,
+        };
+        match quoted {
+            true => Cow::Owned(format!("\"{}\"", value.replace('"', "\"\""))),
+            false => Cow::Borrowed(value),
+        }
+    }
+}
+
 /// Field mode: which delimited fields to address, and how they are
 /// delimited on the way in and on the way out.
 #[derive(Debug, Clone)]
 pub struct FieldSpan {
-    pub delimiter: String,
+    pub delimiter: Delimiter,
     /// What joins the selected fields on output; `None` reuses the
     /// input delimiter (`--output-delimiter`).
     pub output_delimiter: Option<String>,
     /// Whether a delimiter inside a `"quoted"` field splits it
     /// (`--quoted`, i.e. CSV rather than a plain split).
     pub quoted: bool,
+    /// `Some` writes every field selected or rewritten as a CSV value,
+    /// quoted in this style; `None` writes the text as it was read.
+    pub quoting: Option<Quoting>,
     pub fields: ColumnList,
 }
 
 impl FieldSpan {
     /// Field mode with the input delimiter reused on output and no
     /// quoting, which is what a plain `--fields` asks for.
-    pub fn new(delimiter: impl Into<String>, fields: ColumnList) -> FieldSpan {
+    pub fn new(delimiter: impl Into<Delimiter>, fields: ColumnList) -> FieldSpan {
         FieldSpan {
             delimiter: delimiter.into(),
             output_delimiter: None,
             quoted: false,
+            quoting: None,
             fields,
         }
     }
+
+    /// What a field's text stands for: in quoted mode, a `"quoted"`
+    /// field stands for what is inside its quotes, a doubled quote
+    /// for one quote. Otherwise the text is the value.
+    fn value<'a>(&self, field: &'a str) -> Cow<'a, str> {
+        match self.quoted {
+            true => unquote(field),
+            false => Cow::Borrowed(field),
+        }
+    }
+
+    /// The char range of every single field the parts cover, the parts
+    /// picked by `parts` for the line's number of fields. A part of
+    /// several fields (`2-4`) yields each of them.
+    fn each_field<'a>(
+        &'a self,
+        line: &str,
+        parts: fn(&'a ColumnList, usize) -> Cow<'a, [RangeInclusive<usize>]>,
+    ) -> Vec<RangeInclusive<usize>> {
+        self.each_numbered_field(line, parts)
+            .into_iter()
+            .map(|(_, field)| field)
+            .collect()
+    }
+
+    /// Like [`FieldSpan::each_field`], each field with its 1-based
+    /// position on the line.
+    fn each_numbered_field<'a>(
+        &'a self,
+        line: &str,
+        parts: fn(&'a ColumnList, usize) -> Cow<'a, [RangeInclusive<usize>]>,
+    ) -> Vec<(usize, RangeInclusive<usize>)> {
+        let positions = field_positions(line, &self.delimiter, self.quoted);
+        parts(&self.fields, positions.len())
+            .iter()
+            .flat_map(|part| {
+                let first = (*part.start()).max(1);
+                let last = (*part.end()).min(positions.len());
+                (first..=last).map(|number| (number, positions[number - 1].clone()))
+            })
+            .collect()
+    }
 }
 
-/// How a column list addresses a line: by character positions, or by
-/// delimited fields.
+/// JSON mode (`--jsonl`): which members of the object on each line to
+/// address. A name in the list is a key path (`.user.id`), a range
+/// counts the top-level members, `~1` being the last of them.
+#[derive(Debug, Clone)]
+pub struct MemberSpan {
+    /// What joins the selected values on output; `None` is a tab
+    /// (`--output-delimiter`).
+    pub output_delimiter: Option<String>,
+    pub members: ColumnList,
+}
+
+impl MemberSpan {
+    pub fn new(members: ColumnList) -> MemberSpan {
+        MemberSpan {
+            output_delimiter: None,
+            members,
+        }
+    }
+
+    /// The members the parts address in the object on the line, in the
+    /// order written, each with the key it goes by: the path as written,
+    /// or a top-level member's own key. A path leading nowhere, or a
+    /// range past the last member, addresses nothing.
+    fn addressed<'a>(&self, members: &'a [Member]) -> Vec<(String, &'a Member)> {
+        let mut addressed = Vec::new();
+        for part in &self.members.parts {
+            match part {
+                ColumnPart::Name(path) => {
+                    if let Some(member) = json::find(members, path) {
+                        let key = path.strip_prefix('.').unwrap_or(path);
+                        addressed.push((key.to_owned(), member));
+                    }
+                }
+                part => {
+                    if let Some(range) = part.resolve(members.len()) {
+                        let first = (*range.start()).max(1);
+                        let last = (*range.end()).min(members.len());
+                        addressed.extend(
+                            members
+                                .get(first - 1..last)
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|member| (member.key.clone(), member)),
+                        );
+                    }
+                }
+            }
+        }
+        addressed
+    }
+
+    /// The char ranges of the values addressed, in the order written.
+    fn values(&self, line: &str) -> Vec<RangeInclusive<usize>> {
+        let members = json::members(line);
+        self.addressed(&members)
+            .into_iter()
+            .map(|(_, member)| member.value.clone())
+            .collect()
+    }
+
+    /// The char ranges of the values a rewrite maps: the strings,
+    /// numbers, booleans and nulls the addressed members hold, however
+    /// deep — a rewrite of an object rewrites what is in it, never its
+    /// keys or its punctuation.
+    fn leaves(&self, line: &str) -> Vec<RangeInclusive<usize>> {
+        fn collect(member: &Member, leaves: &mut Vec<RangeInclusive<usize>>) {
+            match member.container {
+                true => member
+                    .children
+                    .iter()
+                    .for_each(|child| collect(child, leaves)),
+                false => leaves.push(member.value.clone()),
+            }
+        }
+
+        let members = json::members(line);
+        let mut leaves = Vec::new();
+        for (_, member) in self.addressed(&members) {
+            collect(member, &mut leaves);
+        }
+        RangeSet::new(leaves).parts().to_vec()
+    }
+
+    /// The char ranges that deleting the addressed members removes: each
+    /// run of adjacent members with the comma after it, or before it
+    /// when the run ends its object, so what is left stays valid JSON.
+    fn removals(&self, line: &str) -> Vec<RangeInclusive<usize>> {
+        fn collect(
+            members: &[Member],
+            addressed: &[&Member],
+            removals: &mut Vec<RangeInclusive<usize>>,
+        ) {
+            let is_addressed = |member: &Member| {
+                addressed
+                    .iter()
+                    .any(|other| other.extent == member.extent)
+            };
+            let mut index = 0;
+            while index < members.len() {
+                if !is_addressed(&members[index]) {
+                    collect(&members[index].children, addressed, removals);
+                    index += 1;
+                    continue;
+                }
+                let first = index;
+                while index < members.len() && is_addressed(&members[index]) {
+                    index += 1;
+                }
+                let last = index - 1;
+                let mut start = *members[first].extent.start();
+                let mut end = *members[last].extent.end();
+                if let Some(next) = members.get(last + 1) {
+                    end = *next.extent.start() - 1;
+                } else if first > 0 {
+                    start = *members[first - 1].extent.end() + 1;
+                }
+                removals.push(start..=end);
+            }
+        }
+
+        let members = json::members(line);
+        let addressed: Vec<&Member> = self
+            .addressed(&members)
+            .into_iter()
+            .map(|(_, member)| member)
+            .collect();
+        let mut removals = Vec::new();
+        collect(&members, &addressed, &mut removals);
+        removals
+    }
+}
+
+/// How a column list addresses a line: by character positions, by
+/// delimited fields, or by the members of a JSON object.
 #[derive(Debug, Clone)]
 pub enum ColumnSpan {
     Chars(ColumnList),
     Fields(FieldSpan),
+    Members(MemberSpan),
 }
 
 impl ColumnSpan {
     /// Field mode with the input delimiter reused on output.
-    pub fn fields(delimiter: impl Into<String>, fields: ColumnList) -> ColumnSpan {
+    pub fn fields(delimiter: impl Into<Delimiter>, fields: ColumnList) -> ColumnSpan {
         ColumnSpan::Fields(FieldSpan::new(delimiter, fields))
     }
 
+    /// The column list the span addresses, whatever its columns are.
+    pub fn columns(&self) -> &ColumnList {
+        match self {
+            ColumnSpan::Chars(columns) => columns,
+            ColumnSpan::Fields(spec) => &spec.fields,
+            ColumnSpan::Members(spec) => &spec.members,
+        }
+    }
+
     /// The char ranges to read, in the order written, so a permuted list
     /// (`3,1,2`) reads the parts in that order. Field parts that lie past
     /// the last field of `line` are dropped: they address nothing, and
     /// keeping them would join a stray delimiter into the result.
     pub fn read_ranges(&self, line: &str) -> Cow<'_, [RangeInclusive<usize>]> {
         match self {
-            ColumnSpan::Chars(list) => Cow::Borrowed(list.written()),
+            ColumnSpan::Chars(list) => list.written_for(char_count(list, line)),
             ColumnSpan::Fields(spec) => {
-                Cow::Owned(resolve_fields(line, spec, spec.fields.written(), false))
+                Cow::Owned(resolve_fields(line, spec, ColumnList::written_for, false))
             }
+            ColumnSpan::Members(spec) => Cow::Owned(spec.values(line)),
         }
     }
 
@@ -114,23 +561,33 @@ impl ColumnSpan {
     /// and never overlap and the line can be rebuilt in one pass.
     pub fn write_ranges(&self, line: &str) -> Cow<'_, [RangeInclusive<usize>]> {
         match self {
-            ColumnSpan::Chars(list) => Cow::Borrowed(list.normalized()),
-            ColumnSpan::Fields(spec) => {
-                Cow::Owned(resolve_fields(line, spec, spec.fields.normalized(), false))
-            }
+            ColumnSpan::Chars(list) => list.normalized_for(char_count(list, line)),
+            ColumnSpan::Fields(spec) => Cow::Owned(resolve_fields(
+                line,
+                spec,
+                ColumnList::normalized_for,
+                false,
+            )),
+            ColumnSpan::Members(spec) => Cow::Owned(
+                RangeSet::new(spec.values(line))
+                    .parts()
+                    .to_vec(),
+            ),
         }
     }
 
     /// Like [`ColumnSpan::write_ranges`], but in field mode each part
     /// swallows one adjacent delimiter (like `cut`), so deleting fields
     /// does not leave a dangling delimiter behind. Normalizing first is
-    /// what makes `-C 2,3` delete the same columns as `-C 2-3`.
+    /// what makes `-C 2,3` delete the same columns as `-C 2-3`. In JSON
+    /// mode a member goes whole, key and comma included.
     pub fn delete_ranges(&self, line: &str) -> Cow<'_, [RangeInclusive<usize>]> {
         match self {
-            ColumnSpan::Chars(list) => Cow::Borrowed(list.normalized()),
+            ColumnSpan::Chars(list) => list.normalized_for(char_count(list, line)),
             ColumnSpan::Fields(spec) => {
-                Cow::Owned(resolve_fields(line, spec, spec.fields.normalized(), true))
+                Cow::Owned(resolve_fields(line, spec, ColumnList::normalized_for, true))
             }
+            ColumnSpan::Members(spec) => Cow::Owned(spec.removals(line)),
         }
     }
 
@@ -138,22 +595,144 @@ impl ColumnSpan {
     /// the order written and joined. A span reaching past the line
     /// selects nothing, like `cut` — which is what makes it a usable
     /// sort, unique or grouping key on a short line.
+    ///
+    /// Written as CSV (`--quote`), the fields are taken one by one, each
+    /// quoted as its value needs, so `2-3` joined by `;` is two fields.
+    /// In JSON mode a string is selected as its text, its quotes and
+    /// escapes gone, and any other value as written.
     pub fn select<'a>(&self, line: &'a str) -> Cow<'a, str> {
-        text::select_ranges(line, &self.read_ranges(line), self.joiner())
+        match self {
+            ColumnSpan::Members(spec) => {
+                let values: Vec<Cow<str>> = spec
+                    .values(line)
+                    .iter()
+                    .map(|value| json::decode(text::select_columns(line, value)))
+                    .collect();
+                Cow::Owned(values.join(self.joiner()))
+            }
+            ColumnSpan::Fields(
+                spec @ FieldSpan {
+                    quoting: Some(quoting),
+                    ..
+                },
+            ) => {
+                let joiner = self.joiner();
+                let fields: Vec<String> = spec
+                    .each_field(line, ColumnList::written_for)
+                    .iter()
+                    .map(|field| {
+                        let value = spec.value(text::select_columns(line, field));
+                        quoting
+                            .quote(&value, joiner)
+                            .into_owned()
+                    })
+                    .collect();
+                Cow::Owned(fields.join(joiner))
+            }
+            _ => text::select_ranges(line, &self.read_ranges(line), self.joiner()),
+        }
+    }
+
+    /// The line with `map` applied to the columns within the span (the
+    /// [write ranges](ColumnSpan::write_ranges)), the rest left as it is.
+    /// Written as CSV, each field is mapped on its own, as its value,
+    /// and quoted again for the line's delimiter — so a comma replaced
+    /// into a CSV field does not split it. In JSON mode each value is
+    /// mapped as its text and written back as JSON: a string stays one,
+    /// and a number (or `true`, `false`, `null`) the rewrite turns into
+    /// something else becomes a string.
+    pub fn map<F>(&self, line: &str, map: F) -> String
+    where
+        F: Fn(&str) -> String,
+    {
+        match self {
+            ColumnSpan::Members(spec) => text::map_ranges(line, &spec.leaves(line), |value| {
+                let mapped = map(&json::decode(value));
+                match value.starts_with('"') || !json::is_scalar(&mapped) {
+                    true => json::encode(&mapped),
+                    false => mapped,
+                }
+            }),
+            ColumnSpan::Fields(
+                spec @ FieldSpan {
+                    quoting: Some(quoting),
+                    ..
+                },
+            ) => {
+                let fields = spec.each_field(line, ColumnList::normalized_for);
+                text::map_ranges(line, &fields, |field| {
+                    let mapped = map(&spec.value(field));
+                    quoting
+                        .quote(&mapped, spec.delimiter.joiner())
+                        .into_owned()
+                })
+            }
+            _ => text::map_ranges(line, &self.write_ranges(line), map),
+        }
     }
 
     /// What joins the parts of a selection: the output delimiter in
-    /// field mode (the input delimiter unless overridden), nothing in
-    /// char mode, where columns are adjacent by definition.
+    /// field mode (unless overridden, the input delimiter, or a space
+    /// for one that is not literal), nothing in char mode, where columns
+    /// are adjacent by definition, and a tab in JSON mode unless
+    /// overridden.
     pub fn joiner(&self) -> &str {
         match self {
             ColumnSpan::Chars(_) => "",
             ColumnSpan::Fields(spec) => spec
                 .output_delimiter
                 .as_deref()
-                .unwrap_or(&spec.delimiter),
+                .unwrap_or(spec.delimiter.joiner()),
+            ColumnSpan::Members(spec) => spec
+                .output_delimiter
+                .as_deref()
+                .unwrap_or("\t"),
         }
     }
+
+    /// The columns the span reads from the line, written as one JSON
+    /// object (`--output-format json` with `--jsonl`). A member goes by
+    /// its key path, its value as written. A field or a char range goes
+    /// by its position, its value a number when it reads as one.
+    pub fn object(&self, line: &str) -> String {
+        let key = |number: usize| number.to_string();
+        let entries: Vec<(String, String)> = match self {
+            ColumnSpan::Chars(_) => self
+                .read_ranges(line)
+                .iter()
+                .enumerate()
+                .map(|(index, part)| {
+                    (
+                        key(index + 1),
+                        json::infer(text::select_columns(line, part)),
+                    )
+                })
+                .collect(),
+            ColumnSpan::Fields(spec) => spec
+                .each_numbered_field(line, ColumnList::written_for)
+                .into_iter()
+                .map(|(number, field)| {
+                    let value = spec.value(text::select_columns(line, &field));
+                    (key(number), json::infer(&value))
+                })
+                .collect(),
+            ColumnSpan::Members(spec) => {
+                let members = json::members(line);
+                spec.addressed(&members)
+                    .into_iter()
+                    .map(|(key, member)| {
+                        let value = text::select_columns(line, &member.value);
+                        (key, value.to_owned())
+                    })
+                    .collect()
+            }
+        };
+        let members: Vec<String> = entries
+            .iter()
+            .map(|(key, value)| format!("{}:{value}", json::encode(key)))
+            .collect();
+        format!("{{{}}}", members.join(","))
+    }
 }
 
 impl From<RangeInclusive<usize>> for ColumnSpan {
@@ -168,21 +747,60 @@ impl From<ColumnList> for ColumnSpan {
     }
 }
 
+/// The values of a line's fields, split like any line in field mode:
+/// the names a header row gives its fields, the values an output format
+/// writes. A quoted value is taken without its quotes, so the field
+/// `"unit price"` is named `unit price`.
+pub fn field_values(line: &str, delimiter: &Delimiter, quoted: bool) -> Vec<String> {
+    field_positions(line, delimiter, quoted)
+        .iter()
+        .map(|part| {
+            let name = text::select_columns(line, part);
+            match quoted {
+                true => unquote(name).into_owned(),
+                false => name.to_owned(),
+            }
+        })
+        .collect()
+}
+
+/// The value of a CSV field: what is inside its quotes, a doubled quote
+/// standing for one. A field that is not quoted is its own value.
+fn unquote(field: &str) -> Cow<'_, str> {
+    match field
+        .strip_prefix('"')
+        .and_then(|field| field.strip_suffix('"'))
+    {
+        Some(inner) => Cow::Owned(inner.replace("\"\"", "\"")),
+        None => Cow::Borrowed(field),
+    }
+}
+
+/// How many chars a line has, for a column list to count back from.
+/// Counting walks the line, so a list that never counts back skips it.
+fn char_count(list: &ColumnList, line: &str) -> usize {
+    if list.from_end {
+        line.chars().count()
+    } else {
+        0
+    }
+}
+
 /// Resolve every field part to the char range it occupies, dropping the
 /// parts that address no field at all. The line is split into fields
-/// once, however many parts ask about it.
-fn resolve_fields(
+/// once, however many parts ask about it; `parts` picks the field parts
+/// (written or normalized) for the line's number of fields.
+fn resolve_fields<'a>(
     line: &str,
-    spec: &FieldSpan,
-    parts: &[RangeInclusive<usize>],
+    spec: &'a FieldSpan,
+    parts: fn(&'a ColumnList, usize) -> Cow<'a, [RangeInclusive<usize>]>,
     swallow_delimiter: bool,
 ) -> Vec<RangeInclusive<usize>> {
     let positions = field_positions(line, &spec.delimiter, spec.quoted);
-    let delimiter_len = spec.delimiter.chars().count();
 
-    parts
+    parts(&spec.fields, positions.len())
         .iter()
-        .filter_map(|part| resolve_part(&positions, part, delimiter_len, swallow_delimiter))
+        .filter_map(|part| resolve_part(&positions, part, swallow_delimiter))
         .collect()
 }
 
@@ -192,7 +810,6 @@ fn resolve_fields(
 fn resolve_part(
     positions: &[RangeInclusive<usize>],
     fields: &RangeInclusive<usize>,
-    delimiter_len: usize,
     swallow_delimiter: bool,
 ) -> Option<RangeInclusive<usize>> {
     let first = (*fields.start()).max(1);
@@ -207,11 +824,13 @@ fn resolve_part(
 
     if swallow_delimiter {
         //take one bordering delimiter with the fields, like `cut`:
-        //the trailing one when a field follows, else the leading one
+        //the trailing one when a field follows, else the leading one.
+        //A delimiter is whatever lies between two fields, so a run of
+        //whitespace or a regex match goes whole, however long
         if wanted_last < positions.len() {
-            end += delimiter_len;
+            end = *positions[wanted_last].start() - 1;
         } else if first > 1 {
-            start -= delimiter_len;
+            start = *positions[first - 2].end() + 1;
         }
     }
 
@@ -228,27 +847,47 @@ fn resolve_part(
 /// quote handles it, since the pair leaves the state where it found it.
 /// A field keeps its quotes: they are part of the text it occupies, so
 /// selected fields re-join into valid CSV.
-fn field_positions(line: &str, delimiter: &str, quoted: bool) -> Vec<RangeInclusive<usize>> {
-    let delimiter_len = delimiter.chars().count();
+///
+/// Split on whitespace, the blanks around the fields are left out, and a
+/// blank line has no fields at all, as in awk.
+fn field_positions(line: &str, delimiter: &Delimiter, quoted: bool) -> Vec<RangeInclusive<usize>> {
     let mut positions = Vec::new();
 
     //1-based char positions: where the current field starts, and where
     //the walk currently is
     let mut start = 1usize;
-    let mut column = 1usize;
+    let line = match delimiter {
+        Delimiter::Whitespace => {
+            let trimmed = line.trim_start();
+            start += line[..line.len() - trimmed.len()]
+                .chars()
+                .count();
+            trimmed.trim_end()
+        }
+        _ => line,
+    };
+    if line.is_empty() && *delimiter == Delimiter::Whitespace {
+        return positions;
+    }
+
+    let mut column = start;
     let mut inside_quotes = false;
     //chars of a matched delimiter still to walk over
     let mut skip = 0usize;
+    let mut finder = DelimiterFinder::new(delimiter, line);
 
     for (offset, character) in line.char_indices() {
         if skip > 0 {
             skip -= 1;
         } else if quoted && character == '"' {
             inside_quotes = !inside_quotes;
-        } else if !inside_quotes && line[offset..].starts_with(delimiter) {
+        } else if !inside_quotes && let Some(len) = finder.at(offset) {
+            let width = line[offset..offset + len]
+                .chars()
+                .count();
             positions.push(start..=column - 1);
-            start = column + delimiter_len;
-            skip = delimiter_len - 1;
+            start = column + width;
+            skip = width - 1;
         }
         column += 1;
     }
@@ -257,6 +896,57 @@ fn field_positions(line: &str, delimiter: &str, quoted: bool) -> Vec<RangeInclus
     positions
 }
 
+/// Tells, as a line is walked from its start, whether a delimiter
+/// starts at the current byte offset, and how many bytes it takes.
+struct DelimiterFinder<'a> {
+    delimiter: &'a Delimiter,
+    line: &'a str,
+    //the next regex match at or after the walk, as byte offsets. A regex
+    //searches ahead, so the match is kept until the walk reaches or
+    //passes it rather than searched for again at every char; `None` once
+    //there is no match left
+    ahead: Option<(usize, usize)>,
+}
+
+impl<'a> DelimiterFinder<'a> {
+    fn new(delimiter: &'a Delimiter, line: &'a str) -> DelimiterFinder<'a> {
+        DelimiterFinder {
+            delimiter,
+            line,
+            ahead: Some((0, 0)),
+        }
+    }
+
+    fn at(&mut self, offset: usize) -> Option<usize> {
+        let rest = &self.line[offset..];
+        match self.delimiter {
+            Delimiter::Literal(literal) => rest
+                .starts_with(literal.as_str())
+                .then_some(literal.len()),
+            Delimiter::Whitespace => {
+                let run = rest.len() - rest.trim_start().len();
+                (run > 0).then_some(run)
+            }
+            Delimiter::Pattern(regex) => {
+                //a match left behind (passed inside quotes, or an empty
+                //one) is stale: search again from here
+                if let Some((start, end)) = self.ahead
+                    && (start < offset || (start == offset && end == start))
+                {
+                    self.ahead = regex
+                        .find_at(self.line, offset)
+                        .map(|found| (found.start(), found.end()));
+                }
+                //an empty match would split between every two chars
+                match self.ahead {
+                    Some((start, end)) if start == offset && end > start => Some(end - start),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -282,6 +972,134 @@ mod tests {
         assert_eq!(list.normalized(), [1..=4]);
     }
 
+    fn named(parts: &[&str]) -> ColumnList {
+        ColumnList::with_names(
+            parts
+                .iter()
+                .map(|part| match part.parse() {
+                    Ok(position) => ColumnPart::Range(position..=position),
+                    Err(_) => ColumnPart::Name(part.to_string()),
+                })
+                .collect(),
+        )
+    }
+
+    fn header_position(header: &[String], name: &str) -> Option<RangeInclusive<usize>> {
+        header
+            .iter()
+            .position(|field| field == name)
+            .map(|index| index + 1..=index + 1)
+    }
+
+    #[test]
+    fn names_resolve_to_header_positions_in_the_written_order() {
+        let header = ["id", "price", "qty"].map(String::from);
+        let list = named(&["qty", "1", "price"]);
+        assert!(list.has_names());
+
+        let resolved = list
+            .resolve(|name| header_position(&header, name))
+            .unwrap();
+        assert!(!resolved.has_names());
+        assert_eq!(resolved.written(), [3..=3, 1..=1, 2..=2]);
+    }
+
+    #[test]
+    fn an_unknown_name_is_reported() {
+        let header = ["id"].map(String::from);
+        assert_eq!(
+            named(&["price"]).resolve(|name| header_position(&header, name)),
+            Err("price".to_owned())
+        );
+    }
+
+    #[test]
+    fn unresolved_names_address_nothing() {
+        assert_eq!(named(&["qty", "2"]).written(), [2..=2]);
+    }
+
+    #[test]
+    fn field_names_split_like_fields() {
+        assert_eq!(
+            field_values("id,price,,qty", &Delimiter::from(","), false),
+            ["id", "price", "", "qty"]
+        );
+    }
+
+    #[test]
+    fn quoted_field_names_lose_their_quotes() {
+        assert_eq!(
+            field_values(
+                r#"id,"unit, price","say ""hi""""#,
+                &Delimiter::from(","),
+                true
+            ),
+            ["id", "unit, price", r#"say "hi""#]
+        );
+        //without --quoted, quotes are part of the name
+        assert_eq!(
+            field_values(r#""id""#, &Delimiter::from(","), false),
+            [r#""id""#]
+        );
+    }
+
+    fn from_end(from: RangeBound, to: RangeBound) -> ColumnList {
+        ColumnList::with_names(vec![ColumnPart::FromEnd(from, to)])
+    }
+
+    #[test]
+    fn end_relative_parts_resolve_per_line() {
+        use RangeBound::{FromEnd, FromStart};
+        let list = ColumnList::with_names(vec![
+            ColumnPart::FromEnd(FromEnd(1), FromEnd(1)),
+            ColumnPart::Range(1..=1),
+        ]);
+        //the order as written holds, whatever the line's length
+        assert_eq!(list.written_for(3).as_ref(), [3..=3, 1..=1]);
+        assert_eq!(list.written_for(5).as_ref(), [5..=5, 1..=1]);
+        assert_eq!(list.normalized_for(2).as_ref(), [1..=2]);
+
+        //`2-~2` on a line of two columns is inverted, so covers nothing;
+        //`~5-` reaching before the first column starts at it
+        assert!(
+            from_end(FromStart(2), FromEnd(2))
+                .written_for(2)
+                .is_empty()
+        );
+        assert_eq!(
+            from_end(FromEnd(5), FromStart(usize::MAX))
+                .written_for(3)
+                .as_ref(),
+            [1..=usize::MAX]
+        );
+    }
+
+    #[test]
+    fn field_span_counts_back_from_the_last_field_of_each_line() {
+        use RangeBound::{FromEnd, FromStart};
+        let last = ColumnSpan::fields(
+            ",",
+            from_end(RangeBound::FromEnd(1), RangeBound::FromEnd(1)),
+        );
+        assert_eq!(last.select("a,b,c"), "c");
+        assert_eq!(last.select("a,bb"), "bb");
+
+        //everything but the last two fields
+        let head = ColumnSpan::fields(",", from_end(FromStart(1), FromEnd(3)));
+        assert_eq!(head.select("a,b,c,d"), "a,b");
+        assert_eq!(head.delete_ranges("a,b,c,d").as_ref(), [1..=4]);
+        assert!(head.read_ranges("a,b").is_empty());
+    }
+
+    #[test]
+    fn char_span_counts_back_from_the_last_char_of_each_line() {
+        use RangeBound::{FromEnd, FromStart};
+        let span = ColumnSpan::Chars(from_end(FromEnd(2), FromStart(usize::MAX)));
+        assert_eq!(span.select("abcd"), "cd");
+        assert_eq!(span.select("łąść"), "ść");
+        assert_eq!(span.write_ranges("abc").as_ref(), [2..=usize::MAX]);
+    }
+
     #[test]
     fn char_span_reads_the_parts_in_order() {
         let span = chars(&[3..=4, 1..=2]);
@@ -402,6 +1220,63 @@ mod tests {
         );
     }
 
+    fn csv_fields(quoting: Quoting, parts: &[RangeInclusive<usize>]) -> ColumnSpan {
+        ColumnSpan::Fields(FieldSpan {
+            quoted: true,
+            quoting: Some(quoting),
+            output_delimiter: Some(";".to_owned()),
+            ..FieldSpan::new(",", list(parts))
+        })
+    }
+
+    #[test]
+    fn quoting_minimal_quotes_only_what_needs_it() {
+        let line = r#"a,"b,c","x ""y""",d;e"#;
+        //a range of fields is written field by field, each on its own
+        assert_eq!(
+            csv_fields(Quoting::Minimal, &[1..=4]).select(line),
+            r#"a;b,c;"x ""y""";"d;e""#
+        );
+    }
+
+    #[test]
+    fn quoting_always_and_never() {
+        let line = r#"a,"b,c""#;
+        assert_eq!(
+            csv_fields(Quoting::Always, &[2..=2, 1..=1]).select(line),
+            r#""b,c";"a""#
+        );
+        assert_eq!(csv_fields(Quoting::Never, &[1..=2]).select(line), "a;b,c");
+    }
+
+    #[test]
+    fn quoting_requotes_a_rewritten_field_for_the_line() {
+        let span = ColumnSpan::Fields(FieldSpan {
+            quoted: true,
+            quoting: Some(Quoting::Minimal),
+            ..FieldSpan::new(",", list(&[2..=3]))
+        });
+        //the map sees each value without its quotes, and what it puts in
+        //is quoted for the line's own delimiter
+        assert_eq!(
+            span.map(r#"a,"b",c"#, |value| value.replace('b', "1,2")),
+            r#"a,"1,2",c"#
+        );
+        assert_eq!(
+            span.map("a,b,c", |value| format!("{value}\"")),
+            r#"a,"b""","c""""#
+        );
+    }
+
+    #[test]
+    fn without_quoting_a_map_rewrites_the_text_as_read() {
+        let span = fields(",", &[2..=2]);
+        assert_eq!(
+            span.map("a,b,c", |value| value.replace('b', "1,2")),
+            "a,1,2,c"
+        );
+    }
+
     #[test]
     fn field_span_clamps_open_ended_ranges() {
         assert_eq!(
@@ -470,6 +1345,83 @@ mod tests {
         );
     }
 
+    fn whitespace(parts: &[RangeInclusive<usize>]) -> ColumnSpan {
+        ColumnSpan::fields(Delimiter::Whitespace, list(parts))
+    }
+
+    fn pattern(regex: &str, parts: &[RangeInclusive<usize>]) -> ColumnSpan {
+        ColumnSpan::fields(Delimiter::Pattern(Regex::new(regex).unwrap()), list(parts))
+    }
+
+    #[test]
+    fn whitespace_span_splits_on_runs_and_skips_the_margins() {
+        //pos: "  1 pts/0   bash " -> 1=3, pts/0=5-9, bash=13-16
+        let line = "  1 pts/0   bash ";
+        assert_eq!(
+            whitespace(&[1..=1, 3..=3])
+                .read_ranges(line)
+                .as_ref(),
+            [3..=3, 13..=16]
+        );
+        assert_eq!(whitespace(&[1..=1, 3..=3]).select(line), "1 bash");
+        //a run is one delimiter, so there are no empty fields to address
+        assert!(
+            whitespace(&[4..=4])
+                .read_ranges(line)
+                .is_empty()
+        );
+        assert!(
+            whitespace(&[1..=1])
+                .read_ranges("   ")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn whitespace_span_deletes_a_whole_run_with_the_field() {
+        //pos: "a  b\tc" -> a=1, b=4, c=6
+        assert_eq!(
+            whitespace(&[2..=2])
+                .delete_ranges("a  b\tc")
+                .as_ref(),
+            [4..=5]
+        );
+        assert_eq!(
+            whitespace(&[3..=3])
+                .delete_ranges("a  b\tc")
+                .as_ref(),
+            [5..=6]
+        );
+    }
+
+    #[test]
+    fn pattern_span_splits_on_every_match() {
+        //pos: "a; b,c" -> a=1, b=4, c=6
+        let span = pattern("[;,] *", &[2..=3]);
+        assert_eq!(span.read_ranges("a; b,c").as_ref(), [4..=6]);
+        assert_eq!(span.delete_ranges("a; b,c").as_ref(), [2..=6]);
+        //fields split by a regex rejoin with a space, like awk's
+        assert_eq!(pattern("[;,] *", &[3..=3, 1..=1]).select("a; b,c"), "c a");
+    }
+
+    #[test]
+    fn pattern_span_ignores_empty_matches() {
+        //`,*` matches nothing between every two chars; only the commas split
+        assert_eq!(pattern(",*", &[2..=2]).select("ab,,cd"), "cd");
+    }
+
+    #[test]
+    fn pattern_span_respects_quotes() {
+        let span = ColumnSpan::Fields(FieldSpan {
+            quoted: true,
+            ..FieldSpan::new(
+                Delimiter::Pattern(Regex::new(", *").unwrap()),
+                list(&[2..=2]),
+            )
+        });
+        assert_eq!(span.select(r#""a, b", c, d"#), "c");
+    }
+
     #[test]
     fn delete_span_swallows_the_trailing_delimiter() {
         assert_eq!(
@@ -530,4 +1482,96 @@ mod tests {
             [1..=2, 4..=5]
         );
     }
+
+    const OBJECT: &str = r#"{"id": 7, "user": {"name": "Ann", "tags": ["a", "b"]}, "ok": true}"#;
+
+    fn members(parts: &[&str]) -> ColumnSpan {
+        ColumnSpan::Members(MemberSpan::new(named(parts)))
+    }
+
+    #[test]
+    fn member_span_selects_values_by_path_or_position() {
+        assert_eq!(members(&[".user.name", "1"]).select(OBJECT), "Ann\t7");
+        assert_eq!(members(&["3"]).select(OBJECT), "true");
+        assert_eq!(members(&[".user.tags"]).select(OBJECT), r#"["a", "b"]"#);
+        //a missing key, or a line that is no object, selects nothing
+        assert_eq!(members(&[".user.age", "9"]).select(OBJECT), "");
+        assert_eq!(members(&["1"]).select("plain text"), "");
+    }
+
+    #[test]
+    fn member_span_counts_back_from_the_last_member() {
+        let span = ColumnSpan::Members(MemberSpan::new(from_end(
+            RangeBound::FromEnd(1),
+            RangeBound::FromEnd(1),
+        )));
+        assert_eq!(span.select(OBJECT), "true");
+    }
+
+    #[test]
+    fn member_span_maps_the_values_inside_and_keeps_them_json() {
+        let upper = |value: &str| value.to_uppercase();
+        assert_eq!(
+            members(&[".user"]).map(OBJECT, upper),
+            r#"{"id": 7, "user": {"name": "ANN", "tags": ["A", "B"]}, "ok": true}"#
+        );
+        //a number rewritten to text becomes a string, quotes get escaped
+        let line = r#"{"n": 10, "s": "x"}"#;
+        assert_eq!(
+            members(&["n", "s"]).map(line, |value| value.replace('1', "one")),
+            r#"{"n": "one0", "s": "x"}"#
+        );
+        assert_eq!(
+            members(&["s"]).map(line, |_| "say \"hi\"".to_owned()),
+            r#"{"n": 10, "s": "say \"hi\""}"#
+        );
+    }
+
+    #[test]
+    fn member_span_deletes_members_with_a_comma() {
+        let delete = |parts: &[&str], line: &str| {
+            text::remove_ranges(line, &members(parts).delete_ranges(line))
+        };
+        assert_eq!(
+            delete(&["id"], OBJECT),
+            r#"{"user": {"name": "Ann", "tags": ["a", "b"]}, "ok": true}"#
+        );
+        assert_eq!(delete(&["user", "ok"], OBJECT), r#"{"id": 7}"#);
+        assert_eq!(
+            delete(&[".user.name", ".user.tags.1"], OBJECT),
+            r#"{"id": 7, "user": {"tags": ["a"]}, "ok": true}"#
+        );
+        let all = ColumnSpan::Members(MemberSpan::new(list(&[1..=3])));
+        assert_eq!(
+            text::remove_ranges(OBJECT, &all.delete_ranges(OBJECT)),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn objects_are_keyed_by_key_path_or_position() {
+        let span = fields(",", &[2..=3, 1..=1]);
+        assert_eq!(span.object("a,010,1.5"), r#"{"2":"010","3":1.5,"1":"a"}"#);
+        assert_eq!(
+            members(&[".user.name", "1"]).object(OBJECT),
+            r#"{"user.name":"Ann","id":7}"#
+        );
+    }
+
+    #[test]
+    fn labels_keep_the_names_a_list_was_written_with() {
+        let written = ColumnList::with_names(vec![
+            ColumnPart::Name("price".to_owned()),
+            ColumnPart::Range(1..=2),
+        ]);
+        let resolved = written
+            .resolve(|_| Some(4..=4))
+            .unwrap();
+        assert_eq!(resolved, list(&[4..=4, 1..=2]));
+        assert_eq!(
+            resolved.labels(),
+            Some(vec!["price".to_owned(), "1".to_owned(), "2".to_owned()])
+        );
+        assert_eq!(ColumnList::from(3..=usize::MAX).labels(), None);
+    }
 }
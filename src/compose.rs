@@ -4,15 +4,25 @@
 //! here, so the engine modules never depend on the CLI layer and the
 //! CLI layer never constructs engine internals itself.
 
-use crate::cli_args::{Config, FindPattern, ReorderMode, Replacement};
+use crate::cli_args::{
+    Collect, Config, FindPattern, HistogramSpec, Percentile, ReorderMode, Replacement,
+};
+use crate::collate::{Collation, Compare, SortOrder};
 use crate::columns::{ColumnList, ColumnSpan};
-use crate::file_processor::{FileProcessor, Reorder, RowMode, SortSpec};
+use crate::file_processor::{
+    Context, FileProcessor, Reorder, RowMode, SortColumn, SortSpec, Spill, Top,
+};
+use crate::format::{Formatter, Heading, OutputFormat, Rows};
+use crate::hyperloglog::DEFAULT_PRECISION;
 use crate::predicate::{GrepPredicate, LinePredicate};
-use crate::reduce::{Aggregate, Join, LineReducer, Summarize};
+use crate::reduce::{
+    Aggregate, DistinctMethod, Estimator, Histogram, Join, LineReducer, QuantileMethod, Summarize,
+};
+use crate::schema::Schema;
 use crate::transform::{
-    DeleteColumns, DropEmpty, LineTransform, MapColumns, NumberLines, Pipeline,
-    RegexReplaceInColumns, ReplaceInColumns, ReplaceInColumnsIgnoreCase, SelectColumns, SplitLines,
-    WrapLines,
+    CumulativeSum, DeleteColumns, DelimitFields, Delta, DropEmpty, LineTransform, MapColumns,
+    Moving, MovingWindow, NumberLines, Pipeline, Rank, RegexReplaceInColumns, ReplaceInColumns,
+    ReplaceInColumnsIgnoreCase, SelectColumns, SplitLines, WrapLines, WriteObjects,
 };
 
 /// Assemble the streaming processor implied by the configuration.
@@ -26,6 +36,11 @@ pub fn build_processor(config: &Config) -> FileProcessor {
         .unique
         .then(|| cols.key_or_cols(&config.unique_key));
     let transforms = build_pipeline(config, &mut cols);
+    let ordered = build_ordered(config);
+    let header = config
+        .header
+        .then(|| build_header(config, &cols));
+    let formatter = build_formatter(config, &cols);
 
     FileProcessor {
         rows: config.rows_or_full(),
@@ -33,9 +48,15 @@ pub fn build_processor(config: &Config) -> FileProcessor {
         reorder,
         predicate,
         unique_key_span,
+        unique_collation: config.collation,
         transforms,
+        ordered,
         reducer: build_reducer(config),
         stop_when_matched: config.quiet,
+        context: build_context(config),
+        header,
+        multiline_records: config.quoted,
+        formatter,
     }
 }
 
@@ -47,6 +68,8 @@ pub fn build_processor(config: &Config) -> FileProcessor {
 struct ColumnClaims<'a> {
     config: &'a Config,
     claimed: bool,
+    //the columns `--delimit` writes as fields, once it has picked them
+    delimited: Option<ColumnSpan>,
 }
 
 impl<'a> ColumnClaims<'a> {
@@ -54,6 +77,27 @@ impl<'a> ColumnClaims<'a> {
         ColumnClaims {
             config,
             claimed: false,
+            delimited: None,
+        }
+    }
+
+    /// The columns `--delimit` turns into fields: like a bare selection,
+    /// a `--cols` no operation took; else every field of the schema.
+    fn delimit(&mut self, schema: &Schema) -> ColumnSpan {
+        let span = match self.config.cols.is_some() && !self.claimed {
+            true => self.claim(),
+            false => self.config.span_for(schema.columns()),
+        };
+        self.delimited = Some(span.clone());
+        span
+    }
+
+    /// The members written as a JSON object: like a bare selection, a
+    /// `--cols` no operation took; else every member.
+    fn objects(&mut self) -> ColumnSpan {
+        match self.config.cols.is_some() && !self.claimed {
+            true => self.claim(),
+            false => self.config.span_for(ColumnList::full()),
         }
     }
 
@@ -76,6 +120,11 @@ impl<'a> ColumnClaims<'a> {
     fn cols_claimed(&self) -> bool {
         self.claimed
     }
+
+    /// What `--delimit` picked, if it ran.
+    fn delimited(&self) -> Option<&ColumnSpan> {
+        self.delimited.as_ref()
+    }
 }
 
 /// Build the reducer implied by the configuration, if any: the rows are
@@ -86,6 +135,14 @@ fn build_reducer(config: &Config) -> Option<Box<dyn LineReducer>> {
     if let Some(separator) = &config.join {
         return Some(Box::new(Join::new(separator.clone())));
     }
+    if let Some(histogram) = &config.histogram {
+        return Some(Box::new(Histogram::new(
+            config.span_for(histogram.columns.clone()),
+            histogram.binning,
+            histogram.bar,
+            config.output_separator(),
+        )));
+    }
 
     let aggregates = build_aggregates(config);
     if aggregates.is_empty() {
@@ -108,6 +165,17 @@ fn build_reducer(config: &Config) -> Option<Box<dyn LineReducer>> {
 /// One statistic flag: the columns it reads, and what it computes.
 type Statistic<'a> = (&'a Option<ColumnList>, fn(ColumnSpan) -> Aggregate);
 
+/// A least or greatest value, which also needs to know how the values
+/// compare.
+type Extreme<'a> = (&'a Option<ColumnList>, fn(ColumnSpan, Compare) -> Aggregate);
+
+/// A statistic of the spread, which also needs to know whether the rows
+/// are a sample or the population.
+type Spread<'a> = (
+    &'a Option<ColumnList>,
+    fn(ColumnSpan, Estimator) -> Aggregate,
+);
+
 /// The summary columns, in the order they are printed: the row count
 /// first, then each statistic in the order the flags are declared.
 fn build_aggregates(config: &Config) -> Vec<Aggregate> {
@@ -117,17 +185,80 @@ fn build_aggregates(config: &Config) -> Vec<Aggregate> {
     if summary.count {
         aggregates.push(Aggregate::Count);
     }
-    let statistics: [Statistic; 4] = [
+    let statistics: [Statistic; 2] = [
         (&summary.sum, Aggregate::Sum),
         (&summary.avg, Aggregate::Avg),
+    ];
+    for (columns, aggregate) in statistics {
+        if let Some(columns) = columns {
+            aggregates.push(aggregate(config.span_for(columns.clone())));
+        }
+    }
+    let compare = config
+        .compare
+        .unwrap_or(Compare::Numeric);
+    let extremes: [Extreme; 2] = [
         (&summary.min, Aggregate::Min),
         (&summary.max, Aggregate::Max),
     ];
-    for (columns, aggregate) in statistics {
+    for (columns, aggregate) in extremes {
+        if let Some(columns) = columns {
+            aggregates.push(aggregate(config.span_for(columns.clone()), compare));
+        }
+    }
+    let estimator = match summary.population {
+        true => Estimator::Population,
+        false => Estimator::Sample,
+    };
+    let spreads: [Spread; 3] = [
+        (&summary.stddev, Aggregate::StdDev),
+        (&summary.var, Aggregate::Var),
+        (&summary.skew, Aggregate::Skew),
+    ];
+    for (columns, aggregate) in spreads {
+        if let Some(columns) = columns {
+            aggregates.push(aggregate(config.span_for(columns.clone()), estimator));
+        }
+    }
+    let method = match summary.approximate {
+        true => QuantileMethod::Approximate,
+        false => QuantileMethod::Exact,
+    };
+    if let Some(columns) = &summary.median {
+        aggregates.push(Aggregate::Median(config.span_for(columns.clone()), method));
+    }
+    for Percentile { rank, columns } in &summary.percentiles {
+        let span = config.span_for(columns.clone());
+        aggregates.push(Aggregate::Percentile(*rank, span, method));
+    }
+    if let Some(columns) = &summary.distinct {
+        let method = match summary.approximate {
+            true => DistinctMethod::Approximate {
+                precision: summary
+                    .precision
+                    .unwrap_or(DEFAULT_PRECISION),
+            },
+            false => DistinctMethod::Exact,
+        };
+        aggregates.push(Aggregate::Distinct(
+            config.span_for(columns.clone()),
+            method,
+        ));
+    }
+    let texts: [Statistic; 3] = [
+        (&summary.first, Aggregate::First),
+        (&summary.last, Aggregate::Last),
+        (&summary.mode, Aggregate::Mode),
+    ];
+    for (columns, aggregate) in texts {
         if let Some(columns) = columns {
             aggregates.push(aggregate(config.span_for(columns.clone())));
         }
     }
+    if let Some(Collect { columns, separator }) = &summary.collect {
+        let span = config.span_for(columns.clone());
+        aggregates.push(Aggregate::Collect(span, separator.clone()));
+    }
 
     aggregates
 }
@@ -148,16 +279,107 @@ fn row_mode(config: &Config) -> RowMode {
 /// Attach the column key to the configured reordering, if any.
 fn build_reorder(config: &Config, cols: &mut ColumnClaims) -> Option<Reorder> {
     config.reorder.map(|mode| match mode {
-        ReorderMode::Sort { numeric, reverse } => Reorder::Sort(SortSpec {
-            key_span: cols.key_or_cols(&config.sort_key),
-            numeric,
-            reverse,
-        }),
+        ReorderMode::Sort { numeric, reverse } => Reorder::Sort(Box::new(SortSpec {
+            keys: sort_columns(config, cols, numeric, reverse),
+            spill: config.buffer_size.map(|budget| Spill {
+                budget,
+                dir: config
+                    .temp_dir
+                    .clone()
+                    .unwrap_or_else(std::env::temp_dir),
+            }),
+            top: config.top.map(|count| Top {
+                count,
+                group_span: config
+                    .summary
+                    .group_by
+                    .clone()
+                    .map(|columns| config.span_for(columns)),
+            }),
+        })),
         ReorderMode::Tac => Reorder::Tac,
         ReorderMode::Shuffle => Reorder::Shuffle,
     })
 }
 
+/// The sort keys: each `--sort-key` in turn, sorting by its own
+/// modifiers or else by the flags (`--numeric`, `--version-sort`,
+/// `--human-numeric-sort`, `--reverse`) — or, without any, `--cols`
+/// sorting by those. `--collate` applies to every key.
+fn sort_columns(
+    config: &Config,
+    cols: &mut ColumnClaims,
+    numeric: bool,
+    reverse: bool,
+) -> Vec<SortColumn> {
+    let order = SortOrder {
+        compare: match (config.compare, numeric) {
+            (Some(compare), _) => compare,
+            (None, true) => Compare::Numeric,
+            (None, false) => Compare::Text,
+        },
+        collation: config.collation,
+        reverse,
+    };
+    if config.sort_keys.is_empty() {
+        return vec![SortColumn {
+            span: cols.claim(),
+            order,
+        }];
+    }
+    config
+        .sort_keys
+        .iter()
+        .map(|key| {
+            let mut own = key.order.unwrap_or(order);
+            //`--collate` reaches the keys that do not fold case themselves
+            if own.collation == Collation::Bytes {
+                own.collation = config.collation;
+            }
+            SortColumn {
+                span: config.span_for(key.columns.clone()),
+                order: own,
+            }
+        })
+        .collect()
+}
+
+/// The context around `--grep` matches: asking for either side asks
+/// for both, the other being 0 rows.
+fn build_context(config: &Config) -> Option<Context> {
+    if config.before_context.is_none() && config.after_context.is_none() {
+        return None;
+    }
+    Some(Context {
+        before: config.before_context.unwrap_or(0),
+        after: config.after_context.unwrap_or(0),
+    })
+}
+
+/// The pipeline the header row goes through: only what changes which
+/// columns there are. A bare `--cols` selects them, `--delete --cols`
+/// removes them and `--delimit` makes fields of the same columns as in
+/// the rows, so the header keeps naming the columns below it; every
+/// other transform rewrites content, which a header has none of.
+/// Runs once every other builder had its say on `cols`.
+fn build_header(config: &Config, cols: &ColumnClaims) -> Pipeline {
+    let mut pipeline: Vec<Box<dyn LineTransform>> = Vec::new();
+    if let (Some(span), Some(schema), Some(delimiter)) =
+        (cols.delimited(), &config.schema, &config.delimit)
+    {
+        pipeline.push(Box::new(DelimitFields::new(
+            span.clone(),
+            schema.clone(),
+            delimiter.clone(),
+        )));
+    } else if config.delete && config.cols.is_some() {
+        pipeline.push(Box::new(DeleteColumns::new(config.col_span())));
+    } else if !cols.cols_claimed() && config.cols.is_some() {
+        pipeline.push(Box::new(SelectColumns::new(config.col_span())));
+    }
+    Pipeline::new(pipeline)
+}
+
 /// Build the row filter implied by the configuration, if any.
 fn build_predicate(config: &Config, cols: &mut ColumnClaims) -> Option<Box<dyn LinePredicate>> {
     config.grep.as_ref().map(|pattern| {
@@ -217,6 +439,17 @@ fn build_pipeline(config: &Config, cols: &mut ColumnClaims) -> Pipeline {
         pipeline.push(Box::new(MapColumns::trim(cols.claim())));
     }
 
+    //delimiting comes after the rewriting transforms, whose columns are
+    //the record's chars, and turns the record into fields
+    if let (Some(schema), Some(delimiter)) = (&config.schema, &config.delimit) {
+        let span = cols.delimit(schema);
+        pipeline.push(Box::new(DelimitFields::new(
+            span,
+            schema.clone(),
+            delimiter.clone(),
+        )));
+    }
+
     //splitting comes after the rewriting transforms (they are scoped to
     //columns of the original line) and before wrapping, which then cuts
     //each piece to width
@@ -233,11 +466,58 @@ fn build_pipeline(config: &Config, cols: &mut ColumnClaims) -> Pipeline {
     if config.drop_empty {
         pipeline.push(Box::new(DropEmpty));
     }
+    //running columns, like the row numbers, see the settled rows; they
+    //append their field before numbering prefixes its own
+    let key_span = running_key(config);
+    let separator = config.output_separator();
+    if let Some(columns) = &config.cumsum {
+        let span = config.span_for(columns.clone());
+        pipeline.push(Box::new(CumulativeSum::new(
+            span,
+            key_span.clone(),
+            &separator,
+        )));
+    }
+    if let Some(columns) = &config.delta {
+        let span = config.span_for(columns.clone());
+        pipeline.push(Box::new(Delta::new(span, key_span.clone(), &separator)));
+    }
+    if let Some(columns) = config
+        .rank
+        .as_ref()
+        .filter(|_| !sorts(config))
+    {
+        let span = config.span_for(columns.clone());
+        pipeline.push(Box::new(Rank::new(span, key_span.clone(), &separator)));
+    }
+    let moving = [
+        (&config.moving_avg, Moving::Avg),
+        (&config.moving_min, Moving::Min),
+        (&config.moving_max, Moving::Max),
+    ];
+    for (columns, statistic) in moving {
+        if let (Some(columns), Some(size)) = (columns, config.window) {
+            let span = config.span_for(columns.clone());
+            pipeline.push(Box::new(MovingWindow::new(
+                span,
+                statistic,
+                size,
+                key_span.clone(),
+                &separator,
+            )));
+        }
+    }
     //numbering comes after every row is settled — after expansion and
     //after the empties are gone — so the numbers come out contiguous
     if config.number {
         pipeline.push(Box::new(NumberLines::new(config.output_separator())));
     }
+    //JSON written as JSON keeps its members as they are, nested ones
+    //included: the row is written as an object after every transform,
+    //and a bare `--cols` picks the members
+    if writes_objects(config) {
+        pipeline.push(Box::new(WriteObjects::new(cols.objects())));
+    }
 
     //with no operation claiming the column range, `--cols` alone selects
     //the range, mirroring how `--rows` alone selects lines. Everything in
@@ -250,15 +530,207 @@ fn build_pipeline(config: &Config, cols: &mut ColumnClaims) -> Pipeline {
     Pipeline::new(pipeline)
 }
 
+/// Build the transforms run on the rows as they are put out. A rank is
+/// only meaningful over rows in order of the column, and a `--sort`
+/// puts them in order only there, after buffering them all.
+fn build_ordered(config: &Config) -> Pipeline {
+    let mut pipeline: Vec<Box<dyn LineTransform>> = Vec::new();
+    if let Some(columns) = config
+        .rank
+        .as_ref()
+        .filter(|_| sorts(config))
+    {
+        let span = config.span_for(columns.clone());
+        pipeline.push(Box::new(Rank::new(
+            span,
+            running_key(config),
+            config.output_separator(),
+        )));
+    }
+    Pipeline::new(pipeline)
+}
+
+/// Whether the rows are sorted, rather than left in their order or
+/// reversed or shuffled.
+fn sorts(config: &Config) -> bool {
+    matches!(config.reorder, Some(ReorderMode::Sort { .. }))
+}
+
+/// The span keying the running columns' groups (`--group-by`).
+fn running_key(config: &Config) -> Option<ColumnSpan> {
+    config
+        .summary
+        .group_by
+        .clone()
+        .map(|columns| config.span_for(columns))
+}
+
+/// Whether the rows are written as the JSON objects they were read as:
+/// JSON in (`--jsonl`), JSON out, and no summary in between.
+fn writes_objects(config: &Config) -> bool {
+    config.jsonl
+        && matches!(
+            config.output_format,
+            Some(OutputFormat::Json | OutputFormat::Jsonl)
+        )
+        && build_aggregates(config).is_empty()
+        && config.histogram.is_none()
+}
+
+/// Build the formatter of `--output-format`, if given. The columns are
+/// named by the header row; a summary's by the columns keying it and
+/// the statistics; the fields `--delimit` made by their schema names; a
+/// bare selection's by the labels the columns were written with —
+/// failing which, they go by their positions.
+/// Runs once every other builder had its say on `cols`.
+fn build_formatter(config: &Config, cols: &ColumnClaims) -> Option<Formatter> {
+    let format = config.output_format?;
+    if writes_objects(config) {
+        return Some(Formatter::new(format, Rows::Objects, Heading::Positions));
+    }
+
+    let aggregates = build_aggregates(config);
+    let summarized = config.histogram.is_some() || !aggregates.is_empty();
+    //the rows `--delimit` made are split on its delimiter, not the
+    //output's, into the fields the span picked — as CSV, since a value
+    //holding the delimiter was quoted
+    let delimited = match (&config.schema, &config.delimit, cols.delimited()) {
+        (Some(schema), Some(delimiter), Some(span)) if !summarized => {
+            Some((schema, delimiter, span))
+        }
+        _ => None,
+    };
+    let heading = if let Some(histogram) = &config.histogram {
+        histogram_names(config, histogram)
+    } else if !aggregates.is_empty() {
+        summary_names(config, &aggregates)
+    } else if config.header {
+        Heading::FirstRow
+    } else if let Some((schema, _, span)) = delimited {
+        field_names(schema, span.columns())
+    } else if selects_values(config, cols) {
+        labels(config, config.cols.as_ref())
+    } else {
+        Heading::Positions
+    };
+    let rows = match delimited {
+        Some((_, delimiter, _)) => Rows::delimited(delimiter, true, None),
+        None => Rows::delimited(&config.output_separator(), config.quoted, config.quoting),
+    };
+    Some(Formatter::new(format, rows, heading))
+}
+
+/// Whether the rows are just the values a bare `--cols` selects, one
+/// column a field: nothing took `--cols`, nor added a field of its own.
+fn selects_values(config: &Config, cols: &ColumnClaims) -> bool {
+    let values = config.field_delimiter.is_some() || config.jsonl;
+    values && !cols.cols_claimed() && !config.delete && !config.number && !appends_columns(config)
+}
+
+/// Whether running columns are appended to the rows.
+fn appends_columns(config: &Config) -> bool {
+    [
+        &config.cumsum,
+        &config.delta,
+        &config.rank,
+        &config.moving_avg,
+        &config.moving_min,
+        &config.moving_max,
+    ]
+    .iter()
+    .any(|columns| columns.is_some())
+}
+
+/// The names of the fields `--delimit` writes: a field of the schema by
+/// its name, any other range by the chars it spans.
+fn field_names(schema: &Schema, columns: &ColumnList) -> Heading {
+    //a range counted from the end spans chars only a record knows
+    if columns.labels().is_none() {
+        return Heading::Positions;
+    }
+    let names = columns
+        .written()
+        .iter()
+        .map(|range| match schema.name_of(range) {
+            Some(name) => name.to_owned(),
+            None if range.start() == range.end() => range.start().to_string(),
+            None => format!("{}-{}", range.start(), range.end()),
+        })
+        .collect();
+    Heading::Names(names)
+}
+
+/// The names of a summary's columns: those of the group key, then one
+/// per statistic — `count`, or `sum(price)` naming what it reads.
+fn summary_names(config: &Config, aggregates: &[Aggregate]) -> Heading {
+    let Heading::Names(mut names) = labels(config, config.summary.group_by.as_ref()) else {
+        return Heading::Positions;
+    };
+    //counting chars, the key is one value however many columns it has
+    if config.field_delimiter.is_none() && !config.jsonl && !names.is_empty() {
+        names = vec![names.join(",")];
+    }
+    for aggregate in aggregates {
+        let read = aggregate
+            .span()
+            .map(|span| labels(config, Some(span.columns())));
+        names.push(match read {
+            Some(Heading::Names(read)) => format!("{}({})", aggregate.name(), read.join(",")),
+            _ => aggregate.name(),
+        });
+    }
+    Heading::Names(names)
+}
+
+/// The names of a histogram's columns: the value tabulated, or the
+/// bounds of its bins, then `count`, `percent`, and `bar` if drawn.
+fn histogram_names(config: &Config, histogram: &HistogramSpec) -> Heading {
+    let mut names = match (histogram.binning, labels(config, Some(&histogram.columns))) {
+        (Some(_), _) => vec!["from".to_owned(), "to".to_owned()],
+        (None, Heading::Names(names)) => vec![names.join(",")],
+        (None, _) => return Heading::Positions,
+    };
+    names.extend(["count", "percent"].map(str::to_owned));
+    if histogram.bar {
+        names.push("bar".to_owned());
+    }
+    Heading::Names(names)
+}
+
+/// The names of the columns a list reads: what each was written as,
+/// a key path without its leading dot. No list names none.
+fn labels(config: &Config, columns: Option<&ColumnList>) -> Heading {
+    let Some(columns) = columns else {
+        return Heading::Names(Vec::new());
+    };
+    match columns.labels() {
+        Some(labels) if config.jsonl => Heading::Names(
+            labels
+                .iter()
+                .map(|label| {
+                    label
+                        .strip_prefix('.')
+                        .unwrap_or(label)
+                        .to_owned()
+                })
+                .collect(),
+        ),
+        Some(labels) => Heading::Names(labels),
+        None => Heading::Positions,
+    }
+}
+
 #[cfg(test)]
 //tests tweak single flags on a default config; mutating it reads
 //better here than struct-update syntax
 #[allow(clippy::field_reassign_with_default)]
 mod tests {
     use super::*;
-    use crate::columns::ColumnList;
+    use crate::cli_args::SortKey;
+    use crate::columns::{ColumnList, ColumnPart};
     use crate::constants::NEW_LINE;
-    use crate::ranges::RangeSpec;
+    use crate::ranges::RangeBound::{FromEnd, FromStart};
+    use crate::ranges::{RangeBound, RangePart, RangeSpec};
     use crate::transform::Lines;
     use std::io::{self, Cursor};
 
@@ -413,6 +885,103 @@ mod tests {
         assert_eq!(result, "b 10\nc  9\na  2\n");
     }
 
+    #[test]
+    fn top_keeps_the_first_sorted_rows() {
+        let mut config = Config::default();
+        config.reorder = sorted(true, true);
+        config.top = Some(2);
+        config.cols = Some((3..=4).into());
+
+        //of the equal 10s, the first read comes first, as in a stable sort
+        let result = run(config, "a  2\nb 10\nc  9\nd 10\n");
+        assert_eq!(result, "b 10\nd 10\n");
+    }
+
+    #[test]
+    fn sort_keys_break_ties_in_order_each_by_its_own_modifiers() {
+        let mut config = Config::default();
+        config.reorder = sorted(false, false);
+        config.field_delimiter = Some(",".into());
+        config.sort_keys = vec![
+            ColumnList::from(2..=2).into(),
+            SortKey {
+                columns: (3..=3).into(),
+                order: Some(SortOrder {
+                    compare: Compare::Numeric,
+                    reverse: true,
+                    ..SortOrder::default()
+                }),
+            },
+        ];
+
+        //lines equal on both keys keep the order read
+        let result = run(config, "a,x,9\nb,y,10\nc,x,10\nd,x,9\ne,y,2\n");
+        assert_eq!(result, "c,x,10\na,x,9\nd,x,9\nb,y,10\ne,y,2\n");
+    }
+
+    #[test]
+    fn a_sort_key_without_modifiers_sorts_as_the_flags_say() {
+        let mut config = Config::default();
+        config.reorder = sorted(true, true);
+        config.field_delimiter = Some(",".into());
+        config.sort_keys = vec![
+            ColumnList::from(1..=1).into(),
+            SortKey {
+                columns: (2..=2).into(),
+                order: Some(SortOrder::default()),
+            },
+        ];
+
+        let result = run(config, "9,b\n10,b\n9,a\n");
+        assert_eq!(result, "10,b\n9,a\n9,b\n");
+    }
+
+    #[test]
+    fn a_spilled_sort_writes_what_the_sort_in_memory_does() {
+        //every line its own run, and more runs than merge at once
+        let input: String = (0..300)
+            .map(|i| format!("{},{}\n", (i * 37) % 50, i))
+            .collect();
+        let configs: [fn(&mut Config); 3] = [
+            |config| config.reorder = sorted(true, false),
+            |config| {
+                config.reorder = sorted(true, true);
+                config.unique = true;
+                config.unique_key = Some((1..=1).into());
+            },
+            |config| {
+                config.reorder = sorted(false, false);
+                config.summary.group_by = Some((1..=1).into());
+                config.summary.collect = Some(Collect {
+                    columns: (2..=2).into(),
+                    separator: " ".to_owned(),
+                });
+            },
+        ];
+        for configure in configs {
+            let mut config = Config::default();
+            config.field_delimiter = Some(",".into());
+            config.sort_keys = vec![ColumnList::from(1..=1).into()];
+            configure(&mut config);
+            let in_memory = run(config.clone(), &input);
+
+            config.buffer_size = Some(1);
+            assert_eq!(run(config, &input), in_memory);
+        }
+    }
+
+    #[test]
+    fn top_is_kept_per_group_in_first_seen_order() {
+        let mut config = Config::default();
+        config.reorder = sorted(false, false);
+        config.top = Some(1);
+        config.sort_keys = vec![ColumnList::from(2..=2).into()];
+        config.summary.group_by = Some((1..=1).into());
+
+        let result = run(config, "bz\nay\nbx\nac\nbw\n");
+        assert_eq!(result, "bw\nac\n");
+    }
+
     #[test]
     fn sort_preserves_crlf_terminators() {
         let mut config = Config::default();
@@ -466,7 +1035,6 @@ mod tests {
 
     #[test]
     fn end_relative_rows_select_from_the_end() {
-        use crate::ranges::RangeBound::FromEnd;
         let mut config = Config::default();
         config.rows = Some(RangeSpec::new(vec![(FromEnd(2), FromEnd(1))]));
 
@@ -477,7 +1045,6 @@ mod tests {
 
     #[test]
     fn end_relative_rows_combine_with_delete() {
-        use crate::ranges::RangeBound::FromEnd;
         let mut config = Config::default();
         config.delete = true;
         config.rows = Some(RangeSpec::new(vec![(FromEnd(1), FromEnd(1))]));
@@ -488,7 +1055,6 @@ mod tests {
 
     #[test]
     fn end_relative_rows_combine_with_sort() {
-        use crate::ranges::RangeBound::FromEnd;
         let mut config = Config::default();
         config.reorder = sorted(false, false);
         config.rows = Some(RangeSpec::new(vec![(FromEnd(3), FromEnd(1))]));
@@ -497,6 +1063,148 @@ mod tests {
         assert_eq!(result, "a\nb\nc\n");
     }
 
+    /// A reader that fails, standing in for input that never ends.
+    struct Unending;
+
+    impl io::Read for Unending {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("still reading"))
+        }
+    }
+
+    #[test]
+    fn end_relative_rows_stream_all_but_the_tail() {
+        use std::io::Read;
+        let mut config = Config::default();
+        config.delete = true;
+        config.rows = Some(RangeSpec::new(vec![(FromEnd(2), FromEnd(1))]));
+
+        //the lines before the last two are written before the input ends
+        let processor = build_processor(&config);
+        let mut output = Vec::new();
+        let input = io::BufReader::new(Cursor::new("one\ntwo\nthree\nfour\n").chain(Unending));
+        processor
+            .run(input, &mut output)
+            .expect_err("the input fails");
+        assert_eq!(output, b"one\ntwo\n");
+    }
+
+    fn block_rows(from: &str, from_offset: isize, to: &str, to_offset: isize) -> RangeSpec {
+        use crate::ranges::PatternBound;
+        let bound = |source: &str, offset| {
+            RangeBound::Pattern(PatternBound {
+                pattern: regex::Regex::new(source).unwrap(),
+                offset,
+            })
+        };
+        RangeSpec::new(vec![(bound(from, from_offset), bound(to, to_offset))])
+    }
+
+    #[test]
+    fn pattern_rows_select_every_block() {
+        let mut config = Config::default();
+        config.rows = Some(block_rows("BEGIN", 0, "END", 0));
+
+        let result = run(config, "a\nBEGIN\nb\nEND\nc\nBEGIN\nd\nEND\n");
+        assert_eq!(result, "BEGIN\nb\nEND\nBEGIN\nd\nEND\n");
+    }
+
+    #[test]
+    fn pattern_rows_with_offsets_leave_the_markers_out() {
+        let mut config = Config::default();
+        config.rows = Some(block_rows("BEGIN", 1, "END", -1));
+
+        //the row before an END waits for it, and still comes out in order
+        let result = run(config, "a\nBEGIN\nb\nc\nEND\nd\n");
+        assert_eq!(result, "b\nc\n");
+    }
+
+    #[test]
+    fn pattern_rows_combine_with_delete() {
+        let mut config = Config::default();
+        config.delete = true;
+        config.rows = Some(block_rows("BEGIN", 1, "END", -1));
+
+        //the markers survive, what lies between them goes — and the kept
+        //lines held back for the lookahead are not lost at the end
+        let result = run(config, "a\nBEGIN\nb\nEND\nc\nd");
+        assert_eq!(result, "a\nBEGIN\nEND\nc\nd");
+    }
+
+    #[test]
+    fn pattern_rows_combine_with_end_relative_rows() {
+        let mut config = Config::default();
+        let mut parts = vec![(FromEnd(1), FromEnd(1)).into()];
+        parts.extend(
+            block_rows("BEGIN", 1, "END", -1)
+                .parts()
+                .to_vec(),
+        );
+        config.rows = Some(RangeSpec::new(parts));
+
+        let result = run(config, "a\nBEGIN\nb\nEND\nc\n");
+        assert_eq!(result, "b\nc\n");
+    }
+
+    fn stepped_rows(from: RangeBound, to: RangeBound, step: usize) -> RangeSpec {
+        RangeSpec::new(vec![RangePart { from, to, step }])
+    }
+
+    const NUMBERS: &str = "1\n2\n3\n4\n5\n6\n7\n8\n";
+
+    #[test]
+    fn stepped_rows_select_every_nth_row() {
+        let mut config = Config::default();
+        config.rows = Some(stepped_rows(FromStart(2), FromStart(usize::MAX), 3));
+
+        let result = run(config, NUMBERS);
+        assert_eq!(result, "2\n5\n8\n");
+    }
+
+    #[test]
+    fn stepped_rows_combine_with_delete() {
+        let mut config = Config::default();
+        config.delete = true;
+        config.rows = Some(stepped_rows(FromStart(1), FromStart(usize::MAX), 2));
+
+        let result = run(config, NUMBERS);
+        assert_eq!(result, "2\n4\n6\n8\n");
+    }
+
+    #[test]
+    fn stepped_rows_combine_with_grep() {
+        let mut config = Config::default();
+        config.rows = Some(stepped_rows(FromStart(1), FromStart(usize::MAX), 2));
+        config.grep = Some(regex::Regex::new("[1-5]").unwrap());
+
+        //of rows 1, 3, 5 and 7, only those matching
+        let result = run(config, NUMBERS);
+        assert_eq!(result, "1\n3\n5\n");
+    }
+
+    #[test]
+    fn stepped_rows_count_from_an_end_relative_start() {
+        let mut config = Config::default();
+        config.rows = Some(stepped_rows(FromEnd(5), FromEnd(1), 2));
+
+        //the last five rows are 4-8: every other one from row 4
+        let result = run(config, NUMBERS);
+        assert_eq!(result, "4\n6\n8\n");
+    }
+
+    #[test]
+    fn stepped_rows_count_from_each_block() {
+        let mut config = Config::default();
+        let part = RangePart {
+            step: 2,
+            ..block_rows("BEGIN", 0, "END", 0).parts()[0].clone()
+        };
+        config.rows = Some(RangeSpec::new(vec![part]));
+
+        let result = run(config, "BEGIN\na\nb\nEND\nx\nBEGIN\nc\nEND\n");
+        assert_eq!(result, "BEGIN\nb\nBEGIN\nEND\n");
+    }
+
     #[test]
     fn grep_keeps_only_matching_lines() {
         let mut config = Config::default();
@@ -516,6 +1224,137 @@ mod tests {
         assert_eq!(result, "b INFO\n");
     }
 
+    const LOG: &str = "a\nb\nERR1\nc\nd\ne\nf\nERR2\ng\nERR3\nh\n";
+
+    fn grep_context(before: usize, after: usize) -> Config {
+        let mut config = Config::default();
+        config.grep = Some(regex::Regex::new("ERR").unwrap());
+        config.before_context = Some(before);
+        config.after_context = Some(after);
+        config
+    }
+
+    #[test]
+    fn grep_context_takes_the_rows_around_each_match() {
+        let result = run(grep_context(1, 1), LOG);
+        assert_eq!(result, "b\nERR1\nc\n--\nf\nERR2\ng\nERR3\nh\n");
+    }
+
+    #[test]
+    fn grep_context_merges_overlapping_windows() {
+        //ERR1's after-context meets ERR2's before-context: one group
+        let result = run(grep_context(2, 2), LOG);
+        assert_eq!(result, LOG);
+    }
+
+    #[test]
+    fn grep_context_of_zero_still_separates_groups() {
+        let result = run(grep_context(0, 0), LOG);
+        assert_eq!(result, "ERR1\n--\nERR2\n--\nERR3\n");
+    }
+
+    #[test]
+    fn grep_context_is_deleted_with_its_match() {
+        let mut config = grep_context(1, 0);
+        config.delete = true;
+
+        let result = run(config, LOG);
+        assert_eq!(result, "a\nc\nd\ne\nh\n");
+    }
+
+    #[test]
+    fn grep_context_stays_within_the_row_range() {
+        let mut config = grep_context(1, 0);
+        config.rows = Some((4..=usize::MAX).into());
+
+        //ERR1 lies outside the range: it neither matches nor has context
+        let result = run(config, LOG);
+        assert_eq!(result, "f\nERR2\ng\nERR3\n");
+    }
+
+    #[test]
+    fn grep_context_is_transformed_like_its_match() {
+        let mut config = grep_context(0, 1);
+        config.upper = true;
+
+        let result = run(config, "x\nERR\ny\nz\n");
+        assert_eq!(result, "ERR\nY\n");
+    }
+
+    #[test]
+    fn grep_context_does_not_count_as_a_match() {
+        let processor = build_processor(&grep_context(1, 1));
+        let mut output = Vec::new();
+        let outcome = processor
+            .run(Cursor::new("a\nb\n"), &mut output)
+            .unwrap();
+        assert!(!outcome.matched);
+        assert!(output.is_empty());
+    }
+
+    const PRICES: &str = "name,price\nb,2\na,10\nc,3\n";
+
+    fn with_header() -> Config {
+        let mut config = Config::default();
+        config.header = true;
+        config.field_delimiter = Some(",".into());
+        config
+    }
+
+    #[test]
+    fn header_stays_ahead_of_a_sort() {
+        let mut config = with_header();
+        config.reorder = sorted(false, false);
+
+        let result = run(config, PRICES);
+        assert_eq!(result, "name,price\na,10\nb,2\nc,3\n");
+    }
+
+    #[test]
+    fn header_is_not_filtered_or_counted_as_a_row() {
+        let mut config = with_header();
+        config.grep = Some(regex::Regex::new("^a").unwrap());
+        config.rows = Some((2..=usize::MAX).into());
+
+        //row 2 is the second one below the header
+        let result = run(config, PRICES);
+        assert_eq!(result, "name,price\na,10\n");
+    }
+
+    #[test]
+    fn header_is_projected_with_the_columns() {
+        let mut config = with_header();
+        config.cols = Some((2..=2).into());
+        let result = run(config, PRICES);
+        assert_eq!(result, "price\n2\n10\n3\n");
+
+        let mut config = with_header();
+        config.delete = true;
+        config.cols = Some((1..=1).into());
+        let result = run(config, PRICES);
+        assert_eq!(result, "price\n2\n10\n3\n");
+    }
+
+    #[test]
+    fn header_is_not_rewritten_by_transforms() {
+        let mut config = with_header();
+        config.cols = Some((1..=1).into());
+        config.upper = true;
+        config.number = true;
+
+        let result = run(config, PRICES);
+        assert_eq!(result, "name,price\n1,B,2\n2,A,10\n3,C,3\n");
+    }
+
+    #[test]
+    fn header_is_dropped_by_a_summary() {
+        let mut config = with_header();
+        config.summary.sum = Some((2..=2).into());
+
+        let result = run(config, PRICES);
+        assert_eq!(result, "15\n");
+    }
+
     #[test]
     fn grep_filters_within_row_range_only() {
         let mut config = Config::default();
@@ -568,7 +1407,6 @@ mod tests {
 
     #[test]
     fn reorder_keeps_noncontiguous_segments_in_place() {
-        use crate::ranges::RangeBound::FromStart;
         //deleting a column keeps the lines outside the row range, so
         //each selected segment must reorder in place instead of
         //drifting past the kept lines in between
@@ -605,6 +1443,16 @@ mod tests {
         assert_eq!(result, "b\na\nc\n");
     }
 
+    #[test]
+    fn unique_compares_keys_as_collated() {
+        let mut config = Config::default();
+        config.unique = true;
+        config.collation = Collation::Accents;
+
+        let result = run(config, "Zoë\nzoe\nZOË\nzoey\n");
+        assert_eq!(result, "Zoë\nzoey\n");
+    }
+
     #[test]
     fn unique_compares_only_key_columns() {
         let mut config = Config::default();
@@ -621,7 +1469,7 @@ mod tests {
         let mut config = Config::default();
         config.unique = true;
         config.cols = Some((2..=2).into());
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
 
         //"b," and "c," share the empty field 2 as their key
         let result = run(config, "a,1\nb,\nc,\nd,1\n");
@@ -643,9 +1491,9 @@ mod tests {
         //the motivating case: sort by field 1, replace inside field 2
         let mut config = Config::default();
         config.reorder = sorted(false, false);
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.cols = Some((2..=2).into());
-        config.sort_key = Some((1..=1).into());
+        config.sort_keys = vec![ColumnList::from(1..=1).into()];
         config.replacements = vec![literal("x", "X")];
 
         let result = run(config, "b,x\na,x\n");
@@ -660,7 +1508,7 @@ mod tests {
         let mut config = Config::default();
         config.reorder = sorted(false, false);
         config.cols = Some((3..=5).into());
-        config.sort_key = Some((1..=1).into());
+        config.sort_keys = vec![ColumnList::from(1..=1).into()];
 
         let result = run(config, "xxb..\nxxa..\n");
         assert_eq!(result, "a..\nb..\n");
@@ -670,7 +1518,7 @@ mod tests {
     fn unique_key_is_independent_of_cols() {
         let mut config = Config::default();
         config.unique = true;
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.cols = Some((2..=2).into());
         config.unique_key = Some((1..=1).into());
         config.upper = true;
@@ -819,7 +1667,7 @@ mod tests {
     #[test]
     fn sort_with_group_by_reports_groups_in_sorted_order() {
         let mut config = Config::default();
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.reorder = sorted(false, false);
         config.summary.group_by = Some((1..=1).into());
         config.summary.sum = Some((2..=2).into());
@@ -852,7 +1700,7 @@ mod tests {
     #[test]
     fn group_by_summarizes_each_key() {
         let mut config = Config::default();
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.summary.group_by = Some((1..=1).into());
         config.summary.count = true;
         config.summary.sum = Some((2..=2).into());
@@ -887,7 +1735,7 @@ mod tests {
     #[test]
     fn quoted_fields_survive_a_delimiter_inside_them() {
         let mut config = Config::default();
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.quoted = true;
         config.cols = Some((2..=2).into());
 
@@ -899,7 +1747,7 @@ mod tests {
     #[test]
     fn quoted_fields_permute_and_rejoin_as_csv() {
         let mut config = Config::default();
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.quoted = true;
         config.cols = Some(ColumnList::new(vec![3..=3, 1..=1]));
 
@@ -908,18 +1756,80 @@ mod tests {
         assert_eq!(result, "d,a\n");
 
         let mut config = Config::default();
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.quoted = true;
         config.cols = Some(ColumnList::new(vec![2..=2, 1..=1]));
         let result = run(config, "a,\"b,c\",d\n");
         assert_eq!(result, "\"b,c\",a\n");
     }
 
+    fn quoted_csv() -> Config {
+        let mut config = Config::default();
+        config.field_delimiter = Some(",".into());
+        config.quoted = true;
+        config
+    }
+
+    const MULTILINE_CSV: &str =
+        "3,\"three\nlines\nlong\"\n1,one\n2,\"two, \"\"quoted\"\"\nlines\"\n";
+
+    #[test]
+    fn quoted_records_span_lines_when_sorted() {
+        let mut config = quoted_csv();
+        config.cols = Some((1..=1).into());
+        config.reorder = sorted(true, false);
+
+        let result = run(config, MULTILINE_CSV);
+        assert_eq!(
+            result,
+            "1,one\n2,\"two, \"\"quoted\"\"\nlines\"\n3,\"three\nlines\nlong\"\n"
+        );
+    }
+
+    #[test]
+    fn quoted_records_are_rows_to_filters_and_summaries() {
+        let mut config = quoted_csv();
+        config.cols = Some((2..=2).into());
+        config.grep = Some(regex::Regex::new("lines").unwrap());
+        config.summary.count = true;
+
+        //two records mention `lines`, however many lines they take
+        assert_eq!(run(config, MULTILINE_CSV), "2\n");
+
+        let mut config = quoted_csv();
+        config.cols = Some((2..=2).into());
+        config.rows = Some(RangeSpec::from(2..=3));
+        assert_eq!(
+            run(config, MULTILINE_CSV),
+            "one\n\"two, \"\"quoted\"\"\nlines\"\n"
+        );
+    }
+
+    #[test]
+    fn an_unclosed_quote_ends_the_record_with_the_input() {
+        let mut config = quoted_csv();
+        config.cols = Some((1..=1).into());
+        config.reorder = sorted(false, true);
+
+        let result = run(config, "a,ok\nb,\"open\nc,never closed\n");
+        assert_eq!(result, "b,\"open\nc,never closed\na,ok\n");
+    }
+
+    #[test]
+    fn without_quoted_a_line_is_a_row() {
+        let mut config = Config::default();
+        config.field_delimiter = Some(",".into());
+        config.cols = Some((1..=1).into());
+        config.summary.count = true;
+
+        assert_eq!(run(config, MULTILINE_CSV), "6\n");
+    }
+
     #[test]
     fn column_list_selects_the_parts_in_the_written_order() {
         let mut config = Config::default();
         config.cols = Some(ColumnList::new(vec![3..=3, 1..=1, 2..=2]));
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
 
         //an awk-style projection: fields reordered, rejoined by the delimiter
         let result = run(config, "a,b,c\nx,y,z\n");
@@ -930,7 +1840,7 @@ mod tests {
     fn column_list_joins_on_the_output_delimiter() {
         let mut config = Config::default();
         config.cols = Some(ColumnList::new(vec![2..=2, 1..=1]));
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.output_delimiter = Some(";".to_owned());
 
         let result = run(config, "a,b\n");
@@ -942,7 +1852,7 @@ mod tests {
         let mut config = Config::default();
         config.delete = true;
         config.cols = Some(ColumnList::new(vec![1..=1, 3..=3]));
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
 
         let result = run(config, "a,b,c\n");
         assert_eq!(result, "b\n");
@@ -952,7 +1862,7 @@ mod tests {
     fn column_list_scopes_a_write_to_every_part() {
         let mut config = Config::default();
         config.cols = Some(ColumnList::new(vec![1..=1, 3..=3]));
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
         config.upper = true;
 
         //writing works on the normalized set, so the order is irrelevant
@@ -964,7 +1874,7 @@ mod tests {
     fn field_mode_selects_delimited_fields() {
         let mut config = Config::default();
         config.cols = Some((2..=2).into());
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
 
         let result = run(config, "a,bb,c\nx,yy,z\n");
         assert_eq!(result, "bb\nyy\n");
@@ -975,7 +1885,7 @@ mod tests {
         let mut config = Config::default();
         config.delete = true;
         config.cols = Some((2..=2).into());
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
 
         let result = run(config, "a,b,c\nx,y\n");
         assert_eq!(result, "a,c\nx\n");
@@ -986,7 +1896,7 @@ mod tests {
         let mut config = Config::default();
         config.reorder = sorted(false, false);
         config.cols = Some((2..=2).into());
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
 
         let result = run(config, "x,c\ny,a\nz,b\n");
         assert_eq!(result, "y,a\nz,b\nx,c\n");
@@ -997,7 +1907,7 @@ mod tests {
         let mut config = Config::default();
         config.unique = true;
         config.cols = Some((1..=1).into());
-        config.field_delimiter = Some(",".to_owned());
+        config.field_delimiter = Some(",".into());
 
         let result = run(config, "a,1\na,2\nb,1\n");
         assert_eq!(result, "a,1\nb,1\n");
@@ -1121,4 +2031,159 @@ mod tests {
         let result = run(config, "  a   b,c  \n   \n");
         assert_eq!(result, "1\ta \n2\tb\n3\tc\n");
     }
+
+    const RECORDS: &str = "00042ACME Ltd -0012.5\n00007Bolt     0000001\n";
+
+    fn delimited() -> Config {
+        let mut config = Config::default();
+        config.schema =
+            Some(Schema::parse("id 1-5 number\nname 6-14\namount 15-21 number").unwrap());
+        config.delimit = Some(",".to_owned());
+        config
+    }
+
+    #[test]
+    fn delimit_writes_every_schema_field() {
+        let result = run(delimited(), RECORDS);
+        assert_eq!(result, "42,ACME Ltd,-12.5\n7,Bolt,1\n");
+    }
+
+    #[test]
+    fn delimit_writes_the_selected_columns_in_order() {
+        let mut config = delimited();
+        config.cols = Some(ColumnList::new(vec![15..=21, 6..=14]));
+
+        let result = run(config, RECORDS);
+        assert_eq!(result, "-12.5,ACME Ltd\n1,Bolt\n");
+    }
+
+    #[test]
+    fn delimit_quotes_a_value_holding_the_delimiter() {
+        let result = run(
+            delimited(),
+            "00042ACME, Ltd-0012.5\n00007\"Bolt\"   0000001\n",
+        );
+        assert_eq!(result, "42,\"ACME, Ltd\",-12.5\n7,\"\"\"Bolt\"\"\",1\n");
+    }
+
+    #[test]
+    fn delimit_also_splits_the_header() {
+        let mut config = delimited();
+        config.header = true;
+
+        let result = run(config, &format!("ID   NAME     AMOUNT \n{RECORDS}"));
+        assert_eq!(result, "ID,NAME,AMOUNT\n42,ACME Ltd,-12.5\n7,Bolt,1\n");
+    }
+
+    #[test]
+    fn delimited_fields_are_formatted_by_their_schema_names() {
+        let mut config = delimited();
+        config.output_format = Some(OutputFormat::Json);
+        let result = run(config, RECORDS);
+        assert_eq!(
+            result,
+            "[\n{\"id\":42,\"name\":\"ACME Ltd\",\"amount\":-12.5},\n{\"id\":7,\"name\":\"Bolt\",\"amount\":1}\n]\n"
+        );
+
+        //a range the schema does not name goes by its chars
+        let mut config = delimited();
+        config.output_format = Some(OutputFormat::Csv);
+        config.cols = Some(ColumnList::new(vec![15..=21, 6..=9]));
+        let result = run(config, RECORDS);
+        assert_eq!(result, "amount,6-9\n-12.5,ACME\n1,Bolt\n");
+
+        //a value holding the delimiter stays one column
+        let mut config = delimited();
+        config.output_format = Some(OutputFormat::Jsonl);
+        let result = run(config, "00042ACME, Ltd-0012.5\n");
+        assert_eq!(
+            result,
+            "{\"id\":42,\"name\":\"ACME, Ltd\",\"amount\":-12.5}\n"
+        );
+    }
+
+    const ORDERS: &str = "{\"id\": 2, \"user\": {\"name\": \"bob\"}, \"total\": 10}\n\
+                          {\"id\": 1, \"user\": {\"name\": \"ann\"}, \"total\": 3}\n";
+
+    fn jsonl() -> Config {
+        let mut config = Config::default();
+        config.jsonl = true;
+        config
+    }
+
+    #[test]
+    fn jsonl_selects_and_filters_by_key_path() {
+        let mut config = jsonl();
+        config.cols = Some(ColumnList::with_names(vec![
+            ColumnPart::Name(".user.name".to_owned()),
+            ColumnPart::Name(".total".to_owned()),
+        ]));
+        let result = run(config, ORDERS);
+        assert_eq!(result, "bob\t10\nann\t3\n");
+
+        let mut config = jsonl();
+        config.grep = Some(regex::Regex::new("^ann$").unwrap());
+        config.cols = Some(ColumnList::with_names(vec![ColumnPart::Name(
+            "user.name".to_owned(),
+        )]));
+        config.upper = true;
+        let result = run(config, ORDERS);
+        assert_eq!(
+            result,
+            "{\"id\": 1, \"user\": {\"name\": \"ANN\"}, \"total\": 3}\n"
+        );
+    }
+
+    #[test]
+    fn jsonl_sorts_by_a_member() {
+        let mut config = jsonl();
+        config.reorder = sorted(true, false);
+        config.sort_keys =
+            vec![ColumnList::with_names(vec![ColumnPart::Name(".total".to_owned())]).into()];
+        let result = run(config, ORDERS);
+        assert!(result.starts_with("{\"id\": 1,"), "{result}");
+    }
+
+    #[test]
+    fn rows_are_formatted_under_the_header() {
+        let mut config = with_header();
+        config.output_format = Some(OutputFormat::Json);
+        let result = run(config, PRICES);
+        assert_eq!(
+            result,
+            "[\n{\"name\":\"b\",\"price\":2},\n{\"name\":\"a\",\"price\":10},\n{\"name\":\"c\",\"price\":3}\n]\n"
+        );
+
+        //a selection is named by the columns it kept
+        let mut config = with_header();
+        config.output_format = Some(OutputFormat::Markdown);
+        config.cols = Some((2..=2).into());
+        let result = run(config, PRICES);
+        assert_eq!(result, "| price |\n| --- |\n| 2 |\n| 10 |\n| 3 |\n");
+    }
+
+    #[test]
+    fn summaries_are_named_by_key_and_statistic() {
+        let mut config = with_header();
+        config.output_format = Some(OutputFormat::Csv);
+        config.summary.count = true;
+        config.summary.sum = Some((2..=2).into());
+        config.summary.group_by = Some((1..=1).into());
+        let result = run(config, "name,price\nb,2\na,10\nb,3\n");
+        assert_eq!(result, "1,count,sum(2)\nb,2,5\na,1,10\n");
+    }
+
+    #[test]
+    fn jsonl_is_written_as_the_objects_it_was_read_as() {
+        let mut config = jsonl();
+        config.output_format = Some(OutputFormat::Json);
+        config.cols = Some(ColumnList::with_names(vec![ColumnPart::Name(
+            ".user".to_owned(),
+        )]));
+        let result = run(config, ORDERS);
+        assert_eq!(
+            result,
+            "[\n{\"user\":{\"name\": \"bob\"}},\n{\"user\":{\"name\": \"ann\"}}\n]\n"
+        );
+    }
 }
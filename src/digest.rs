@@ -0,0 +1,187 @@
+//! A t-digest (Dunning's merging variant): a sketch of a distribution
+//! that answers quantile queries in bounded memory, for `--approximate`
+//! percentiles over inputs too large to keep every value of.
+//!
+//! Values are clustered into centroids, each a mean and a weight. The
+//! scale function lets a centroid grow large around the median while
+//! keeping those near the tails small, so the extreme quantiles — the
+//! p99 of a latency report — stay the most accurate. New values wait in
+//! a buffer and are merged in batches.
+
+use std::f64::consts::PI;
+
+/// How finely the distribution is kept: about this many centroids at
+/// most, whatever the number of values.
+const COMPRESSION: f64 = 100.0;
+
+/// How many values wait in the buffer before they are merged.
+const BUFFER: usize = 500;
+
+#[derive(Debug, Clone, Copy)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TDigest {
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new() -> TDigest {
+        TDigest::default()
+    }
+
+    /// Take one value into the sketch.
+    pub fn add(&mut self, value: f64) {
+        if self.centroids.is_empty() && self.buffer.is_empty() {
+            self.min = value;
+            self.max = value;
+        }
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+        if self.buffer.len() >= BUFFER {
+            self.merge();
+        }
+    }
+
+    /// The value below which the fraction `q` (0 to 1) of the values
+    /// lie, interpolated between centroids; `None` without values.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let mut digest = self.clone();
+        digest.merge();
+        let centroids = &digest.centroids;
+        let total: f64 = centroids
+            .iter()
+            .map(|centroid| centroid.weight)
+            .sum();
+        if centroids.is_empty() {
+            return None;
+        }
+        if centroids.len() == 1 {
+            return Some(centroids[0].mean);
+        }
+
+        //each centroid stands at the middle of the weight it holds; the
+        //first and last lead out to the smallest and largest value
+        let target = q.clamp(0.0, 1.0) * total;
+        let mut before = 0.0;
+        let mut previous = (0.0, digest.min);
+        for centroid in centroids {
+            let center = before + centroid.weight / 2.0;
+            if target < center {
+                return Some(interpolate(previous, (center, centroid.mean), target));
+            }
+            previous = (center, centroid.mean);
+            before += centroid.weight;
+        }
+        Some(interpolate(previous, (total, digest.max), target))
+    }
+
+    /// Merge the buffered values into the centroids: everything sorted
+    /// by mean, then neighbours combined while the scale function allows
+    /// a centroid that much weight.
+    fn merge(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all: Vec<Centroid> = self.centroids.clone();
+        all.extend(
+            self.buffer
+                .drain(..)
+                .map(|value| Centroid {
+                    mean: value,
+                    weight: 1.0,
+                }),
+        );
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total: f64 = all
+            .iter()
+            .map(|centroid| centroid.weight)
+            .sum();
+
+        let mut merged = Vec::new();
+        let mut current = all[0];
+        let mut before = 0.0;
+        let mut limit = total * scale_inverse(scale(0.0) + 1.0);
+        for &next in &all[1..] {
+            if before + current.weight + next.weight <= limit {
+                let weight = current.weight + next.weight;
+                current.mean += (next.mean - current.mean) * next.weight / weight;
+                current.weight = weight;
+            } else {
+                before += current.weight;
+                merged.push(current);
+                limit = total * scale_inverse(scale(before / total) + 1.0);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+}
+
+/// The scale function k₁: how many "units" of centroid the quantiles up
+/// to `q` may hold. Its slope is steepest at the tails.
+fn scale(q: f64) -> f64 {
+    COMPRESSION / (2.0 * PI) * (2.0 * q - 1.0).asin()
+}
+
+/// The quantile at which the scale function reaches `k`.
+fn scale_inverse(k: f64) -> f64 {
+    if k >= COMPRESSION / 4.0 {
+        return 1.0;
+    }
+    ((2.0 * PI * k / COMPRESSION).sin() + 1.0) / 2.0
+}
+
+/// The value at `target` on the line through two (weight, value) points.
+fn interpolate(from: (f64, f64), to: (f64, f64), target: f64) -> f64 {
+    if to.0 <= from.0 {
+        return to.1;
+    }
+    from.1 + (to.1 - from.1) * (target - from.0) / (to.0 - from.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_digest_has_no_quantiles() {
+        assert_eq!(TDigest::new().quantile(0.5), None);
+    }
+
+    #[test]
+    fn a_few_values_are_kept_exactly() {
+        let mut digest = TDigest::new();
+        for value in [3.0, 1.0, 2.0] {
+            digest.add(value);
+        }
+        assert_eq!(digest.quantile(0.0), Some(1.0));
+        assert_eq!(digest.quantile(0.5), Some(2.0));
+        assert_eq!(digest.quantile(1.0), Some(3.0));
+    }
+
+    #[test]
+    fn many_values_stay_close_and_bounded() {
+        let mut digest = TDigest::new();
+        //a shuffled 1..=100000, without a random source
+        for index in 0..100_000u64 {
+            digest.add(((index * 7919) % 100_000 + 1) as f64);
+        }
+        for (q, expected) in [(0.5, 50_000.0), (0.95, 95_000.0), (0.99, 99_000.0)] {
+            let estimate = digest.quantile(q).unwrap();
+            assert!(
+                (estimate - expected).abs() < expected * 0.005,
+                "q{q}: {estimate}"
+            );
+        }
+        assert!(digest.centroids.len() <= 2 * COMPRESSION as usize);
+    }
+}
@@ -4,18 +4,23 @@
 //! parts (see [`crate::compose`]), so this module stays independent of
 //! the CLI layer.
 
+use crate::collate::{Collation, SortOrder, SortValue};
 use crate::columns::ColumnSpan;
 use crate::constants::NEW_LINE;
+use crate::format::{FormatWriter, Formatter};
 use crate::predicate::LinePredicate;
-use crate::ranges::{RangeSet, RangeSpec};
+use crate::ranges::{RangeSpec, RowSelector};
 use crate::reduce::LineReducer;
+use crate::spill::{self, MERGE_WIDTH, Run};
 use crate::text;
 use crate::transform::{Lines, Pipeline};
 
 use bstr::io::BufReadExt;
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::io;
 use std::io::{BufRead, Write};
+use std::path::PathBuf;
 use std::str::from_utf8;
 
 /// A buffered line split into content and its original terminator.
@@ -29,6 +34,13 @@ struct Line {
 struct RunState {
     //lines held back for reordering
     reorder_buffer: Vec<Line>,
+    //about how many bytes of memory the held lines take up
+    buffered_bytes: usize,
+    //sorted runs of lines spilled to disk (`--buffer-size`), in the
+    //order they were read
+    runs: Vec<Run>,
+    //or, with `--top`, only the best of them so far
+    top: TopRows,
     //unique keys already written (`--unique`)
     seen_keys: HashSet<String>,
     //the line just written carried no terminator (it was the input's
@@ -36,6 +48,32 @@ struct RunState {
     needs_separator: bool,
     //at least one row was selected for processing (`RunOutcome::matched`)
     matched: bool,
+    //lines read but not yet decided on: a pattern bound shifted
+    //backwards (`/END/-1`) only decides a row some lines later
+    lookahead: VecDeque<Vec<u8>>,
+    //lines judged but not yet settled, with their number: a match
+    //further on may still make them its context (`--before-context`)
+    lookbehind: VecDeque<(Vec<u8>, usize, RowStatus)>,
+    //the last match judged, and the last one settled
+    last_match_judged: Option<usize>,
+    last_match_settled: Option<usize>,
+    //the last row shown as a match or its context, so a gap before the
+    //next one gets a `--` separator
+    last_shown: Option<usize>,
+}
+
+/// What a row turned out to be, once the row range and the predicate
+/// had their say.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowStatus {
+    /// Outside the row range.
+    Outside,
+    /// Inside the row range, but failing the predicate.
+    Miss,
+    /// Inside the row range and satisfying the predicate.
+    Match,
+    /// A miss close enough to a match to be processed along with it.
+    Context,
 }
 
 /// What a run has to say for itself once it is over.
@@ -48,18 +86,177 @@ pub struct RunOutcome {
     pub matched: bool,
 }
 
-/// How to order the buffered lines: by which columns, compared
-/// lexicographically or numerically, ascending or descending.
+/// How many rows around each match are processed along with it, like
+/// grep's `-B` and `-A`. Context rows are not matches themselves: they
+/// are shown (or deleted) with the match, but do not count for the exit
+/// code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Context {
+    pub before: usize,
+    pub after: usize,
+}
+
+/// How to order the buffered lines: by which keys, compared in turn
+/// until two lines differ (and failing that, kept in the order read) —
+/// whether to keep only the first few of them, and whether to spill
+/// them to disk rather than hold them all.
 pub struct SortSpec {
-    pub key_span: ColumnSpan,
-    pub numeric: bool,
-    pub reverse: bool,
+    pub keys: Vec<SortColumn>,
+    pub top: Option<Top>,
+    pub spill: Option<Spill>,
+}
+
+/// Sort more lines than memory holds (`--buffer-size`, `--temp-dir`):
+/// once the buffered lines take up about `budget` bytes, they are sorted
+/// and written to a run in `dir`, and the runs are merged on the way out.
+/// The output is the same as that of the sort in memory.
+pub struct Spill {
+    pub budget: usize,
+    pub dir: PathBuf,
+}
+
+/// One sort key: the columns it reads, and how they compare.
+pub struct SortColumn {
+    pub span: ColumnSpan,
+    pub order: SortOrder,
+}
+
+/// Keep only the first `count` lines of the sorted order (`--top`), per
+/// `--group-by` key when there is one. Rather than sorting every line,
+/// each group keeps a heap of its best `count` so far, so the memory a
+/// sort takes is bounded by `count` times the number of groups.
+pub struct Top {
+    pub count: usize,
+    pub group_span: Option<ColumnSpan>,
+}
+
+/// A line's place in the sort: the value of each of its keys, and
+/// whether that key runs descending. Reversing each comparison rather
+/// than the sorted lines keeps the sort stable in descending order too.
+#[derive(PartialEq, Eq)]
+struct LineKey(Vec<(SortValue, bool)>);
+
+impl Ord for LineKey {
+    fn cmp(&self, other: &LineKey) -> Ordering {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|((this, reverse), (that, _))| match reverse {
+                true => that.cmp(this),
+                false => this.cmp(that),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for LineKey {
+    fn partial_cmp(&self, other: &LineKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl SortSpec {
+    fn key(&self, content: &str) -> LineKey {
+        LineKey(
+            self.keys
+                .iter()
+                .map(|key| {
+                    //a key span beyond the line yields an empty key, like `cut`
+                    let text = key.span.select(content);
+                    (SortValue::new(&text, key.order), key.order.reverse)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A line competing for a place in the `--top`: ordered by its key, the
+/// way the sort goes, then by arrival, so that of equal lines the first
+/// read wins, as in the stable sort. The heap holding them is a max-heap,
+/// so its top is the line that would come last — the one to give up.
+struct Ranked {
+    key: LineKey,
+    arrival: u64,
+    line: Line,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Ranked) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then(self.arrival.cmp(&other.arrival))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Ranked) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Ranked) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// The best lines so far of each `--top` group, in the order the groups
+/// first appeared.
+#[derive(Default)]
+struct TopRows {
+    order: Vec<String>,
+    groups: HashMap<String, BinaryHeap<Ranked>>,
+    arrivals: u64,
+}
+
+impl TopRows {
+    fn offer(&mut self, spec: &SortSpec, top: &Top, line: Line) {
+        let group = match &top.group_span {
+            Some(span) => span.select(&line.content).into_owned(),
+            None => String::new(),
+        };
+        let heap = match self.groups.get_mut(&group) {
+            Some(heap) => heap,
+            None => {
+                self.order.push(group.clone());
+                self.groups.entry(group).or_default()
+            }
+        };
+        heap.push(Ranked {
+            key: spec.key(&line.content),
+            arrival: self.arrivals,
+            line,
+        });
+        self.arrivals += 1;
+        if heap.len() > top.count {
+            heap.pop();
+        }
+    }
+
+    /// Every group's lines, best first, emptying the groups.
+    fn drain(&mut self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for group in self.order.drain(..) {
+            if let Some(heap) = self.groups.remove(&group) {
+                lines.extend(
+                    heap.into_sorted_vec()
+                        .into_iter()
+                        .map(|ranked| ranked.line),
+                );
+            }
+        }
+        lines
+    }
 }
 
 /// A sequence-breaking operation: unlike per-line transforms, it needs
 /// the whole row range buffered before anything can be written.
 pub enum Reorder {
-    Sort(SortSpec),
+    //boxed: a key span is large, and the other variants carry nothing
+    Sort(Box<SortSpec>),
     /// Reverse the order of the buffered lines, like `tac`.
     Tac,
     /// Write the buffered lines in random order.
@@ -95,48 +292,6 @@ impl RowMode {
     }
 }
 
-/// An `Ord` wrapper around the parsed numeric sort key. Lines that do
-/// not parse as a number (`None`, including `NaN`) sort before all
-/// numbers — a sentinel value would collide with an actual `-inf` key.
-struct NumericKey(Option<f64>);
-
-impl NumericKey {
-    fn parse(text: &str) -> NumericKey {
-        NumericKey(
-            text.trim()
-                .parse()
-                .ok()
-                .filter(|value: &f64| !value.is_nan()),
-        )
-    }
-}
-
-impl PartialEq for NumericKey {
-    fn eq(&self, other: &NumericKey) -> bool {
-        self.cmp(other) == std::cmp::Ordering::Equal
-    }
-}
-
-impl Eq for NumericKey {}
-
-impl PartialOrd for NumericKey {
-    fn partial_cmp(&self, other: &NumericKey) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Ord for NumericKey {
-    fn cmp(&self, other: &NumericKey) -> std::cmp::Ordering {
-        use std::cmp::Ordering;
-        match (&self.0, &other.0) {
-            (None, None) => Ordering::Equal,
-            (None, Some(_)) => Ordering::Less,
-            (Some(_), None) => Ordering::Greater,
-            (Some(this), Some(that)) => this.total_cmp(that),
-        }
-    }
-}
-
 /// Write one line of content, terminated as it was on input. An input
 /// line without a terminator is the last one of the file — but it may
 /// have been reordered, or expanded into several lines, so it can end
@@ -159,6 +314,78 @@ fn write_content<W: Write>(
     Ok(())
 }
 
+/// The line as text; input that is not UTF-8 cannot be processed.
+fn utf8(raw_line: &[u8], line_number: usize) -> io::Result<&str> {
+    from_utf8(raw_line).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {line_number} is not valid UTF-8: {e}"),
+        )
+    })
+}
+
+/// Feed the records of `reader` to `take`, each with its terminator,
+/// for as long as `take` returns `true`. A record is a line — unless
+/// `multiline`, when a line that leaves a `"quoted"` field open is
+/// joined with the lines after it until the quotes balance, newlines
+/// kept. Input that ends inside quotes ends the record where it is.
+fn for_each_record<R: BufRead, F>(mut reader: R, multiline: bool, mut take: F) -> io::Result<()>
+where
+    F: FnMut(&[u8]) -> io::Result<bool>,
+{
+    if !multiline {
+        return reader.for_byte_line_with_terminator(take);
+    }
+    let mut record = Vec::new();
+    let mut inside_quotes = false;
+    reader.for_byte_line_with_terminator(|line| {
+        record.extend_from_slice(line);
+        inside_quotes ^= opens_quotes(line);
+        if inside_quotes {
+            return Ok(true);
+        }
+        let go_on = take(&record)?;
+        record.clear();
+        Ok(go_on)
+    })?;
+    if !record.is_empty() {
+        take(&record)?;
+    }
+    Ok(())
+}
+
+/// Read one record of `reader` into `record`, the way
+/// [`for_each_record`] reads them all; nothing is read at the end of
+/// the input.
+pub fn read_record<R: BufRead>(
+    reader: &mut R,
+    multiline: bool,
+    record: &mut Vec<u8>,
+) -> io::Result<()> {
+    let mut inside_quotes = false;
+    loop {
+        let start = record.len();
+        if reader.read_until(b'\n', record)? == 0 {
+            return Ok(());
+        }
+        inside_quotes ^= multiline && opens_quotes(&record[start..]);
+        if !inside_quotes {
+            return Ok(());
+        }
+    }
+}
+
+/// Whether a line toggles the quoting state: an odd number of quotes
+/// leaves a field open (or closes one). A doubled quote, RFC 4180's
+/// escape, counts twice and changes nothing.
+fn opens_quotes(line: &[u8]) -> bool {
+    !line
+        .iter()
+        .filter(|&&byte| byte == b'"')
+        .count()
+        .is_multiple_of(2)
+}
+
 fn write_pending_separator<W: Write>(state: &mut RunState, writer: &mut W) -> io::Result<()> {
     if state.needs_separator {
         writer.write_all(NEW_LINE.as_bytes())?;
@@ -180,14 +407,34 @@ pub struct FileProcessor {
     pub predicate: Option<Box<dyn LinePredicate>>,
     /// Key columns for `--unique`; `None` means duplicates are kept.
     pub unique_key_span: Option<ColumnSpan>,
+    /// What `--unique` ignores when comparing keys (`--collate`).
+    pub unique_collation: Collation,
     /// Per-line transforms, applied in order.
     pub transforms: Pipeline,
+    /// Transforms run on the rows as they are put out, once reordered:
+    /// a `--rank` of sorted rows, which only come in order then.
+    pub ordered: Pipeline,
     /// `Some` summarizes the processed lines instead of writing them
     /// (`--count`, `--sum`, …): the summary replaces the rows.
     pub reducer: Option<Box<dyn LineReducer>>,
     /// Stop reading as soon as a row matches (`--quiet`, which asks only
     /// whether anything matches at all).
     pub stop_when_matched: bool,
+    /// `Some` processes the rows around each predicate match too
+    /// (`--before-context`, `--after-context`).
+    pub context: Option<Context>,
+    /// `Some` makes the first line a header (`--header`): it is not a
+    /// row, so no range, filter or reordering touches it, and it is only
+    /// run through this pipeline — the column selection, so the header
+    /// of a projection names the columns that are left.
+    pub header: Option<Pipeline>,
+    /// Whether a row may span lines (`--quoted`): a newline inside a
+    /// `"quoted"` field does not end it, as RFC 4180 allows, so lines
+    /// are joined until the quotes balance and the record is one row.
+    pub multiline_records: bool,
+    /// `Some` renders what is written in an output format
+    /// (`--output-format`): the header, the rows, or the summary.
+    pub formatter: Option<Formatter>,
 }
 
 impl FileProcessor {
@@ -197,37 +444,64 @@ impl FileProcessor {
     /// is built fresh per input anyway, so there is nothing to reuse.
     pub fn run<R: BufRead, W: Write>(
         mut self,
+        reader: R,
+        writer: &mut W,
+    ) -> io::Result<RunOutcome> {
+        //the format comes after everything else: it only ever sees what
+        //would have been written
+        let Some(mut formatter) = self.formatter.take() else {
+            return self.stream(reader, writer);
+        };
+        let mut formatted = FormatWriter::new(&mut formatter, writer);
+        let outcome = self.stream(reader, &mut formatted)?;
+        formatted.finish()?;
+        Ok(outcome)
+    }
+
+    /// Stream `reader` into `writer`: the rows, or what a reducer makes
+    /// of them.
+    fn stream<R: BufRead, W: Write>(
+        &mut self,
         mut reader: R,
         writer: &mut W,
     ) -> io::Result<RunOutcome> {
         let mut state = RunState::default();
-
-        if self.rows.is_absolute() {
-            //the total line count is irrelevant, so lines can stream through
-            let rows = self.rows.resolve(usize::MAX);
-            let mut line_number = 0usize;
-            reader.for_byte_line_with_terminator(|raw_line| {
-                line_number += 1;
-                self.process_line(raw_line, line_number, &rows, &mut state, writer)?;
+        self.take_header(&mut reader, &mut state, writer)?;
+
+        if self.rows.tail().is_some() {
+            //rows are decided without the total line count: lines stream
+            //through, and only those in doubt wait in the lookahead
+            let mut rows = self.rows.selector(usize::MAX);
+            for_each_record(reader, self.multiline_records, |raw_line| {
+                self.take_line(raw_line, &mut rows, &mut state, writer)?;
                 //asked only whether anything matches, there is no reason
                 //to read the rest of the input once something has
                 Ok(!(self.stop_when_matched && state.matched))
             })?;
+            self.drain_lookahead(&mut rows, &mut state, writer)?;
+            self.drain_lookbehind(&mut state, writer)?;
         } else {
-            //end-relative bounds (`~N`) only resolve once the total
+            //a pattern block bounded by `~N` only ends once the total
             //line count is known: the whole input must be buffered
             let mut lines: Vec<Vec<u8>> = Vec::new();
-            reader.for_byte_line_with_terminator(|raw_line| {
+            for_each_record(reader, self.multiline_records, |raw_line| {
                 lines.push(raw_line.to_vec());
                 Ok(true)
             })?;
-            let rows = self.rows.resolve(lines.len());
+            let mut rows = self.rows.selector(lines.len());
+            //every line is at hand, so the patterns can be followed to
+            //the end before any row is decided
+            for raw_line in &lines {
+                rows.observe(raw_line);
+            }
             for (index, raw_line) in lines.iter().enumerate() {
-                self.process_line(raw_line, index + 1, &rows, &mut state, writer)?;
+                let selected = rows.contains(index + 1);
+                self.process_line(raw_line, index + 1, selected, &mut state, writer)?;
                 if self.stop_when_matched && state.matched {
                     break;
                 }
             }
+            self.drain_lookbehind(&mut state, writer)?;
         }
 
         self.flush_reordered(&mut state, writer)?;
@@ -243,53 +517,237 @@ impl FileProcessor {
         })
     }
 
+    /// Read the header line, if the input has one, and put it out ahead
+    /// of the rows. A summary or a join replaces the rows with something
+    /// else, which the header would not describe, so then it is dropped.
+    fn take_header<R: BufRead, W: Write>(
+        &mut self,
+        reader: &mut R,
+        state: &mut RunState,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let Some(projection) = &mut self.header else {
+            return Ok(());
+        };
+        let mut raw_line = Vec::new();
+        read_record(reader, self.multiline_records, &mut raw_line)?;
+        if raw_line.is_empty() || self.reducer.is_some() {
+            return Ok(());
+        }
+
+        let (content, terminator) = text::split_line_terminator(utf8(&raw_line, 1)?);
+        match projection.apply(content) {
+            Lines::One(content) => write_content(&content, terminator, state, writer),
+            Lines::Several(contents) => contents
+                .iter()
+                .try_for_each(|content| write_content(content, terminator, state, writer)),
+        }
+    }
+
+    /// Take the next line as it streams in. Once the row selector can
+    /// tell whether it is selected it is processed right away; until
+    /// then it waits in the lookahead, together with the lines before it.
+    fn take_line<W: Write>(
+        &mut self,
+        raw_line: &[u8],
+        rows: &mut RowSelector,
+        state: &mut RunState,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let line_number = rows.observe(raw_line);
+        if rows.delay() == 0 {
+            let selected = rows.contains(line_number);
+            return self.process_line(raw_line, line_number, selected, state, writer);
+        }
+
+        //once the lookahead is full its oldest line is decided, and the
+        //buffer it leaves takes the new line: the lookahead is a ring
+        //that stays the same size however long the input is
+        let mut slot = Vec::new();
+        if state.lookahead.len() == rows.delay()
+            && let Some(decided) = state.lookahead.pop_front()
+        {
+            let decided_number = line_number - rows.delay();
+            let selected = rows.contains(decided_number);
+            self.process_line(&decided, decided_number, selected, state, writer)?;
+            slot = decided;
+            slot.clear();
+        }
+        slot.extend_from_slice(raw_line);
+        state.lookahead.push_back(slot);
+        Ok(())
+    }
+
+    /// Process the lines still waiting in the lookahead once the input
+    /// has ended: nothing further can change their selection.
+    fn drain_lookahead<W: Write>(
+        &mut self,
+        rows: &mut RowSelector,
+        state: &mut RunState,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        rows.end_input();
+        let mut line_number = rows.seen() - state.lookahead.len();
+        while let Some(raw_line) = state.lookahead.pop_front() {
+            if self.stop_when_matched && state.matched {
+                break;
+            }
+            line_number += 1;
+            let selected = rows.contains(line_number);
+            self.process_line(&raw_line, line_number, selected, state, writer)?;
+        }
+        Ok(())
+    }
+
+    /// Judge a row, then settle it — right away, or once it is known
+    /// whether a match further on makes it context.
     fn process_line<W: Write>(
         &mut self,
         raw_line: &[u8],
         line_number: usize,
-        rows: &RangeSet,
+        selected: bool,
         state: &mut RunState,
         writer: &mut W,
     ) -> io::Result<()> {
-        if !rows.contains(line_number) {
-            if self.row_mode.keeps_unselected() {
-                self.write_kept_line(raw_line, state, writer)?;
+        let status = self.judge(raw_line, line_number, selected)?;
+        let Some(context) = self.context else {
+            return self.settle(raw_line, line_number, status, state, writer);
+        };
+
+        if status == RowStatus::Match {
+            state.last_match_judged = Some(line_number);
+        }
+        //every row waits its turn, so the rows settle in input order
+        //whatever they turn out to be
+        state
+            .lookbehind
+            .push_back((raw_line.to_vec(), line_number, status));
+        while state.lookbehind.len() > context.before {
+            if let Some((raw_line, line_number, status)) = state.lookbehind.pop_front() {
+                self.settle_in_context(context, &raw_line, line_number, status, state, writer)?;
             }
-            return Ok(());
         }
+        Ok(())
+    }
 
-        //without a content filter, deleting whole rows needs no UTF-8 look
-        if self.row_mode.deletes_selected() && self.predicate.is_none() {
+    /// Settle the rows still waiting in the lookbehind once the input has
+    /// ended: no further match can make them context.
+    fn drain_lookbehind<W: Write>(
+        &mut self,
+        state: &mut RunState,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let Some(context) = self.context else {
             return Ok(());
+        };
+        while let Some((raw_line, line_number, status)) = state.lookbehind.pop_front() {
+            if self.stop_when_matched && state.matched {
+                break;
+            }
+            self.settle_in_context(context, &raw_line, line_number, status, state, writer)?;
         }
+        Ok(())
+    }
 
-        let utf8_line = from_utf8(raw_line).map_err(|e| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("line {line_number} is not valid UTF-8: {e}"),
-            )
-        })?;
+    /// Settle a row leaving the lookbehind. A miss becomes context when
+    /// a match lies at most `after` rows before it or `before` rows after
+    /// it — every row up to that far after it has been judged by now.
+    /// Shown rows that do not follow the previous ones get a `--` line
+    /// in between, like grep's group separator.
+    fn settle_in_context<W: Write>(
+        &mut self,
+        context: Context,
+        raw_line: &[u8],
+        line_number: usize,
+        status: RowStatus,
+        state: &mut RunState,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let status = match status {
+            RowStatus::Match => {
+                state.last_match_settled = Some(line_number);
+                status
+            }
+            RowStatus::Miss
+                if state
+                    .last_match_judged
+                    .is_some_and(|row| row > line_number)
+                    || state
+                        .last_match_settled
+                        .is_some_and(|row| line_number - row <= context.after) =>
+            {
+                RowStatus::Context
+            }
+            other => other,
+        };
 
-        let (content, terminator) = text::split_line_terminator(utf8_line);
+        if matches!(status, RowStatus::Match | RowStatus::Context) {
+            //a separator only means something among the shown rows alone:
+            //not between kept ones, nor once reordered or summarized
+            let separated = self.row_mode == RowMode::Select
+                && self.reorder.is_none()
+                && self.reducer.is_none();
+            if separated
+                && state
+                    .last_shown
+                    .is_some_and(|row| line_number > row + 1)
+            {
+                write_content("--", NEW_LINE, state, writer)?;
+            }
+            state.last_shown = Some(line_number);
+        }
+        self.settle(raw_line, line_number, status, state, writer)
+    }
 
-        if let Some(predicate) = &self.predicate
-            && !predicate.matches(content)
-        {
+    /// Whether the row lies in the row range and satisfies the predicate.
+    fn judge(&self, raw_line: &[u8], line_number: usize, selected: bool) -> io::Result<RowStatus> {
+        if !selected {
+            return Ok(RowStatus::Outside);
+        }
+        //without a content filter, every row in the range matches, and
+        //deleting it needs no UTF-8 look
+        let Some(predicate) = &self.predicate else {
+            return Ok(RowStatus::Match);
+        };
+        let (content, _) = text::split_line_terminator(utf8(raw_line, line_number)?);
+        if predicate.matches(content) {
+            Ok(RowStatus::Match)
+        } else {
+            Ok(RowStatus::Miss)
+        }
+    }
+
+    /// Do with a judged row what its status says: pass it through or drop
+    /// it, delete it, or transform and emit it.
+    fn settle<W: Write>(
+        &mut self,
+        raw_line: &[u8],
+        line_number: usize,
+        status: RowStatus,
+        state: &mut RunState,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        match status {
             //a non-matching line is treated like one outside the row range
-            if self.row_mode.keeps_unselected() {
-                self.write_kept_line(raw_line, state, writer)?;
+            RowStatus::Outside | RowStatus::Miss => {
+                if self.row_mode.keeps_unselected() {
+                    self.write_kept_line(raw_line, state, writer)?;
+                }
+                return Ok(());
             }
-            return Ok(());
+            //the row lies in the range and satisfied the predicate: it is
+            //a match, whether it is about to be rewritten or deleted
+            RowStatus::Match => state.matched = true,
+            //context goes where its match goes, but matched nothing
+            RowStatus::Context => {}
         }
 
-        //the row lies in the range and satisfied the predicate: it is a
-        //match, whether it is about to be rewritten or deleted
-        state.matched = true;
-
         if self.row_mode.deletes_selected() {
             return Ok(());
         }
 
+        let (content, terminator) = text::split_line_terminator(utf8(raw_line, line_number)?);
+
         //a transform may expand the line into several, or drop it
         match self.transforms.apply(content) {
             Lines::One(content) => self.emit(&content, terminator, state, writer),
@@ -309,11 +767,28 @@ impl FileProcessor {
         state: &mut RunState,
         writer: &mut W,
     ) -> io::Result<()> {
-        if self.reorder.is_some() {
-            state.reorder_buffer.push(Line {
+        if let Some(reorder) = &self.reorder {
+            let line = Line {
                 content: content.to_owned(),
                 terminator,
-            });
+            };
+            match reorder {
+                Reorder::Sort(spec) => match &spec.top {
+                    Some(top) => state.top.offer(spec, top, line),
+                    None => {
+                        state.buffered_bytes += line.content.len() + size_of::<Line>();
+                        state.reorder_buffer.push(line);
+                        let over_budget = spec
+                            .spill
+                            .as_ref()
+                            .is_some_and(|spill| state.buffered_bytes > spill.budget);
+                        if over_budget {
+                            Self::spill_buffer(spec, state)?;
+                        }
+                    }
+                },
+                Reorder::Tac | Reorder::Shuffle => state.reorder_buffer.push(line),
+            }
             return Ok(());
         }
         self.output(content, terminator, state, writer)
@@ -334,6 +809,22 @@ impl FileProcessor {
         if !self.passes_unique(content, &mut state.seen_keys) {
             return Ok(());
         }
+        match self.ordered.apply(content) {
+            Lines::One(content) => self.put(&content, terminator, state, writer),
+            Lines::Several(contents) => contents
+                .iter()
+                .try_for_each(|content| self.put(content, terminator, state, writer)),
+        }
+    }
+
+    /// Hand a line that is to appear to the reducer, or to the writer.
+    fn put<W: Write>(
+        &mut self,
+        content: &str,
+        terminator: &str,
+        state: &mut RunState,
+        writer: &mut W,
+    ) -> io::Result<()> {
         if let Some(reducer) = &mut self.reducer {
             return reducer.accept(content, writer);
         }
@@ -358,13 +849,21 @@ impl FileProcessor {
         writer.write_all(raw_line)
     }
 
-    /// Whether the line survives `--unique`: its key columns have not
-    /// been written yet. Without `--unique` every line passes.
+    /// Whether the line survives `--unique`: its key columns, folded as
+    /// `--collate` says, have not been written yet. Without `--unique`
+    /// every line passes.
     /// A key span beyond the line yields an empty key, like `cut`.
     fn passes_unique(&self, content: &str, seen_keys: &mut HashSet<String>) -> bool {
         match &self.unique_key_span {
             None => true,
-            Some(span) => seen_keys.insert(span.select(content).into_owned()),
+            Some(span) => {
+                let key = span.select(content);
+                seen_keys.insert(
+                    self.unique_collation
+                        .fold(&key)
+                        .into_owned(),
+                )
+            }
         }
     }
 
@@ -376,12 +875,15 @@ impl FileProcessor {
         let Some(reorder) = &self.reorder else {
             return Ok(());
         };
-        if state.reorder_buffer.is_empty() {
-            return Ok(());
+        if !state.runs.is_empty() {
+            return self.merge_runs(state, writer);
         }
 
         let mut buffer = std::mem::take(&mut state.reorder_buffer);
+        state.buffered_bytes = 0;
         match reorder {
+            //the heaps kept the best lines, and give them up in order
+            Reorder::Sort(spec) if spec.top.is_some() => buffer = state.top.drain(),
             Reorder::Sort(spec) => Self::sort_lines(&mut buffer, spec),
             Reorder::Tac => buffer.reverse(),
             Reorder::Shuffle => {
@@ -401,21 +903,53 @@ impl FileProcessor {
     }
 
     fn sort_lines(buffer: &mut [Line], spec: &SortSpec) {
-        //`Reverse` keeps the sort stable in descending order too
-        use std::cmp::Reverse;
-        //a key span beyond the line yields an empty key, like `cut`
-        let text_key = |line: &Line| {
-            spec.key_span
-                .select(&line.content)
-                .into_owned()
+        //stable: lines equal on every key stay in the order read
+        buffer.sort_by_cached_key(|line| spec.key(&line.content));
+    }
+
+    /// Sort the buffered lines and write them out as a run, making room
+    /// for the next ones. Without a `--buffer-size` nothing is spilled.
+    fn spill_buffer(spec: &SortSpec, state: &mut RunState) -> io::Result<()> {
+        let Some(spill) = &spec.spill else {
+            return Ok(());
         };
-        match (spec.numeric, spec.reverse) {
-            (false, false) => buffer.sort_by_cached_key(|line| text_key(line)),
-            (false, true) => buffer.sort_by_cached_key(|line| Reverse(text_key(line))),
-            (true, false) => buffer.sort_by_cached_key(|line| NumericKey::parse(&text_key(line))),
-            (true, true) => {
-                buffer.sort_by_cached_key(|line| Reverse(NumericKey::parse(&text_key(line))))
-            }
+        let mut buffer = std::mem::take(&mut state.reorder_buffer);
+        state.buffered_bytes = 0;
+        Self::sort_lines(&mut buffer, spec);
+        let lines = buffer
+            .iter()
+            .map(|line| (line.content.as_str(), line.terminator));
+        state
+            .runs
+            .push(Run::write(&spill.dir, lines)?);
+        //past as many runs as are merged at once, those so far are merged
+        //into one: they are the earliest lines, so it stays first
+        if state.runs.len() == MERGE_WIDTH {
+            let runs = std::mem::take(&mut state.runs);
+            let merged = spill::merge_into(&spill.dir, runs, |content| spec.key(content))?;
+            state.runs.push(merged);
         }
+        Ok(())
+    }
+
+    /// Put out the lines of a sort that spilled to disk: those still
+    /// buffered become the last run, and the runs are merged on the way
+    /// out, each line then taking the way every sorted line takes.
+    fn merge_runs<W: Write>(&mut self, state: &mut RunState, writer: &mut W) -> io::Result<()> {
+        //the merge reads each line's keys while the processor puts lines
+        //out, so the spec is set aside for the while
+        let Some(Reorder::Sort(spec)) = self.reorder.take() else {
+            unreachable!("only a sort spills its lines");
+        };
+        let result = Self::spill_buffer(&spec, state).and_then(|()| {
+            let runs = std::mem::take(&mut state.runs);
+            spill::merge(
+                runs,
+                |content| spec.key(content),
+                |content, terminator| self.output(&content, terminator, state, writer),
+            )
+        });
+        self.reorder = Some(Reorder::Sort(spec));
+        result
     }
 }
@@ -0,0 +1,625 @@
+//! Output formats (`--output-format`): the rows `ft` writes — processed
+//! rows, the header, a summary — rendered as JSON, JSON Lines, a
+//! Markdown or HTML table, TSV or CSV, rather than as delimited text.
+//!
+//! A [`Formatter`] comes after everything else: the processor writes
+//! its lines into a [`FormatWriter`] as it would into the output, and
+//! each complete line is split into its fields and rendered, its values
+//! escaped the way the format asks for. Columns are named by the header
+//! row, by the names the processor knows them by (a summary's, the
+//! labels of the columns selected), or else by their positions.
+//!
+//! An aligned table (`table`, like `column -t`) is the one format that
+//! buffers: a column is only as wide as its widest value, which the last
+//! row may hold. Widths are display widths, so CJK and emoji, two
+//! columns wide on a terminal, still line up.
+
+use std::io::{self, Write};
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::columns::{Delimiter, Quoting, field_values};
+use crate::constants::NEW_LINE;
+use crate::json;
+
+/// What the rows are written as, instead of the delimited text they
+/// were read as (`--output-format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One JSON array, every row an object in it.
+    Json,
+    /// JSON Lines: every row one JSON object, its fields the members.
+    Jsonl,
+    /// A Markdown (GitHub-flavoured) table.
+    Markdown,
+    /// An HTML `<table>`.
+    Html,
+    /// Tab-separated values, tabs and line breaks escaped.
+    Tsv,
+    /// RFC 4180 CSV, a value quoted when it has to be.
+    Csv,
+    /// Columns padded to line up, numbers aligned right.
+    Table,
+}
+
+/// What names the columns of the rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Heading {
+    /// The first line is the header row (`--header`): its fields are the
+    /// names, and it is not a row.
+    FirstRow,
+    /// The columns have these names, in order; a column past the last
+    /// goes by its position.
+    Names(Vec<String>),
+    /// The columns go by their positions.
+    Positions,
+}
+
+/// How the lines given to the formatter hold their values.
+#[derive(Debug, Clone)]
+pub enum Rows {
+    /// Fields between separators, `"quoted"` as CSV values when `quoted`.
+    Delimited { separator: Delimiter, quoted: bool },
+    /// JSON objects already, written by `--jsonl` (JSON and JSON Lines
+    /// only): they pass through as they are.
+    Objects,
+}
+
+impl Rows {
+    /// Rows delimited the way they were written: joined by `separator`,
+    /// and quoted by `quoting`, or as they were read when `quoted`.
+    pub fn delimited(separator: &str, quoted: bool, quoting: Option<Quoting>) -> Rows {
+        let quoted = match quoting {
+            Some(Quoting::Never) => false,
+            Some(Quoting::Minimal | Quoting::Always) => true,
+            None => quoted,
+        };
+        Rows::Delimited {
+            separator: Delimiter::from(separator),
+            quoted,
+        }
+    }
+}
+
+/// Renders rows in an output format, a row at a time; whatever frames
+/// the rows (a JSON array, a table) is opened before the first and
+/// closed by [`Formatter::finish`].
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    format: OutputFormat,
+    rows: Rows,
+    //the column names, once known; `None` while the header row is due
+    names: Option<Vec<String>>,
+    //whether the frame is open: the heading, and anything before it
+    begun: bool,
+    written: usize,
+    //the rows of an aligned table, which waits for the widest value
+    table: Vec<Vec<String>>,
+}
+
+impl Formatter {
+    pub fn new(format: OutputFormat, rows: Rows, heading: Heading) -> Formatter {
+        let names = match &heading {
+            Heading::FirstRow => None,
+            Heading::Names(names) => Some(names.clone()),
+            Heading::Positions => Some(Vec::new()),
+        };
+        Formatter {
+            format,
+            rows,
+            names,
+            begun: false,
+            written: 0,
+            table: Vec::new(),
+        }
+    }
+
+    /// Render one line, without its terminator: the header row, which
+    /// names the columns, or a row.
+    pub fn line(&mut self, line: &str, writer: &mut dyn Write) -> io::Result<()> {
+        if let Rows::Objects = self.rows {
+            return self.object(line, writer);
+        }
+        let values = self.values(line);
+        if self.names.is_none() {
+            self.names = Some(values);
+            return self.begin(0, writer);
+        }
+        self.begin(values.len(), writer)?;
+
+        match self.format {
+            OutputFormat::Json | OutputFormat::Jsonl => {
+                let object = self.object_of(&values);
+                self.object(&object, writer)?;
+                return Ok(());
+            }
+            OutputFormat::Markdown => write_markdown_row(&values, writer)?,
+            OutputFormat::Html => write_html_row("td", &values, writer)?,
+            OutputFormat::Tsv => write_tsv_row(&values, writer)?,
+            OutputFormat::Csv => write_csv_row(&values, writer)?,
+            OutputFormat::Table => self.table.push(values),
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Close what the rows were framed in. A JSON array is written even
+    /// without rows, as is the heading of named columns.
+    pub fn finish(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        let named = matches!(&self.names, Some(names) if !names.is_empty());
+        if named || self.format == OutputFormat::Json {
+            self.begin(0, writer)?;
+        }
+        if !self.begun {
+            return Ok(());
+        }
+        match self.format {
+            OutputFormat::Json if self.written == 0 => write!(writer, "]{NEW_LINE}"),
+            OutputFormat::Json => write!(writer, "{NEW_LINE}]{NEW_LINE}"),
+            OutputFormat::Html => write!(writer, "</tbody>{NEW_LINE}</table>{NEW_LINE}"),
+            OutputFormat::Table => {
+                let names = self.names.clone().unwrap_or_default();
+                write_table(&names, &self.table, writer)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether a row may span lines: a `"quoted"` value may hold a
+    /// line break, which does not end the row.
+    fn multiline(&self) -> bool {
+        matches!(self.rows, Rows::Delimited { quoted: true, .. })
+    }
+
+    /// The values of a delimited line.
+    fn values(&self, line: &str) -> Vec<String> {
+        match &self.rows {
+            Rows::Delimited { separator, quoted } => field_values(line, separator, *quoted),
+            Rows::Objects => vec![line.to_owned()],
+        }
+    }
+
+    /// A row as a JSON object: each value keyed by the name of its
+    /// column, a number when it reads as one and text otherwise.
+    fn object_of(&self, values: &[String]) -> String {
+        let members: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let key = json::encode(&self.name(index));
+                format!("{key}:{}", json::infer(value))
+            })
+            .collect();
+        format!("{{{}}}", members.join(","))
+    }
+
+    /// Write a row that is a JSON object already.
+    fn object(&mut self, object: &str, writer: &mut dyn Write) -> io::Result<()> {
+        self.begin(0, writer)?;
+        match self.format {
+            OutputFormat::Json if self.written > 0 => write!(writer, ",{NEW_LINE}{object}")?,
+            OutputFormat::Json => write!(writer, "{object}")?,
+            _ => write!(writer, "{object}{NEW_LINE}")?,
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    /// The name of the column at a 0-based index.
+    fn name(&self, index: usize) -> String {
+        self.names
+            .as_ref()
+            .and_then(|names| names.get(index))
+            .cloned()
+            .unwrap_or_else(|| (index + 1).to_string())
+    }
+
+    /// Open the frame, once, ahead of the first row: a table of
+    /// `columns` columns, when the names leave the count open.
+    fn begin(&mut self, columns: usize, writer: &mut dyn Write) -> io::Result<()> {
+        if self.begun {
+            return Ok(());
+        }
+        self.begun = true;
+        let names = self.names.clone().unwrap_or_default();
+        let headings: Vec<String> = (0..names.len().max(columns))
+            .map(|index| self.name(index))
+            .collect();
+        match self.format {
+            OutputFormat::Json => write!(writer, "[{NEW_LINE}"),
+            OutputFormat::Jsonl => Ok(()),
+            OutputFormat::Markdown => {
+                write_markdown_row(&headings, writer)?;
+                let rule = vec!["---".to_owned(); headings.len()];
+                write!(writer, "| {} |{NEW_LINE}", rule.join(" | "))
+            }
+            OutputFormat::Html => {
+                write!(writer, "<table>{NEW_LINE}")?;
+                //positions name nothing, so a table without names has
+                //no head
+                if !names.is_empty() {
+                    write!(writer, "<thead>{NEW_LINE}")?;
+                    write_html_row("th", &names, writer)?;
+                    write!(writer, "</thead>{NEW_LINE}")?;
+                }
+                write!(writer, "<tbody>{NEW_LINE}")
+            }
+            OutputFormat::Tsv if !names.is_empty() => write_tsv_row(&names, writer),
+            OutputFormat::Csv if !names.is_empty() => write_csv_row(&names, writer),
+            OutputFormat::Tsv | OutputFormat::Csv | OutputFormat::Table => Ok(()),
+        }
+    }
+}
+
+fn write_markdown_row(values: &[String], writer: &mut dyn Write) -> io::Result<()> {
+    let cells: Vec<String> = values
+        .iter()
+        .map(|value| escape_markdown(value))
+        .collect();
+    write!(writer, "| {} |{NEW_LINE}", cells.join(" | "))
+}
+
+fn write_html_row(cell: &str, values: &[String], writer: &mut dyn Write) -> io::Result<()> {
+    let cells: String = values
+        .iter()
+        .map(|value| format!("<{cell}>{}</{cell}>", escape_html(value)))
+        .collect();
+    write!(writer, "<tr>{cells}</tr>{NEW_LINE}")
+}
+
+fn write_tsv_row(values: &[String], writer: &mut dyn Write) -> io::Result<()> {
+    let cells: Vec<String> = values
+        .iter()
+        .map(|value| escape_tsv(value))
+        .collect();
+    write!(writer, "{}{NEW_LINE}", cells.join("\t"))
+}
+
+fn write_csv_row(values: &[String], writer: &mut dyn Write) -> io::Result<()> {
+    let cells: Vec<_> = values
+        .iter()
+        .map(|value| Quoting::Minimal.quote(value, ","))
+        .collect();
+    write!(writer, "{}{NEW_LINE}", cells.join(","))
+}
+
+/// Write the rows as an aligned table, under the names when there are
+/// any: every column padded to its widest value, two spaces apart. A
+/// column of numbers — every value that is not empty reads as one — is
+/// aligned right, any other left.
+fn write_table(names: &[String], rows: &[Vec<String>], writer: &mut dyn Write) -> io::Result<()> {
+    let cells: Vec<Vec<String>> = (!names.is_empty())
+        .then_some(names)
+        .into_iter()
+        .chain(rows.iter().map(Vec::as_slice))
+        .map(|row| {
+            row.iter()
+                .map(|value| escape_table(value))
+                .collect()
+        })
+        .collect();
+    let columns = cells
+        .iter()
+        .map(Vec::len)
+        .max()
+        .unwrap_or(0);
+
+    let mut widths = vec![0; columns];
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+    let numeric: Vec<bool> = (0..columns)
+        .map(|column| {
+            let mut values = rows
+                .iter()
+                .filter_map(|row| row.get(column))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .peekable();
+            values.peek().is_some() && values.all(|value| value.parse::<f64>().is_ok())
+        })
+        .collect();
+
+    for row in &cells {
+        let mut line = String::new();
+        for (column, cell) in row.iter().enumerate() {
+            if column > 0 {
+                line.push_str("  ");
+            }
+            let padding = " ".repeat(widths[column] - cell.width());
+            match numeric[column] {
+                true => {
+                    line.push_str(&padding);
+                    line.push_str(cell);
+                }
+                false => {
+                    line.push_str(cell);
+                    line.push_str(&padding);
+                }
+            }
+        }
+        //the last column needs no padding to line up with anything
+        write!(writer, "{}{NEW_LINE}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// A value as a table cell, on one line: a tab or a line break is
+/// written as `\t`, `\n` or `\r`.
+fn escape_table(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A value as a Markdown table cell: a pipe would end the cell and a
+/// line break the row, so the one is escaped and the other a `<br>`.
+fn escape_markdown(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '|' => escaped.push_str("\\|"),
+            '\n' => escaped.push_str("<br>"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A value as HTML text, safe inside an element and an attribute alike.
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A value as a TSV field: a tab or a line break would split it, so they
+/// are written as `\t`, `\n` and `\r`, and a backslash doubled.
+fn escape_tsv(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats what is written through it: the bytes are gathered into
+/// lines — rows, when a quoted value spans lines — and every complete
+/// one goes through the formatter on its way to the writer.
+/// [`FormatWriter::finish`] takes an unterminated last line and closes
+/// the format.
+pub struct FormatWriter<'a, W: Write> {
+    formatter: &'a mut Formatter,
+    writer: &'a mut W,
+    pending: Vec<u8>,
+}
+
+impl<'a, W: Write> FormatWriter<'a, W> {
+    pub fn new(formatter: &'a mut Formatter, writer: &'a mut W) -> FormatWriter<'a, W> {
+        FormatWriter {
+            formatter,
+            writer,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Format the last line, terminated or not, and close the format.
+    pub fn finish(mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.format(&line)?;
+        }
+        self.formatter.finish(self.writer)?;
+        self.writer.flush()
+    }
+
+    fn format(&mut self, line: &[u8]) -> io::Result<()> {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let line = String::from_utf8_lossy(line);
+        self.formatter.line(&line, self.writer)
+    }
+}
+
+impl<W: Write> Write for FormatWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let multiline = self.formatter.multiline();
+        let mut from = 0;
+        while let Some(offset) = self.pending[from..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+        {
+            let end = from + offset;
+            //an odd number of quotes leaves a value open, line break and all
+            let quotes = self.pending[..end]
+                .iter()
+                .filter(|&&byte| byte == b'"')
+                .count();
+            if multiline && !quotes.is_multiple_of(2) {
+                from = end + 1;
+                continue;
+            }
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.format(&line)?;
+            from = 0;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(format: OutputFormat, heading: Heading, lines: &str) -> String {
+        let mut formatter = Formatter::new(format, Rows::delimited(",", true, None), heading);
+        let mut output = Vec::new();
+        let mut writer = FormatWriter::new(&mut formatter, &mut output);
+        writer
+            .write_all(lines.as_bytes())
+            .expect("formatting failed");
+        writer
+            .finish()
+            .expect("finishing failed");
+        String::from_utf8(output)
+            .expect("the output is not valid UTF-8")
+            .replace(NEW_LINE, "\n")
+    }
+
+    #[test]
+    fn json_writes_an_array_of_objects() {
+        assert_eq!(
+            formatted(
+                OutputFormat::Json,
+                Heading::FirstRow,
+                "name,qty\na,1\nb,007\n"
+            ),
+            "[\n{\"name\":\"a\",\"qty\":1},\n{\"name\":\"b\",\"qty\":\"007\"}\n]\n"
+        );
+        assert_eq!(
+            formatted(OutputFormat::Json, Heading::FirstRow, "name,qty\n"),
+            "[\n]\n"
+        );
+    }
+
+    #[test]
+    fn jsonl_keys_unnamed_columns_by_position() {
+        assert_eq!(
+            formatted(
+                OutputFormat::Jsonl,
+                Heading::Names(vec!["name".to_owned()]),
+                "a,\"x, \"\"y\"\"\"\n"
+            ),
+            "{\"name\":\"a\",\"2\":\"x, \\\"y\\\"\"}\n"
+        );
+    }
+
+    #[test]
+    fn markdown_escapes_pipes_and_line_breaks() {
+        assert_eq!(
+            formatted(
+                OutputFormat::Markdown,
+                Heading::FirstRow,
+                "a,b\n\"x|y\",\"1\n2\"\n"
+            ),
+            "| a | b |\n| --- | --- |\n| x\\|y | 1<br>2 |\n"
+        );
+        assert_eq!(
+            formatted(OutputFormat::Markdown, Heading::Positions, "x,y\n"),
+            "| 1 | 2 |\n| --- | --- |\n| x | y |\n"
+        );
+    }
+
+    #[test]
+    fn html_escapes_markup() {
+        assert_eq!(
+            formatted(OutputFormat::Html, Heading::FirstRow, "a\n<b>&'\"\n"),
+            "<table>\n<thead>\n<tr><th>a</th></tr>\n</thead>\n<tbody>\n\
+             <tr><td>&lt;b&gt;&amp;&#39;&quot;</td></tr>\n</tbody>\n</table>\n"
+        );
+        assert_eq!(
+            formatted(OutputFormat::Html, Heading::Positions, "x\n"),
+            "<table>\n<tbody>\n<tr><td>x</td></tr>\n</tbody>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn tsv_escapes_tabs_and_line_breaks() {
+        assert_eq!(
+            formatted(
+                OutputFormat::Tsv,
+                Heading::Positions,
+                "\"a\tb\",\"c\nd\\e\"\n"
+            ),
+            "a\\tb\tc\\nd\\\\e\n"
+        );
+    }
+
+    #[test]
+    fn csv_quotes_only_where_needed() {
+        assert_eq!(
+            formatted(
+                OutputFormat::Csv,
+                Heading::Names(vec!["n".to_owned(), "v".to_owned()]),
+                "a,\"x, y\"\r\nb"
+            ),
+            "n,v\na,\"x, y\"\nb\n"
+        );
+    }
+
+    #[test]
+    fn table_aligns_columns_numbers_right() {
+        assert_eq!(
+            formatted(
+                OutputFormat::Table,
+                Heading::FirstRow,
+                "name,qty,note\nab,5,x\nc,10,\n"
+            ),
+            "name  qty  note\nab      5  x\nc      10\n"
+        );
+    }
+
+    #[test]
+    fn table_pads_to_display_width() {
+        assert_eq!(
+            formatted(
+                OutputFormat::Table,
+                Heading::Positions,
+                "東京,1\nab,22\n😀,3\n"
+            ),
+            "東京   1\nab    22\n😀     3\n"
+        );
+    }
+
+    #[test]
+    fn objects_pass_through() {
+        let mut formatter = Formatter::new(OutputFormat::Json, Rows::Objects, Heading::Positions);
+        let mut output = Vec::new();
+        formatter
+            .line("{\"a\":1}", &mut output)
+            .expect("formatting failed");
+        formatter
+            .line("{\"a\":[2]}", &mut output)
+            .expect("formatting failed");
+        formatter
+            .finish(&mut output)
+            .expect("finishing failed");
+        assert_eq!(
+            String::from_utf8(output)
+                .expect("the output is not valid UTF-8")
+                .replace(NEW_LINE, "\n"),
+            "[\n{\"a\":1},\n{\"a\":[2]}\n]\n"
+        );
+    }
+}
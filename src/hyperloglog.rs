@@ -0,0 +1,148 @@
+//! A HyperLogLog: a sketch that counts the distinct values of a stream
+//! in a fixed few kilobytes, for `--approximate --distinct` over inputs
+//! with too many different values to remember each one.
+//!
+//! Every value is hashed; the first `precision` bits of the hash pick one
+//! of 2^precision registers, which keeps the longest run of leading zeros
+//! the rest of a hash has started with. A run of `k` zeros turns up about
+//! once per 2^k different values, and a repeated value hashes the same,
+//! so duplicates change nothing. The count is read from the registers
+//! with Ertl's improved estimator, which needs no bias tables or switch
+//! to linear counting to stay accurate from a handful of values up.
+//!
+//! The relative standard error is about 1.04 / √(2^precision): 0.8% at
+//! the default precision of 14, in 16 KiB per group.
+
+use std::f64::consts::LN_2;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// The precision `--precision` defaults to: 2^14 registers.
+pub const DEFAULT_PRECISION: u8 = 14;
+
+/// The precisions allowed: below 4 the estimate is too coarse to be of
+/// use, above 18 the registers outgrow the memory they are there to save.
+pub const PRECISIONS: std::ops::RangeInclusive<u8> = 4..=18;
+
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new(precision: u8) -> HyperLogLog {
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Take one value into the sketch.
+    pub fn add(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> self.remainder_bits()) as usize;
+        //the rest of the hash, moved up; zeros past its end do not count
+        let rest = hash << self.precision;
+        let rank = (rest.leading_zeros() + 1).min(self.remainder_bits() + 1) as u8;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// How many different values were added, estimated.
+    pub fn estimate(&self) -> f64 {
+        let remainder = self.remainder_bits() as usize;
+        let registers = self.registers.len() as f64;
+        //how many registers hold each rank, 0 to remainder + 1
+        let mut counts = vec![0u64; remainder + 2];
+        for &rank in &self.registers {
+            counts[rank as usize] += 1;
+        }
+
+        let mut z = registers * tau((registers - counts[remainder + 1] as f64) / registers);
+        for count in counts[1..=remainder].iter().rev() {
+            z = 0.5 * (z + *count as f64);
+        }
+        z += registers * sigma(counts[0] as f64 / registers);
+        registers * registers / (2.0 * LN_2 * z)
+    }
+
+    fn remainder_bits(&self) -> u32 {
+        64 - u32::from(self.precision)
+    }
+}
+
+/// The correction for registers still at zero: x + x²/2 + x⁴/4 + …,
+/// summed until it stops changing. Infinite when every register is, so
+/// that no values at all estimate to 0.
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut weight = 1.0;
+    let mut sum = x;
+    loop {
+        x *= x;
+        let previous = sum;
+        sum += x * weight;
+        weight += weight;
+        if sum == previous {
+            return sum;
+        }
+    }
+}
+
+/// The correction for registers that ran out of hash bits.
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut weight = 1.0;
+    let mut sum = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous = sum;
+        weight *= 0.5;
+        sum -= (1.0 - x).powi(2) * weight;
+        if sum == previous {
+            return sum / 3.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_sketch_counts_nothing() {
+        assert_eq!(HyperLogLog::new(DEFAULT_PRECISION).estimate(), 0.0);
+    }
+
+    #[test]
+    fn a_few_values_are_counted_exactly_whatever_their_repeats() {
+        let mut sketch = HyperLogLog::new(DEFAULT_PRECISION);
+        for value in ["a", "b", "a", "c", "b", "a"] {
+            sketch.add(value);
+        }
+        assert_eq!(sketch.estimate().round(), 3.0);
+    }
+
+    #[test]
+    fn many_values_stay_within_the_error_bound() {
+        for precision in [PRECISIONS.start(), &DEFAULT_PRECISION] {
+            let mut sketch = HyperLogLog::new(*precision);
+            for value in 0..200_000 {
+                sketch.add(&(value % 100_000).to_string());
+            }
+            let error = 1.04 / ((1u64 << precision) as f64).sqrt();
+            let estimate = sketch.estimate();
+            //three standard errors
+            assert!(
+                (estimate - 100_000.0).abs() < 100_000.0 * 3.0 * error,
+                "precision {precision}: {estimate}"
+            );
+        }
+    }
+}
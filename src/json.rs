@@ -0,0 +1,456 @@
+//! JSON Lines (`--jsonl`): every line is one JSON object, and columns
+//! are its members — addressed by key path (`.user.id`) or by position
+//! among the top-level members. The line is not parsed into values but
+//! scanned for where each member lies, so a member resolves to a char
+//! range like a field does, and whatever is not addressed is written
+//! back exactly as it was read.
+//!
+//! A line that is not an object has no members: it addresses nothing,
+//! like a line too short for a column range.
+
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
+/// How deeply objects and arrays may nest, as serde_json allows: the
+/// scan recurses into each, so a deeper line is taken for malformed
+/// rather than run the stack out.
+const MAX_DEPTH: usize = 128;
+
+/// One member of an object (or element of an array) and where it lies
+/// on the line, in 1-based, inclusive char positions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    /// The key; an array element's is its 0-based index.
+    pub key: String,
+    /// The whole member, key included: what deleting it removes.
+    pub extent: RangeInclusive<usize>,
+    /// The value alone.
+    pub value: RangeInclusive<usize>,
+    /// Whether the value is an object or an array.
+    pub container: bool,
+    /// The members of an object value, the elements of an array value.
+    pub children: Vec<Member>,
+}
+
+/// The top-level members of an object line; none when the line is not
+/// a well-formed object.
+pub fn members(line: &str) -> Vec<Member> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut scanner = Scanner {
+        chars: &chars,
+        at: 0,
+        depth: 0,
+    };
+    scanner.skip_whitespace();
+    if scanner.peek() != Some('{') {
+        return Vec::new();
+    }
+    match scanner.container() {
+        Some(members) if scanner.at_end() => members,
+        _ => Vec::new(),
+    }
+}
+
+/// The member a key path leads to: `.user.id` is the member `id` of the
+/// object `user`, and a number steps into an array (`.tags.0`). The
+/// leading dot is optional.
+pub fn find<'a>(members: &'a [Member], path: &str) -> Option<&'a Member> {
+    let path = path.strip_prefix('.').unwrap_or(path);
+    let mut keys = path.split('.');
+    let first = keys.next()?;
+    let mut member = members
+        .iter()
+        .find(|member| member.key == first)?;
+    for key in keys {
+        member = member
+            .children
+            .iter()
+            .find(|child| child.key == key)?;
+    }
+    Some(member)
+}
+
+/// What a value's JSON text stands for: the text of a string, its
+/// escapes undone; any other value as written.
+pub fn decode(value: &str) -> Cow<'_, str> {
+    let Some(inner) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    else {
+        return Cow::Borrowed(value);
+    };
+    if !inner.contains('\\') {
+        return Cow::Borrowed(inner);
+    }
+
+    let mut decoded = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => decoded.push('\n'),
+            Some('t') => decoded.push('\t'),
+            Some('r') => decoded.push('\r'),
+            Some('b') => decoded.push('\u{8}'),
+            Some('f') => decoded.push('\u{c}'),
+            Some('u') => {
+                let unit = hex_unit(&mut chars);
+                //a high surrogate pairs with the `\u` escape after it
+                let code = match unit {
+                    Some(high @ 0xD800..=0xDBFF) => {
+                        let mut rest = chars.clone();
+                        match (rest.next(), rest.next(), hex_unit(&mut rest)) {
+                            (Some('\\'), Some('u'), Some(low @ 0xDC00..=0xDFFF)) => {
+                                chars = rest;
+                                Some(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                            }
+                            _ => None,
+                        }
+                    }
+                    unit => unit,
+                };
+                //the scan let only whole escapes through, but a lone
+                //surrogate is one too, and stands for no char
+                decoded.push(
+                    code.and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER),
+                );
+            }
+            //`\"`, `\\` and `\/` stand for the char escaped
+            Some(other) => decoded.push(other),
+            None => decoded.push('\\'),
+        }
+    }
+    Cow::Owned(decoded)
+}
+
+/// Text as a JSON string, quoted and escaped.
+pub fn encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len() + 2);
+    encoded.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            '\n' => encoded.push_str("\\n"),
+            '\t' => encoded.push_str("\\t"),
+            '\r' => encoded.push_str("\\r"),
+            c if c.is_control() => encoded.push_str(&format!("\\u{:04x}", c as u32)),
+            c => encoded.push(c),
+        }
+    }
+    encoded.push('"');
+    encoded
+}
+
+/// A field's text as a JSON value: a number when it reads as a JSON
+/// number, a string otherwise — so `10` stays a number, while `007`,
+/// which JSON would not read back as 7, stays the text it is.
+pub fn infer(text: &str) -> String {
+    match is_number(text) {
+        true => text.to_owned(),
+        false => encode(text),
+    }
+}
+
+/// Whether the text is a JSON value other than a string, object or
+/// array: a number, `true`, `false` or `null`.
+pub fn is_scalar(text: &str) -> bool {
+    matches!(text, "true" | "false" | "null") || is_number(text)
+}
+
+/// Whether the text is a number as JSON writes it: an optional minus,
+/// an integer part without leading zeros, then an optional fraction
+/// and exponent.
+fn is_number(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (mantissa, exponent) = match digits.find(['e', 'E']) {
+        Some(at) => (&digits[..at], Some(&digits[at + 1..])),
+        None => (digits, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let all_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+
+    all_digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(all_digits)
+        && exponent.is_none_or(|exponent| {
+            all_digits(
+                exponent
+                    .strip_prefix(['+', '-'])
+                    .unwrap_or(exponent),
+            )
+        })
+}
+
+/// The four hex digits of a `\u` escape.
+fn hex_unit(chars: &mut std::str::Chars) -> Option<u32> {
+    let digits: String = chars.by_ref().take(4).collect();
+    match digits.len() {
+        4 => u32::from_str_radix(&digits, 16).ok(),
+        _ => None,
+    }
+}
+
+/// Walks the chars of a line, noting where each member lies. Any
+/// malformed input ends the scan with `None`.
+struct Scanner<'a> {
+    chars: &'a [char],
+    //0-based; a position reported is 1-based, one more
+    at: usize,
+    //the containers the scan is inside
+    depth: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.at == self.chars.len()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|c| matches!(c, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        self.skip_whitespace();
+        (self.peek() == Some(expected)).then(|| self.at += 1)
+    }
+
+    /// An object's members or an array's elements, the scanner on its
+    /// opening bracket.
+    fn container(&mut self) -> Option<Vec<Member>> {
+        let object = self.peek()? == '{';
+        let close = if object { '}' } else { ']' };
+        self.at += 1;
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.at += 1;
+            self.depth -= 1;
+            return Some(members);
+        }
+        loop {
+            self.skip_whitespace();
+            let start = self.at + 1;
+            let key = match object {
+                true => {
+                    let key_start = self.at;
+                    self.string()?;
+                    let key: String = self.chars[key_start..self.at]
+                        .iter()
+                        .collect();
+                    self.expect(':')?;
+                    decode(&key).into_owned()
+                }
+                false => members.len().to_string(),
+            };
+            let (value, children) = self.value()?;
+            members.push(Member {
+                key,
+                extent: start..=*value.end(),
+                value,
+                container: children.is_some(),
+                children: children.unwrap_or_default(),
+            });
+
+            self.skip_whitespace();
+            match self.peek()? {
+                ',' => self.at += 1,
+                c if c == close => {
+                    self.at += 1;
+                    self.depth -= 1;
+                    return Some(members);
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// A value: where it lies, and its members if it is a container.
+    fn value(&mut self) -> Option<(RangeInclusive<usize>, Option<Vec<Member>>)> {
+        self.skip_whitespace();
+        let start = self.at + 1;
+        let children = match self.peek()? {
+            '{' | '[' => Some(self.container()?),
+            '"' => {
+                self.string()?;
+                None
+            }
+            _ => {
+                let scalar_start = self.at;
+                while self
+                    .peek()
+                    .is_some_and(|c| !matches!(c, ',' | '}' | ']' | ' ' | '\t' | '\n' | '\r'))
+                {
+                    self.at += 1;
+                }
+                let scalar: String = self.chars[scalar_start..self.at]
+                    .iter()
+                    .collect();
+                if !is_scalar(&scalar) {
+                    return None;
+                }
+                None
+            }
+        };
+        Some((start..=self.at, children))
+    }
+
+    /// A string, the scanner on its opening quote.
+    fn string(&mut self) -> Option<()> {
+        (self.peek()? == '"').then_some(())?;
+        self.at += 1;
+        loop {
+            match self.peek()? {
+                '"' => {
+                    self.at += 1;
+                    return Some(());
+                }
+                '\\' => {
+                    self.at += 1;
+                    self.escape()?;
+                }
+                _ => self.at += 1,
+            }
+        }
+    }
+
+    /// What follows a backslash in a string: one of the chars JSON
+    /// escapes, or `u` and four hex digits.
+    fn escape(&mut self) -> Option<()> {
+        let escaped = self.peek()?;
+        self.at += 1;
+        match escaped {
+            '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't' => Some(()),
+            'u' => {
+                let digits = self.chars.get(self.at..self.at + 4)?;
+                self.at += 4;
+                digits
+                    .iter()
+                    .all(char::is_ascii_hexdigit)
+                    .then_some(())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = r#"{"id": 7, "user": {"name": "Ann", "tags": ["a", "b"]}, "ok": true}"#;
+
+    fn value_of<'a>(line: &'a str, member: &Member) -> &'a str {
+        crate::text::select_columns(line, &member.value)
+    }
+
+    #[test]
+    fn members_are_found_with_their_positions() {
+        let members = members(LINE);
+        let keys: Vec<&str> = members
+            .iter()
+            .map(|member| member.key.as_str())
+            .collect();
+        assert_eq!(keys, ["id", "user", "ok"]);
+        assert_eq!(value_of(LINE, &members[0]), "7");
+        assert_eq!(
+            crate::text::select_columns(LINE, &members[0].extent),
+            r#""id": 7"#
+        );
+        assert_eq!(
+            value_of(LINE, &members[1]),
+            r#"{"name": "Ann", "tags": ["a", "b"]}"#
+        );
+        assert!(members[1].container);
+        assert_eq!(value_of(LINE, &members[2]), "true");
+    }
+
+    #[test]
+    fn paths_step_into_objects_and_arrays() {
+        let members = members(LINE);
+        let name = find(&members, ".user.name").unwrap();
+        assert_eq!(value_of(LINE, name), r#""Ann""#);
+        let tag = find(&members, "user.tags.1").unwrap();
+        assert_eq!(value_of(LINE, tag), r#""b""#);
+        assert!(find(&members, ".user.age").is_none());
+        assert!(find(&members, ".id.x").is_none());
+    }
+
+    #[test]
+    fn a_line_that_is_not_an_object_has_no_members() {
+        assert!(members("[1, 2]").is_empty());
+        assert!(members("plain text").is_empty());
+        assert!(members(r#"{"a": 1"#).is_empty());
+        assert!(members(r#"{"a": nope}"#).is_empty());
+        assert!(members(r#"{"a": 1} trailing"#).is_empty());
+        assert!(members("{}").is_empty());
+        //an escape JSON does not know, or a `\u` cut short
+        assert!(members(r#"{"a": "\x"}"#).is_empty());
+        assert!(members(r#"{"a": "\u12"}"#).is_empty());
+        assert!(members(r#"{"a\u12": 1}"#).is_empty());
+        assert!(members(r#"{"a": "\u12g4"}"#).is_empty());
+    }
+
+    #[test]
+    fn a_line_nested_too_deeply_has_no_members() {
+        let nested =
+            |depth: usize| format!(r#"{{"a":{}1{}}}"#, "[".repeat(depth), "]".repeat(depth));
+        //the object itself is one level
+        let line = nested(MAX_DEPTH - 1);
+        assert_eq!(members(&line)[0].key, "a");
+        assert!(members(&nested(MAX_DEPTH)).is_empty());
+        //deep enough to run the stack out if the scan kept recursing
+        assert!(members(&nested(200_000)).is_empty());
+    }
+
+    #[test]
+    fn positions_count_chars_not_bytes() {
+        let line = r#"{"é": "ü", "n": 1}"#;
+        let members = members(line);
+        assert_eq!(members[0].key, "é");
+        assert_eq!(value_of(line, &members[1]), "1");
+    }
+
+    #[test]
+    fn strings_decode_and_encode() {
+        assert_eq!(decode(r#""a\"b\\c\nd""#), "a\"b\\c\nd");
+        assert_eq!(decode(r#""\u00e9\ud83d\ude00""#), "é😀");
+        assert_eq!(decode("12"), "12");
+        assert_eq!(encode("a\"b\\c\nd"), r#""a\"b\\c\nd""#);
+        assert_eq!(decode(&encode("tab\there")), "tab\there");
+    }
+
+    #[test]
+    fn numbers_are_inferred_as_json_reads_them() {
+        assert_eq!(infer("10"), "10");
+        assert_eq!(infer("-1.5e3"), "-1.5e3");
+        assert_eq!(infer("007"), r#""007""#);
+        assert_eq!(infer("1."), r#""1.""#);
+        assert_eq!(infer(""), r#""""#);
+        assert_eq!(infer("Ann"), r#""Ann""#);
+        assert!(is_scalar("null"));
+        assert!(!is_scalar("nil"));
+    }
+}
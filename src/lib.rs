@@ -1,11 +1,18 @@
 pub mod cli_args;
+pub mod collate;
 pub mod columns;
 pub mod compose;
 pub mod constants;
+pub mod digest;
 pub mod error;
 pub mod file_processor;
+pub mod format;
+pub mod hyperloglog;
+pub mod json;
 pub mod predicate;
 pub mod ranges;
 pub mod reduce;
+pub mod schema;
+pub mod spill;
 pub mod text;
 pub mod transform;
@@ -1,13 +1,13 @@
 use std::collections::HashSet;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::io::{BufRead, BufReader, BufWriter, Cursor, ErrorKind, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 use ft::cli_args::{Config, InPlace, Input, cli};
 use ft::compose;
 use ft::error::AppError;
-use ft::file_processor::RunOutcome;
+use ft::file_processor::{RunOutcome, read_record};
 
 /// Exit codes follow `grep`: 0 when rows matched, 1 when a filter was
 /// given and nothing matched at all, 2 when the run failed outright.
@@ -234,13 +234,18 @@ fn same_file(a: &Path, b: &Path) -> bool {
 
 /// Open every input and read them as one stream, in the order given —
 /// like `cat`, so a row range addresses the concatenation rather than
-/// each file separately.
+/// each file separately. With `--header` the stream has one header, the
+/// first input's: every later input's own is dropped.
 fn open_inputs(config: &Config) -> Result<Box<dyn BufRead>, AppError> {
     let mut readers: Vec<Box<dyn Read>> = Vec::with_capacity(config.inputs.len());
-    for input in &config.inputs {
-        readers.push(match input {
-            Input::Stdin => Box::new(std::io::stdin()),
+    for (index, input) in config.inputs.iter().enumerate() {
+        let reader: Box<dyn BufRead> = match input {
+            Input::Stdin => Box::new(BufReader::new(std::io::stdin())),
             Input::File(path) => Box::new(open_file(path)?),
+        };
+        readers.push(match config.header && index > 0 {
+            true => Box::new(SkipHeader::new(reader, config.quoted)),
+            false => reader,
         });
     }
 
@@ -254,6 +259,34 @@ fn open_inputs(config: &Config) -> Result<Box<dyn BufRead>, AppError> {
     Ok(Box::new(BufReader::new(chained)))
 }
 
+/// An input whose header row is skipped as it is first read, rather than
+/// when it is opened: stdin may not have one to give yet.
+struct SkipHeader<R: BufRead> {
+    reader: R,
+    quoted: bool,
+    skipped: bool,
+}
+
+impl<R: BufRead> SkipHeader<R> {
+    fn new(reader: R, quoted: bool) -> SkipHeader<R> {
+        SkipHeader {
+            reader,
+            quoted,
+            skipped: false,
+        }
+    }
+}
+
+impl<R: BufRead> Read for SkipHeader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        if !self.skipped {
+            self.skipped = true;
+            read_record(&mut self.reader, self.quoted, &mut Vec::new())?;
+        }
+        self.reader.read(buffer)
+    }
+}
+
 fn open_file(path: &Path) -> Result<BufReader<File>, AppError> {
     let file = File::open(path).map_err(|source| AppError::OpenInput {
         path: path.to_path_buf(),
@@ -264,11 +297,23 @@ fn open_file(path: &Path) -> Result<BufReader<File>, AppError> {
 
 fn process<R: BufRead, W: Write>(
     config: &Config,
-    reader: R,
+    mut reader: R,
     writer: &mut W,
 ) -> Result<RunOutcome, AppError> {
-    compose::build_processor(config)
-        .run(reader, writer)
+    if !config.header {
+        return compose::build_processor(config)
+            .run(reader, writer)
+            .map_err(AppError::Processing);
+    }
+
+    //the column names are looked up in this input's header row, so it
+    //is read ahead — then put back in front, as the processor passes it
+    //through
+    let mut header = Vec::new();
+    read_record(&mut reader, config.quoted, &mut header).map_err(AppError::Processing)?;
+    let config = config.with_header(&String::from_utf8_lossy(&header))?;
+    compose::build_processor(&config)
+        .run(Cursor::new(header).chain(reader), writer)
         .map_err(AppError::Processing)
 }
 
@@ -1,38 +1,113 @@
 //! A set of 1-based, inclusive ranges, possibly non-contiguous
-//! (e.g. rows `1-5,10-20`). A [`RangeSpec`] may contain bounds counted
-//! from the end of the input (`~N`); it resolves into an absolute
-//! [`RangeSet`] once the total number of lines is known.
+//! (e.g. rows `1-5,10-20`) or stepped (`1-100:10`, every tenth row).
+//! A [`RangeSpec`] may contain bounds counted from the end of the
+//! input (`~N`); it resolves into an absolute [`RangeSet`] once the
+//! total number of lines is known. Until then only the last `N` lines
+//! are in doubt, so a streaming caller holds back that many and decides
+//! the rest as they go by.
+//!
+//! A row bound may also be a pattern (`/BEGIN/-/END/`): which rows it
+//! addresses depends on their content, so no line count resolves it.
+//! A [`RowSelector`] follows those parts line by line instead, while
+//! the input streams past.
 
+use std::collections::VecDeque;
 use std::ops::RangeInclusive;
 
-/// One end of a range: an absolute 1-based position, or a position
-/// counted from the end of the input (`~1` is the last line).
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use regex::Regex;
+
+use crate::text;
+
+/// One end of a range: an absolute 1-based position, a position
+/// counted from the end of the input (`~1` is the last line), or the
+/// lines matching a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RangeBound {
     FromStart(usize),
     FromEnd(usize),
+    Pattern(PatternBound),
 }
 
 impl RangeBound {
-    /// The absolute position, given the total number of lines.
-    /// A `FromEnd` bound pointing before the first line yields 0.
-    fn resolve(self, total: usize) -> usize {
+    /// The absolute position, given the total number of lines (or of
+    /// columns, for a column bound). A `FromEnd` bound pointing before
+    /// the first line yields 0; a pattern has no position until a line
+    /// matches it.
+    pub fn resolve(&self, total: usize) -> Option<usize> {
         match self {
-            RangeBound::FromStart(value) => value,
-            RangeBound::FromEnd(value) => (total + 1).saturating_sub(value),
+            RangeBound::FromStart(value) => Some(*value),
+            RangeBound::FromEnd(value) => Some(
+                total
+                    .saturating_add(1)
+                    .saturating_sub(*value),
+            ),
+            RangeBound::Pattern(_) => None,
         }
     }
 }
 
+/// A bound found by content: every line matching `pattern`, shifted by
+/// `offset` lines — `/BEGIN/+1` is the line after each `BEGIN`, and
+/// `/END/-1` the line before each `END`.
+#[derive(Debug, Clone)]
+pub struct PatternBound {
+    pub pattern: Regex,
+    pub offset: isize,
+}
+
+//a compiled regex has no equality of its own; two bounds are the same
+//when they were written the same
+impl PartialEq for PatternBound {
+    fn eq(&self, other: &PatternBound) -> bool {
+        self.pattern.as_str() == other.pattern.as_str() && self.offset == other.offset
+    }
+}
+
+impl Eq for PatternBound {}
+
+/// One part of a row spec: the rows from `from` to `to`, taking every
+/// `step`-th of them counted from the first (a step of 1 takes them all).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangePart {
+    pub from: RangeBound,
+    pub to: RangeBound,
+    pub step: usize,
+}
+
+impl RangePart {
+    /// Whether a bound of the part is a pattern, making it a block that
+    /// is found rather than a position that is resolved.
+    fn is_pattern(&self) -> bool {
+        matches!(self.from, RangeBound::Pattern(_)) || matches!(self.to, RangeBound::Pattern(_))
+    }
+}
+
+impl From<(RangeBound, RangeBound)> for RangePart {
+    fn from((from, to): (RangeBound, RangeBound)) -> RangePart {
+        RangePart { from, to, step: 1 }
+    }
+}
+
 /// Row ranges as written on the command line, possibly end-relative.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RangeSpec {
-    parts: Vec<(RangeBound, RangeBound)>,
+    parts: Vec<RangePart>,
 }
 
 impl RangeSpec {
-    pub fn new(parts: Vec<(RangeBound, RangeBound)>) -> RangeSpec {
-        RangeSpec { parts }
+    /// A spec from its parts; a pair of bounds is a part taking every row.
+    pub fn new<P: Into<RangePart>>(parts: Vec<P>) -> RangeSpec {
+        RangeSpec {
+            parts: parts
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+
+    /// The parts as written.
+    pub fn parts(&self) -> &[RangePart] {
+        &self.parts
     }
 
     /// The spec covering everything (row 1 onwards).
@@ -40,65 +115,114 @@ impl RangeSpec {
         RangeSpec::from(1..=usize::MAX)
     }
 
-    /// Whether no bound is counted from the end — if so, the spec can
-    /// be resolved without knowing the total number of lines.
-    pub fn is_absolute(&self) -> bool {
-        self.parts.iter().all(|(from, to)| {
-            matches!(from, RangeBound::FromStart(_)) && matches!(to, RangeBound::FromStart(_))
-        })
+    /// How many lines from the end the spec reaches: the largest `~N`
+    /// among its bounds, 0 when nothing is counted from the end. Any
+    /// row before those last lines is decided the same whatever the
+    /// total turns out to be, so only that many lines must wait for the
+    /// input to end. `None` when a pattern part is bounded by `~N`: the
+    /// blocks it finds depend on where the end lies, so no row can be
+    /// decided before the whole input is read.
+    pub fn tail(&self) -> Option<usize> {
+        let mut tail = 0;
+        for part in &self.parts {
+            for bound in [&part.from, &part.to] {
+                if let RangeBound::FromEnd(value) = bound {
+                    if part.is_pattern() {
+                        return None;
+                    }
+                    tail = tail.max(*value);
+                }
+            }
+        }
+        Some(tail)
     }
 
-    /// Resolve every bound against the total number of lines. Parts
-    /// that fall entirely before the first line disappear.
+    /// Resolve every positional bound against the total number of
+    /// lines. Parts that fall entirely before the first line disappear,
+    /// and so do the parts addressed by a pattern, which no total can
+    /// resolve — [`RangeSpec::selector`] follows those.
     pub fn resolve(&self, total: usize) -> RangeSet {
         let parts = self
             .parts
             .iter()
-            .filter_map(|(from, to)| {
-                let from = from.resolve(total).max(1);
-                let to = to.resolve(total);
-                (from <= to).then_some(from..=to)
+            .filter_map(|part| {
+                let from = part.from.resolve(total)?;
+                let to = part.to.resolve(total)?;
+                //a stepped part counts its steps from where it was written
+                //to start, even a row before the first (`0~4` is 4, 8, …)
+                let from = if part.step == 1 { from.max(1) } else { from };
+                (from <= to).then_some((from..=to, part.step))
+            })
+            .collect();
+        RangeSet::with_steps(parts)
+    }
+
+    /// Everything needed to decide, line by line, whether a row is
+    /// selected: the positional parts resolved against `total`, and the
+    /// pattern parts, ready to be followed. Pass `usize::MAX` while the
+    /// total is unknown — which is only right when the spec has a
+    /// [`tail`](RangeSpec::tail) — and the selector holds the rows within
+    /// it until told the input [ended](RowSelector::end_input).
+    pub fn selector(&self, total: usize) -> RowSelector {
+        let blocks = self
+            .parts
+            .iter()
+            .filter(|part| part.is_pattern())
+            .map(|part| {
+                BlockTracker::new(
+                    Edge::of(&part.from, total),
+                    Edge::of(&part.to, total),
+                    part.step,
+                )
             })
             .collect();
-        RangeSet::new(parts)
+        RowSelector::new(self, total, blocks)
     }
 }
 
 impl From<RangeInclusive<usize>> for RangeSpec {
     fn from(range: RangeInclusive<usize>) -> RangeSpec {
-        RangeSpec {
-            parts: vec![(
-                RangeBound::FromStart(*range.start()),
-                RangeBound::FromStart(*range.end()),
-            )],
-        }
+        RangeSpec::new(vec![(
+            RangeBound::FromStart(*range.start()),
+            RangeBound::FromStart(*range.end()),
+        )])
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RangeSet {
     parts: Vec<RangeInclusive<usize>>,
+    //parts taking every step-th value from their start, with the step;
+    //they have gaps, so they merge with nothing
+    stepped: Vec<(RangeInclusive<usize>, usize)>,
 }
 
 impl RangeSet {
     /// Build a normalized set: parts are sorted by start and
     /// overlapping or adjacent parts are merged into one.
-    pub fn new(mut parts: Vec<RangeInclusive<usize>>) -> RangeSet {
-        parts.sort_by_key(|part| *part.start());
-
-        let mut merged: Vec<RangeInclusive<usize>> = Vec::new();
-        for part in parts {
-            match merged.last_mut() {
-                Some(last) if *part.start() <= last.end().saturating_add(1) => {
-                    if part.end() > last.end() {
-                        *last = *last.start()..=*part.end();
-                    }
-                }
-                _ => merged.push(part),
-            }
+    pub fn new(parts: Vec<RangeInclusive<usize>>) -> RangeSet {
+        RangeSet {
+            parts: merge(parts),
+            stepped: Vec::new(),
         }
+    }
 
-        RangeSet { parts: merged }
+    /// Build a set whose parts each take every step-th value, counted
+    /// from their start. Parts with a step of 1 are normalized as
+    /// [`RangeSet::new`] does.
+    pub fn with_steps(parts: Vec<(RangeInclusive<usize>, usize)>) -> RangeSet {
+        let (whole, stepped): (Vec<_>, Vec<_>) = parts
+            .into_iter()
+            .partition(|(_, step)| *step == 1);
+        RangeSet {
+            parts: merge(
+                whole
+                    .into_iter()
+                    .map(|(part, _)| part)
+                    .collect(),
+            ),
+            stepped,
+        }
     }
 
     /// The set covering everything (row/column 1 onwards).
@@ -110,17 +234,268 @@ impl RangeSet {
         self.parts
             .iter()
             .any(|part| part.contains(&value))
+            || self.stepped.iter().any(|(part, step)| {
+                part.contains(&value) && (value - part.start()).is_multiple_of(*step)
+            })
     }
 
-    /// The normalized parts, ascending and non-overlapping.
+    /// The normalized contiguous parts, ascending and non-overlapping.
     pub fn parts(&self) -> &[RangeInclusive<usize>] {
         &self.parts
     }
 }
 
+/// Sort parts by start and merge those overlapping or adjacent.
+fn merge(mut parts: Vec<RangeInclusive<usize>>) -> Vec<RangeInclusive<usize>> {
+    parts.sort_by_key(|part| *part.start());
+
+    let mut merged: Vec<RangeInclusive<usize>> = Vec::new();
+    for part in parts {
+        match merged.last_mut() {
+            Some(last) if *part.start() <= last.end().saturating_add(1) => {
+                if part.end() > last.end() {
+                    *last = *last.start()..=*part.end();
+                }
+            }
+            _ => merged.push(part),
+        }
+    }
+
+    merged
+}
+
 impl From<RangeInclusive<usize>> for RangeSet {
     fn from(range: RangeInclusive<usize>) -> RangeSet {
-        RangeSet { parts: vec![range] }
+        RangeSet {
+            parts: vec![range],
+            stepped: Vec::new(),
+        }
+    }
+}
+
+/// Decides which rows lie in the row range. Positional parts are known
+/// up front; a pattern part is followed as the lines go by, one block
+/// per match of its start — so `/BEGIN/-/END/` selects every such block
+/// in the input, not just the first.
+///
+/// A bound shifted backwards (`/END/-1`) selects a row before the line
+/// that decides it, so the selector cannot answer for a row until it has
+/// seen [`delay`](RowSelector::delay) more lines past it: the caller
+/// holds that many lines back. So does a bound counted from the end
+/// (`~N`): the last `N` rows wait until the input ends, and the lines
+/// before them stream on, like `tail` keeps only its last lines. Without
+/// either the delay is 0 and each row is decided as it is read.
+#[derive(Debug)]
+pub struct RowSelector {
+    //the positional parts as written, resolved again once the total is
+    //known; `None` when nothing counts from the end
+    end_relative: Option<RangeSpec>,
+    positions: RangeSet,
+    blocks: Vec<BlockTracker>,
+    //lines observed so far, which is the number of the last one
+    seen: usize,
+    delay: usize,
+}
+
+impl RowSelector {
+    fn new(spec: &RangeSpec, total: usize, blocks: Vec<BlockTracker>) -> RowSelector {
+        let tail = spec.tail().unwrap_or(0);
+        let delay = blocks
+            .iter()
+            .map(BlockTracker::lookahead)
+            .fold(tail, usize::max);
+        RowSelector {
+            end_relative: (tail > 0).then(|| spec.clone()),
+            positions: spec.resolve(total),
+            blocks,
+            seen: 0,
+            delay,
+        }
+    }
+
+    /// How many lines past a row must be observed before
+    /// [`contains`](RowSelector::contains) can answer for it.
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    /// How many lines were observed so far.
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
+    /// Take note of the next line, returning its 1-based number.
+    /// Without a pattern part the content is not even looked at.
+    pub fn observe(&mut self, raw_line: &[u8]) -> usize {
+        self.seen += 1;
+        if !self.blocks.is_empty() {
+            //a pattern is matched against the content without its
+            //terminator; a line that is not UTF-8 is matched as
+            //best it can be rather than stopping the run here
+            let line = String::from_utf8_lossy(raw_line);
+            let (content, _) = text::split_line_terminator(&line);
+            for block in &mut self.blocks {
+                block.observe(self.seen, content);
+            }
+        }
+        self.seen
+    }
+
+    /// Take note that the input ended: the lines observed are all there
+    /// are, so bounds counted from the end now resolve against them.
+    pub fn end_input(&mut self) {
+        if let Some(spec) = &self.end_relative {
+            self.positions = spec.resolve(self.seen);
+        }
+    }
+
+    /// Whether the row is selected. Rows must be asked about in
+    /// ascending order, each once `delay` further lines were observed
+    /// (or the input [ended](RowSelector::end_input)): blocks wholly
+    /// behind the row asked about are forgotten, so following a
+    /// pattern takes no more memory the longer the input is.
+    pub fn contains(&mut self, line_number: usize) -> bool {
+        let mut selected = self.positions.contains(line_number);
+        for block in &mut self.blocks {
+            selected |= block.contains(line_number);
+        }
+        selected
+    }
+}
+
+/// Where a block of rows starts or ends: at a fixed row, or at a line
+/// matching a pattern, shifted by an offset.
+#[derive(Debug)]
+struct Edge {
+    anchor: Anchor,
+    offset: isize,
+}
+
+#[derive(Debug)]
+enum Anchor {
+    Row(usize),
+    Pattern(Regex),
+}
+
+impl Edge {
+    /// The edge a bound describes; a positional bound is resolved
+    /// against the total number of lines first.
+    fn of(bound: &RangeBound, total: usize) -> Edge {
+        match bound {
+            RangeBound::Pattern(PatternBound { pattern, offset }) => Edge {
+                anchor: Anchor::Pattern(pattern.clone()),
+                offset: *offset,
+            },
+            positional => Edge {
+                anchor: Anchor::Row(positional.resolve(total).unwrap_or(0)),
+                offset: 0,
+            },
+        }
+    }
+
+    /// Whether a block starts on this line.
+    fn starts(&self, line_number: usize, content: &str) -> bool {
+        match &self.anchor {
+            Anchor::Row(row) => line_number == *row,
+            Anchor::Pattern(pattern) => pattern.is_match(content),
+        }
+    }
+
+    /// Whether the open block ends on this line. A fixed row already
+    /// passed ends it at once, like `sed` does with `/START/,5`.
+    fn ends(&self, line_number: usize, content: &str) -> bool {
+        match &self.anchor {
+            Anchor::Row(row) => line_number >= *row,
+            Anchor::Pattern(pattern) => pattern.is_match(content),
+        }
+    }
+
+    /// The row selected for an anchor found on `line_number`.
+    fn shift(&self, line_number: usize) -> usize {
+        line_number.saturating_add_signed(self.offset)
+    }
+}
+
+/// The rows of one block: `last` stays `None` while its end has not
+/// been seen, and a block never closed runs to the end of the input.
+#[derive(Debug)]
+struct Block {
+    first: usize,
+    last: Option<usize>,
+}
+
+/// Follows one pattern part of a row spec. Like awk's `/START/,/END/`,
+/// the end is looked for from the start line on, so a line matching
+/// both is a block of its own — which is what makes a lone `/RE/`
+/// (`/RE/-/RE/`) select just the matching lines. Once a block ends, the
+/// next start is looked for from the following line.
+#[derive(Debug)]
+struct BlockTracker {
+    start: Edge,
+    end: Edge,
+    //every step-th row of a block is selected, counted from its first
+    step: usize,
+    inside: bool,
+    //the blocks found and not yet left behind, in input order
+    blocks: VecDeque<Block>,
+}
+
+impl BlockTracker {
+    fn new(start: Edge, end: Edge, step: usize) -> BlockTracker {
+        BlockTracker {
+            start,
+            end,
+            step,
+            inside: false,
+            blocks: VecDeque::new(),
+        }
+    }
+
+    /// How many lines a row must wait for: as many as the furthest
+    /// backward shift, since that is how far ahead its deciding line
+    /// can lie.
+    fn lookahead(&self) -> usize {
+        [self.start.offset, self.end.offset]
+            .into_iter()
+            .map(|offset| offset.min(0).unsigned_abs())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn observe(&mut self, line_number: usize, content: &str) {
+        if !self.inside && self.start.starts(line_number, content) {
+            self.inside = true;
+            self.blocks.push_back(Block {
+                first: self.start.shift(line_number).max(1),
+                last: None,
+            });
+        }
+        if self.inside && self.end.ends(line_number, content) {
+            self.inside = false;
+            if let Some(block) = self.blocks.back_mut() {
+                block.last = Some(self.end.shift(line_number));
+            }
+        }
+    }
+
+    fn contains(&mut self, line_number: usize) -> bool {
+        //rows are asked about in ascending order, and a later block
+        //never ends before an earlier one: whatever ended before this
+        //row is of no further use
+        while let Some(Block {
+            last: Some(last), ..
+        }) = self.blocks.front()
+            && *last < line_number
+        {
+            self.blocks.pop_front();
+        }
+        self.blocks.iter().any(|block| {
+            block.first <= line_number
+                && block
+                    .last
+                    .is_none_or(|last| line_number <= last)
+                && (line_number - block.first).is_multiple_of(self.step)
+        })
     }
 }
 
@@ -156,12 +531,18 @@ mod tests {
     }
 
     #[test]
-    fn spec_from_absolute_range_is_absolute() {
-        assert!(RangeSpec::from(2..=4).is_absolute());
-        assert!(RangeSpec::full().is_absolute());
+    fn spec_from_absolute_range_has_no_tail() {
+        assert_eq!(RangeSpec::from(2..=4).tail(), Some(0));
+        assert_eq!(RangeSpec::full().tail(), Some(0));
+    }
 
-        let relative = RangeSpec::new(vec![(RangeBound::FromEnd(2), RangeBound::FromEnd(1))]);
-        assert!(!relative.is_absolute());
+    #[test]
+    fn tail_is_the_furthest_end_relative_bound() {
+        let spec = RangeSpec::new(vec![
+            (RangeBound::FromEnd(2), RangeBound::FromEnd(1)),
+            (RangeBound::FromStart(3), RangeBound::FromEnd(5)),
+        ]);
+        assert_eq!(spec.tail(), Some(5));
     }
 
     #[test]
@@ -191,4 +572,209 @@ mod tests {
         let spec = RangeSpec::new(vec![(RangeBound::FromStart(5), RangeBound::FromEnd(3))]);
         assert_eq!(spec.resolve(5), RangeSet::new(vec![]));
     }
+
+    fn pattern(source: &str, offset: isize) -> RangeBound {
+        RangeBound::Pattern(PatternBound {
+            pattern: Regex::new(source).unwrap(),
+            offset,
+        })
+    }
+
+    /// The rows a spec selects from `lines`, asked about as a streaming
+    /// caller would: each once `delay` more lines were observed, and
+    /// without knowing the total unless the spec has no tail.
+    fn selected(spec: &RangeSpec, lines: &[&str]) -> Vec<usize> {
+        let total = match spec.tail() {
+            Some(_) => usize::MAX,
+            None => lines.len(),
+        };
+        let mut selector = spec.selector(total);
+        let mut rows = Vec::new();
+        let mut decided = 0;
+        for line in lines {
+            let seen = selector.observe(line.as_bytes());
+            while decided + selector.delay() < seen {
+                decided += 1;
+                if selector.contains(decided) {
+                    rows.push(decided);
+                }
+            }
+        }
+        //the input ended: whatever still waited can be decided now
+        selector.end_input();
+        while decided < lines.len() {
+            decided += 1;
+            if selector.contains(decided) {
+                rows.push(decided);
+            }
+        }
+        rows
+    }
+
+    const BLOCKS: [&str; 8] = ["a", "BEGIN", "b", "END", "c", "BEGIN", "d", "END"];
+
+    #[test]
+    fn pattern_parts_have_no_tail_and_no_resolution() {
+        let spec = RangeSpec::new(vec![(pattern("BEGIN", 0), pattern("END", 0))]);
+        assert_eq!(spec.tail(), Some(0));
+        //no total says where a pattern matches, so resolving leaves it out
+        assert_eq!(spec.resolve(100), RangeSet::new(vec![]));
+    }
+
+    #[test]
+    fn pattern_part_selects_every_block() {
+        let spec = RangeSpec::new(vec![(pattern("BEGIN", 0), pattern("END", 0))]);
+        assert_eq!(selected(&spec, &BLOCKS), [2, 3, 4, 6, 7, 8]);
+    }
+
+    #[test]
+    fn offsets_make_the_bounds_exclusive() {
+        let spec = RangeSpec::new(vec![(pattern("BEGIN", 1), pattern("END", -1))]);
+        let selector = spec.selector(usize::MAX);
+        //the row before an END is only known once the END is read
+        assert_eq!(selector.delay(), 1);
+        assert_eq!(selected(&spec, &BLOCKS), [3, 7]);
+    }
+
+    #[test]
+    fn offsets_reach_outside_the_block() {
+        //one line of context on either side of each block
+        let spec = RangeSpec::new(vec![(pattern("BEGIN", -1), pattern("END", 1))]);
+        assert_eq!(selected(&spec, &BLOCKS), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn a_block_without_an_end_runs_to_the_end_of_the_input() {
+        let spec = RangeSpec::new(vec![(pattern("c", 0), pattern("NEVER", 0))]);
+        assert_eq!(selected(&spec, &BLOCKS), [5, 6, 7, 8]);
+
+        let spec = RangeSpec::new(vec![(pattern("c", 0), RangeBound::FromStart(usize::MAX))]);
+        assert_eq!(selected(&spec, &BLOCKS), [5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn a_lone_pattern_selects_the_matching_lines() {
+        //the end is looked for on the start line too, like awk
+        let spec = RangeSpec::new(vec![(pattern("BEGIN", 0), pattern("BEGIN", 0))]);
+        assert_eq!(selected(&spec, &BLOCKS), [2, 6]);
+
+        //and shifted, the line after each match
+        let spec = RangeSpec::new(vec![(pattern("BEGIN", 1), pattern("BEGIN", 1))]);
+        assert_eq!(selected(&spec, &BLOCKS), [3, 7]);
+    }
+
+    #[test]
+    fn pattern_bounds_mix_with_positions() {
+        //from row 1 to the first END: one block, the END closes it
+        let spec = RangeSpec::new(vec![(RangeBound::FromStart(1), pattern("END", 0))]);
+        assert_eq!(selected(&spec, &BLOCKS), [1, 2, 3, 4]);
+
+        //from the first BEGIN to the second-last line: where that block
+        //ends depends on the total, so the whole input is needed
+        let spec = RangeSpec::new(vec![(pattern("BEGIN", 0), RangeBound::FromEnd(2))]);
+        assert_eq!(spec.tail(), None);
+        assert_eq!(selected(&spec, &BLOCKS), [2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn pattern_parts_combine_with_positional_parts() {
+        let spec = RangeSpec::new(vec![
+            (RangeBound::FromStart(1), RangeBound::FromStart(1)),
+            (pattern("BEGIN", 1), pattern("END", -1)),
+        ]);
+        assert_eq!(selected(&spec, &BLOCKS), [1, 3, 7]);
+    }
+
+    #[test]
+    fn end_relative_rows_wait_only_for_the_tail() {
+        let lines = ["1", "2", "3", "4", "5", "6"];
+        let spec = RangeSpec::new(vec![(RangeBound::FromEnd(3), RangeBound::FromEnd(2))]);
+        let mut selector = spec.selector(usize::MAX);
+        assert_eq!(selector.delay(), 3);
+        for line in lines {
+            selector.observe(line.as_bytes());
+        }
+        //the first 3 rows are decided before the end, and never selected
+        for row in 1..=3 {
+            assert!(!selector.contains(row));
+        }
+        selector.end_input();
+        assert!(selector.contains(4));
+        assert!(selector.contains(5));
+        assert!(!selector.contains(6));
+    }
+
+    #[test]
+    fn end_relative_rows_stream_with_patterns_and_positions() {
+        //all but the last line, plus every block
+        let spec = RangeSpec::new(vec![
+            (RangeBound::FromStart(1), RangeBound::FromEnd(2)),
+            (pattern("BEGIN", 0), pattern("END", 0)),
+        ]);
+        assert_eq!(spec.tail(), Some(2));
+        assert_eq!(selected(&spec, &BLOCKS), [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let spec = RangeSpec::new(vec![
+            (RangeBound::FromEnd(2), RangeBound::FromEnd(2)),
+            (pattern("BEGIN", 1), pattern("BEGIN", 1)),
+        ]);
+        assert_eq!(selected(&spec, &BLOCKS), [3, 7]);
+    }
+
+    #[test]
+    fn stepped_parts_take_every_nth_value_from_their_start() {
+        let set = RangeSet::with_steps(vec![(2..=10, 3)]);
+        let values: Vec<usize> = (1..=12)
+            .filter(|value| set.contains(*value))
+            .collect();
+        assert_eq!(values, [2, 5, 8]);
+    }
+
+    #[test]
+    fn stepped_parts_merge_with_nothing() {
+        let set = RangeSet::with_steps(vec![(1..=2, 1), (3..=9, 2), (2..=4, 1)]);
+        assert_eq!(set.parts(), [1..=4]);
+        assert!(set.contains(5));
+        assert!(!set.contains(6));
+    }
+
+    #[test]
+    fn stepped_end_relative_parts_count_from_their_resolved_start() {
+        //~5-~1:2 on 8 lines is every other row of 4-8
+        let spec = RangeSpec::new(vec![RangePart {
+            from: RangeBound::FromEnd(5),
+            to: RangeBound::FromEnd(1),
+            step: 2,
+        }]);
+        assert_eq!(spec.resolve(8), RangeSet::with_steps(vec![(4..=8, 2)]));
+        assert_eq!(selected(&spec, &BLOCKS), [4, 6, 8]);
+    }
+
+    #[test]
+    fn a_stepped_start_before_the_first_row_keeps_its_phase() {
+        //like sed's 0~3
+        let spec = RangeSpec::new(vec![RangePart {
+            from: RangeBound::FromStart(0),
+            to: RangeBound::FromStart(usize::MAX),
+            step: 3,
+        }]);
+        assert_eq!(selected(&spec, &BLOCKS), [3, 6]);
+    }
+
+    #[test]
+    fn stepped_pattern_parts_count_from_each_block() {
+        let spec = RangeSpec::new(vec![RangePart {
+            from: pattern("BEGIN", 0),
+            to: pattern("END", 0),
+            step: 2,
+        }]);
+        assert_eq!(selected(&spec, &BLOCKS), [2, 4, 6, 8]);
+    }
+
+    #[test]
+    fn pattern_bounds_compare_by_their_source() {
+        assert_eq!(pattern("a+", 1), pattern("a+", 1));
+        assert_ne!(pattern("a+", 1), pattern("a+", 0));
+        assert_ne!(pattern("a+", 1), pattern("b", 1));
+    }
 }
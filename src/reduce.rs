@@ -2,16 +2,21 @@
 //! [`crate::transform::LineTransform`] can: instead of turning a line
 //! into lines, it consumes every processed line and writes a summary
 //! once the input ends (`--count`, `--sum`, `--avg`, `--min`, `--max`,
-//! optionally per `--group-by` key).
+//! `--stddev`, `--var`, `--skew`, `--median`, `--percentile`,
+//! `--distinct`, `--first`, `--last`, `--mode`, `--collect`, optionally
+//! per `--group-by` key, or a `--histogram`).
 //!
 //! The engine writes the lines *or* hands them to a reducer, never both:
 //! a summary replaces the rows it summarizes.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
 
+use crate::collate::{Compare, SortOrder, SortValue};
 use crate::columns::ColumnSpan;
 use crate::constants::NEW_LINE;
+use crate::digest::TDigest;
+use crate::hyperloglog::HyperLogLog;
 
 /// Something computed over the processed lines as a whole.
 pub trait LineReducer {
@@ -61,46 +66,510 @@ impl LineReducer for Join {
     }
 }
 
-/// One summary column: how many rows, or a statistic over the numbers
-/// found in a column span.
+/// How a histogram groups numbers into bins: into a number of equal bins
+/// spanning the smallest to the largest (`--bins`), or into bins of a
+/// width, aligned on its multiples (`--bin-width`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binning {
+    Count(usize),
+    Width(f64),
+}
+
+/// The most bins a histogram lists: `--bins` may ask for no more, and
+/// `--bin-width` bins spread wider than this list only those that hold
+/// a number, rather than every empty one between them.
+pub const MAX_BINS: usize = 10_000;
+
+/// How far from zero a `--bin-width` bin may lie: past 2^53 widths, the
+/// bounds of neighbouring bins are the same float.
+const BIN_LIMIT: f64 = 9_007_199_254_740_992.0;
+
+/// The longest bar `--bar` draws, for the most frequent value.
+const BAR_WIDTH: usize = 40;
+
+/// A frequency table (`--histogram`, like `sort | uniq -c | sort -rn`
+/// with each value's share of the total): how often each value of a
+/// column appears, the most frequent first. With a binning, the numbers
+/// are counted per bin instead, and the bins listed in order, empty ones
+/// included, so the shape of the distribution shows.
+pub struct Histogram {
+    span: ColumnSpan,
+    separator: String,
+    bar: bool,
+    tally: Tally,
+}
+
+/// What a histogram counts as the rows arrive.
+enum Tally {
+    //every value, in first-seen order so that ties keep the input's
+    Values {
+        order: Vec<String>,
+        counts: HashMap<String, u64>,
+    },
+    //equal bins need the smallest and largest number, known at the end
+    Numbers {
+        bins: usize,
+        numbers: Vec<f64>,
+    },
+    //bins of a fixed width can be counted right away, by index
+    Bins {
+        width: f64,
+        counts: HashMap<i64, u64>,
+    },
+}
+
+impl Histogram {
+    pub fn new(
+        span: ColumnSpan,
+        binning: Option<Binning>,
+        bar: bool,
+        separator: impl Into<String>,
+    ) -> Histogram {
+        let tally = match binning {
+            None => Tally::Values {
+                order: Vec::new(),
+                counts: HashMap::new(),
+            },
+            Some(Binning::Count(bins)) => Tally::Numbers {
+                bins,
+                numbers: Vec::new(),
+            },
+            Some(Binning::Width(width)) => Tally::Bins {
+                width,
+                counts: HashMap::new(),
+            },
+        };
+        Histogram {
+            span,
+            separator: separator.into(),
+            bar,
+            tally,
+        }
+    }
+
+    /// The rows of the table: the value or the bounds of the bin, and
+    /// how many there were.
+    fn rows(&self) -> Vec<(Vec<String>, u64)> {
+        match &self.tally {
+            Tally::Values { order, counts } => {
+                let mut rows: Vec<(Vec<String>, u64)> = order
+                    .iter()
+                    .map(|value| (vec![value.clone()], counts[value]))
+                    .collect();
+                //a stable sort, so equal counts stay in first-seen order
+                rows.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+                rows
+            }
+            Tally::Numbers { bins, numbers } => {
+                let Some(least) = numbers.iter().copied().reduce(f64::min) else {
+                    return Vec::new();
+                };
+                let greatest = numbers
+                    .iter()
+                    .copied()
+                    .fold(least, f64::max);
+                //all the numbers the same: one bin holds them all
+                let bins = if greatest > least { *bins } else { 1 };
+                let width = (greatest - least) / bins as f64;
+                let mut counts = vec![0u64; bins];
+                for number in numbers {
+                    //the largest number closes the last bin rather than
+                    //opening one of its own
+                    let bin = ((number - least) / width) as usize;
+                    counts[bin.min(bins - 1)] += 1;
+                }
+                counts
+                    .into_iter()
+                    .enumerate()
+                    .map(|(bin, count)| {
+                        let from = least + width * bin as f64;
+                        let to = match bin + 1 == bins {
+                            true => greatest,
+                            false => least + width * (bin + 1) as f64,
+                        };
+                        (vec![from.to_string(), to.to_string()], count)
+                    })
+                    .collect()
+            }
+            Tally::Bins { width, counts } => {
+                let (Some(&first), Some(&last)) = (counts.keys().min(), counts.keys().max()) else {
+                    return Vec::new();
+                };
+                let bins: Vec<i64> = match last - first < MAX_BINS as i64 {
+                    true => (first..=last).collect(),
+                    false => {
+                        let mut bins: Vec<i64> = counts.keys().copied().collect();
+                        bins.sort_unstable();
+                        bins
+                    }
+                };
+                bins.into_iter()
+                    .map(|bin| {
+                        let bounds = vec![
+                            (bin as f64 * width).to_string(),
+                            ((bin + 1) as f64 * width).to_string(),
+                        ];
+                        (bounds, counts.get(&bin).copied().unwrap_or(0))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl LineReducer for Histogram {
+    /// Like a summary, a histogram is only known once the input ends.
+    fn accept(&mut self, line: &str, _writer: &mut dyn Write) -> io::Result<()> {
+        match &mut self.tally {
+            Tally::Values { order, counts } => {
+                let value = self.span.select(line);
+                //an empty value is a missing one, as for --distinct
+                if value.is_empty() {
+                    return Ok(());
+                }
+                if let Some(count) = counts.get_mut(&*value) {
+                    *count += 1;
+                } else {
+                    order.push(value.to_string());
+                    counts.insert(value.into_owned(), 1);
+                }
+            }
+            Tally::Numbers { numbers, .. } => numbers.extend(read_number(&self.span, line)),
+            Tally::Bins { width, counts } => {
+                let bin = read_number(&self.span, line).map(|number| (number / *width).floor());
+                if let Some(bin) = bin.filter(|bin| bin.abs() < BIN_LIMIT) {
+                    *counts.entry(bin as i64).or_default() += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write) -> io::Result<()> {
+        let rows = self.rows();
+        let total: u64 = rows
+            .iter()
+            .map(|(_, count)| count)
+            .sum();
+        let most = rows
+            .iter()
+            .map(|(_, count)| *count)
+            .max()
+            .unwrap_or(0);
+        for (mut columns, count) in rows {
+            columns.push(count.to_string());
+            columns.push(format!("{:.1}", count as f64 * 100.0 / total as f64));
+            if self.bar {
+                let length = (count as f64 * BAR_WIDTH as f64 / most as f64).round() as usize;
+                columns.push("#".repeat(length));
+            }
+            writeln!(writer, "{}", columns.join(&self.separator))?;
+        }
+        Ok(())
+    }
+}
+
+/// One summary column: how many rows, a statistic over the numbers found
+/// in a column span, or one over its text.
 #[derive(Debug, Clone)]
 pub enum Aggregate {
     Count,
     Sum(ColumnSpan),
     Avg(ColumnSpan),
-    Min(ColumnSpan),
-    Max(ColumnSpan),
+    /// The least value, compared as numbers or as `--version-sort` or
+    /// `--human-numeric-sort` would, and written as it was read.
+    Min(ColumnSpan, Compare),
+    Max(ColumnSpan, Compare),
+    StdDev(ColumnSpan, Estimator),
+    Var(ColumnSpan, Estimator),
+    Skew(ColumnSpan, Estimator),
+    Median(ColumnSpan, QuantileMethod),
+    /// The value a percentage (0 to 100) of the values lie below.
+    Percentile(f64, ColumnSpan, QuantileMethod),
+    /// How many different values there are.
+    Distinct(ColumnSpan, DistinctMethod),
+    First(ColumnSpan),
+    Last(ColumnSpan),
+    /// The most common value.
+    Mode(ColumnSpan),
+    /// Every value, joined by a separator.
+    Collect(ColumnSpan, String),
+}
+
+/// How a median or percentile is found: from every value, kept until
+/// the input ends, or estimated from a sketch of bounded size
+/// (`--approximate`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantileMethod {
+    Exact,
+    Approximate,
+}
+
+/// How different values are counted: by remembering each one, or
+/// estimated from a HyperLogLog of 2^precision registers
+/// (`--approximate`, `--precision`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistinctMethod {
+    Exact,
+    Approximate { precision: u8 },
+}
+
+/// Whom a standard deviation, variance or skewness describes: the rows
+/// as a sample of something larger (dividing by `n - 1`, the default,
+/// as spreadsheets and SQL's `STDDEV` do), or as the whole population
+/// (`--population`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Estimator {
+    Sample,
+    Population,
 }
 
 impl Aggregate {
+    /// What the statistic is called: the name of its summary column.
+    pub fn name(&self) -> String {
+        match self {
+            Aggregate::Count => "count".to_owned(),
+            Aggregate::Sum(_) => "sum".to_owned(),
+            Aggregate::Avg(_) => "avg".to_owned(),
+            Aggregate::Min(..) => "min".to_owned(),
+            Aggregate::Max(..) => "max".to_owned(),
+            Aggregate::StdDev(..) => "stddev".to_owned(),
+            Aggregate::Var(..) => "var".to_owned(),
+            Aggregate::Skew(..) => "skew".to_owned(),
+            Aggregate::Median(..) => "median".to_owned(),
+            Aggregate::Percentile(rank, ..) => format!("p{rank}"),
+            Aggregate::Distinct(..) => "distinct".to_owned(),
+            Aggregate::First(_) => "first".to_owned(),
+            Aggregate::Last(_) => "last".to_owned(),
+            Aggregate::Mode(_) => "mode".to_owned(),
+            Aggregate::Collect(..) => "collect".to_owned(),
+        }
+    }
+
+    /// The columns the statistic reads; the row count reads none.
+    pub fn span(&self) -> Option<&ColumnSpan> {
+        match self {
+            Aggregate::Count => None,
+            Aggregate::Sum(span)
+            | Aggregate::Avg(span)
+            | Aggregate::Min(span, _)
+            | Aggregate::Max(span, _)
+            | Aggregate::StdDev(span, _)
+            | Aggregate::Var(span, _)
+            | Aggregate::Skew(span, _)
+            | Aggregate::Median(span, _)
+            | Aggregate::Percentile(_, span, _)
+            | Aggregate::Distinct(span, _)
+            | Aggregate::First(span)
+            | Aggregate::Last(span)
+            | Aggregate::Mode(span)
+            | Aggregate::Collect(span, _) => Some(span),
+        }
+    }
+
     /// The number this aggregate reads from a line. `None` for `Count`,
     /// which counts rows rather than values, and for a value that is not
     /// a number — those rows are skipped rather than counted as zero,
     /// which would bend both the sum and the average.
     fn number(&self, line: &str) -> Option<f64> {
-        let span = match self {
-            Aggregate::Count => return None,
-            Aggregate::Sum(span)
-            | Aggregate::Avg(span)
-            | Aggregate::Min(span)
-            | Aggregate::Max(span) => span,
-        };
-        span.select(line)
-            .trim()
-            .parse()
-            .ok()
-            .filter(|value: &f64| !value.is_nan())
+        read_number(self.span()?, line)
+    }
+
+    /// The text this aggregate reads from a line, as it stands. An empty
+    /// value is a missing one, and is skipped like a missing number.
+    fn text(&self, line: &str) -> Option<String> {
+        Some(self.span()?.select(line).into_owned()).filter(|value| !value.is_empty())
     }
 }
 
+/// The number a column span holds in a line, as every statistic reads
+/// it: surrounding whitespace aside, and never NaN or an infinity, so
+/// that a value that is not a number is skipped the same way wherever
+/// numbers are read — an infinity would take over a sum for good, and
+/// leave a histogram no bins.
+pub fn read_number(span: &ColumnSpan, line: &str) -> Option<f64> {
+    span.select(line)
+        .trim()
+        .parse()
+        .ok()
+        .filter(|value: &f64| value.is_finite())
+}
+
 /// One aggregate's running value within one group.
 #[derive(Debug)]
 enum Accumulator {
     Count(u64),
     Sum(f64),
-    Avg { sum: f64, count: u64 },
+    Avg {
+        sum: f64,
+        count: u64,
+    },
     Min(Option<f64>),
     Max(Option<f64>),
+    //the least or greatest value by another order than the numbers':
+    //its sort value to compare the next one with, and its text to print
+    Extreme {
+        compare: Compare,
+        greatest: bool,
+        best: Option<(SortValue, String)>,
+    },
+    Moments {
+        moment: Moment,
+        estimator: Estimator,
+        moments: Moments,
+    },
+    Quantile {
+        rank: f64,
+        values: Values,
+    },
+    Distinct(Seen),
+    First(Option<String>),
+    Last(Option<String>),
+    //how often each value appeared, and when it first did, so a tie goes
+    //to the value seen first rather than to the hash map's order
+    Mode(HashMap<String, (u64, usize)>),
+    Collect {
+        separator: String,
+        values: Vec<String>,
+    },
+}
+
+/// Which statistic of the central moments an accumulator reports.
+#[derive(Debug, Clone, Copy)]
+enum Moment {
+    StdDev,
+    Var,
+    Skew,
+}
+
+/// The running mean and central moments of the numbers seen so far,
+/// updated one number at a time (Welford's algorithm, extended to the
+/// third moment by Terriberry). Summing squares instead would cancel
+/// catastrophically once the numbers are large next to their spread.
+#[derive(Debug, Default)]
+struct Moments {
+    count: u64,
+    mean: f64,
+    //sums of the squared and cubed deviations from the mean
+    m2: f64,
+    m3: f64,
+}
+
+impl Moments {
+    fn add(&mut self, value: f64) {
+        let before = self.count as f64;
+        self.count += 1;
+        let count = self.count as f64;
+        let delta = value - self.mean;
+        let delta_n = delta / count;
+        let term = delta * delta_n * before;
+        self.mean += delta_n;
+        self.m3 += term * delta_n * (count - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term;
+    }
+
+    /// The variance; a sample needs two numbers to have one.
+    fn variance(&self, estimator: Estimator) -> Option<f64> {
+        let count = self.count as f64;
+        match estimator {
+            Estimator::Sample if self.count >= 2 => Some(self.m2 / (count - 1.0)),
+            Estimator::Population if self.count >= 1 => Some(self.m2 / count),
+            _ => None,
+        }
+    }
+
+    /// The skewness: the population's g₁, or the sample's adjusted
+    /// Fisher–Pearson G₁, as spreadsheets' `SKEW` reports, which needs
+    /// three numbers. Numbers that are all the same have none.
+    fn skewness(&self, estimator: Estimator) -> Option<f64> {
+        if self.m2 == 0.0 {
+            return None;
+        }
+        let count = self.count as f64;
+        let population = count.sqrt() * self.m3 / self.m2.powf(1.5);
+        match estimator {
+            Estimator::Population => Some(population),
+            Estimator::Sample if self.count >= 3 => {
+                Some(population * (count * (count - 1.0)).sqrt() / (count - 2.0))
+            }
+            Estimator::Sample => None,
+        }
+    }
+}
+
+/// The values a quantile is found among: all of them, or their sketch.
+#[derive(Debug)]
+enum Values {
+    Exact(Vec<f64>),
+    Approximate(TDigest),
+}
+
+impl Values {
+    fn new(method: QuantileMethod) -> Values {
+        match method {
+            QuantileMethod::Exact => Values::Exact(Vec::new()),
+            QuantileMethod::Approximate => Values::Approximate(TDigest::new()),
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        match self {
+            Values::Exact(values) => values.push(value),
+            Values::Approximate(digest) => digest.add(value),
+        }
+    }
+
+    /// The value `rank` percent of the values lie below, interpolated
+    /// between the two closest ranks as spreadsheets and numpy do, so the
+    /// median of an even count is the mean of the middle two.
+    fn percentile(&self, rank: f64) -> Option<f64> {
+        let values = match self {
+            Values::Exact(values) => values,
+            Values::Approximate(digest) => return digest.quantile(rank / 100.0),
+        };
+        let mut sorted = values.clone();
+        sorted.sort_by(f64::total_cmp);
+        let last = sorted.len().checked_sub(1)?;
+        let position = rank / 100.0 * last as f64;
+        let below = position.floor() as usize;
+        let above = (below + 1).min(last);
+        let fraction = position - below as f64;
+        Some(sorted[below] + (sorted[above] - sorted[below]) * fraction)
+    }
+}
+
+/// The different values seen so far: all of them, or their sketch.
+#[derive(Debug)]
+enum Seen {
+    Exact(HashSet<String>),
+    Approximate(HyperLogLog),
+}
+
+impl Seen {
+    fn new(method: DistinctMethod) -> Seen {
+        match method {
+            DistinctMethod::Exact => Seen::Exact(HashSet::new()),
+            DistinctMethod::Approximate { precision } => {
+                Seen::Approximate(HyperLogLog::new(precision))
+            }
+        }
+    }
+
+    fn add(&mut self, value: String) {
+        match self {
+            Seen::Exact(seen) => {
+                seen.insert(value);
+            }
+            Seen::Approximate(sketch) => sketch.add(&value),
+        }
+    }
+
+    fn count(&self) -> u64 {
+        match self {
+            Seen::Exact(seen) => seen.len() as u64,
+            Seen::Approximate(sketch) => sketch.estimate().round() as u64,
+        }
+    }
 }
 
 impl Accumulator {
@@ -109,8 +578,29 @@ impl Accumulator {
             Aggregate::Count => Accumulator::Count(0),
             Aggregate::Sum(_) => Accumulator::Sum(0.0),
             Aggregate::Avg(_) => Accumulator::Avg { sum: 0.0, count: 0 },
-            Aggregate::Min(_) => Accumulator::Min(None),
-            Aggregate::Max(_) => Accumulator::Max(None),
+            Aggregate::Min(_, Compare::Numeric) => Accumulator::Min(None),
+            Aggregate::Max(_, Compare::Numeric) => Accumulator::Max(None),
+            Aggregate::Min(_, compare) => Accumulator::extreme(*compare, false),
+            Aggregate::Max(_, compare) => Accumulator::extreme(*compare, true),
+            Aggregate::StdDev(_, estimator) => Accumulator::moments(Moment::StdDev, *estimator),
+            Aggregate::Var(_, estimator) => Accumulator::moments(Moment::Var, *estimator),
+            Aggregate::Skew(_, estimator) => Accumulator::moments(Moment::Skew, *estimator),
+            Aggregate::Median(_, method) => Accumulator::Quantile {
+                rank: 50.0,
+                values: Values::new(*method),
+            },
+            Aggregate::Percentile(rank, _, method) => Accumulator::Quantile {
+                rank: *rank,
+                values: Values::new(*method),
+            },
+            Aggregate::Distinct(_, method) => Accumulator::Distinct(Seen::new(*method)),
+            Aggregate::First(_) => Accumulator::First(None),
+            Aggregate::Last(_) => Accumulator::Last(None),
+            Aggregate::Mode(_) => Accumulator::Mode(HashMap::new()),
+            Aggregate::Collect(_, separator) => Accumulator::Collect {
+                separator: separator.clone(),
+                values: Vec::new(),
+            },
         }
     }
 
@@ -119,6 +609,12 @@ impl Accumulator {
             *rows += 1;
             return;
         }
+        if self.reads_text() {
+            if let Some(value) = aggregate.text(line) {
+                self.add_text(value);
+            }
+            return;
+        }
         //every other aggregate reads a number, and a row without one
         //takes no part in it
         let Some(value) = aggregate.number(line) else {
@@ -126,6 +622,12 @@ impl Accumulator {
         };
         match self {
             Accumulator::Count(_) => unreachable!("counted above"),
+            Accumulator::Extreme { .. }
+            | Accumulator::Distinct(_)
+            | Accumulator::First(_)
+            | Accumulator::Last(_)
+            | Accumulator::Mode(_)
+            | Accumulator::Collect { .. } => unreachable!("read as text above"),
             Accumulator::Sum(sum) => *sum += value,
             Accumulator::Avg { sum, count } => {
                 *sum += value;
@@ -143,11 +645,87 @@ impl Accumulator {
                     None => value,
                 })
             }
+            Accumulator::Moments { moments, .. } => moments.add(value),
+            Accumulator::Quantile { values, .. } => values.add(value),
         }
     }
 
-    /// The value to print. A group with no numbers at all has no minimum
-    /// or maximum to show, and an average of nothing is nothing.
+    /// Whether the aggregate takes its values as text, so that any value
+    /// counts, not just a number.
+    fn reads_text(&self) -> bool {
+        matches!(
+            self,
+            Accumulator::Extreme { .. }
+                | Accumulator::Distinct(_)
+                | Accumulator::First(_)
+                | Accumulator::Last(_)
+                | Accumulator::Mode(_)
+                | Accumulator::Collect { .. }
+        )
+    }
+
+    fn add_text(&mut self, value: String) {
+        match self {
+            Accumulator::Extreme {
+                compare,
+                greatest,
+                best,
+            } => {
+                let order = SortOrder {
+                    compare: *compare,
+                    ..SortOrder::default()
+                };
+                let key = SortValue::new(&value, order);
+                if key.is_missing() {
+                    return;
+                }
+                //on a tie the value seen first stays, as with --mode
+                let better = match best {
+                    Some((current, _)) if *greatest => key > *current,
+                    Some((current, _)) => key < *current,
+                    None => true,
+                };
+                if better {
+                    *best = Some((key, value));
+                }
+            }
+            Accumulator::Distinct(seen) => seen.add(value),
+            Accumulator::First(first) => {
+                first.get_or_insert(value);
+            }
+            Accumulator::Last(last) => *last = Some(value),
+            Accumulator::Mode(counts) => {
+                let seen = counts.len();
+                counts
+                    .entry(value)
+                    .or_insert((0, seen))
+                    .0 += 1;
+            }
+            Accumulator::Collect { values, .. } => values.push(value),
+            _ => unreachable!("only the text aggregates take text"),
+        }
+    }
+
+    fn extreme(compare: Compare, greatest: bool) -> Accumulator {
+        Accumulator::Extreme {
+            compare,
+            greatest,
+            best: None,
+        }
+    }
+
+    fn moments(moment: Moment, estimator: Estimator) -> Accumulator {
+        Accumulator::Moments {
+            moment,
+            estimator,
+            moments: Moments::default(),
+        }
+    }
+
+    /// The value to print. A group with no numbers at all has no minimum,
+    /// maximum or percentile to show, and an average of nothing is
+    /// nothing; nor is a spread over too few numbers to have one. Likewise
+    /// a group without values has no first, last or most common one.
     fn value(&self) -> String {
         match self {
             Accumulator::Count(rows) => rows.to_string(),
@@ -157,6 +735,40 @@ impl Accumulator {
             Accumulator::Min(value) | Accumulator::Max(value) => value
                 .map(|value| value.to_string())
                 .unwrap_or_default(),
+            Accumulator::Extreme { best, .. } => best
+                .as_ref()
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default(),
+            Accumulator::Moments {
+                moment,
+                estimator,
+                moments,
+            } => {
+                let value = match moment {
+                    Moment::StdDev => moments
+                        .variance(*estimator)
+                        .map(f64::sqrt),
+                    Moment::Var => moments.variance(*estimator),
+                    Moment::Skew => moments.skewness(*estimator),
+                };
+                value
+                    .map(|value| value.to_string())
+                    .unwrap_or_default()
+            }
+            Accumulator::Quantile { rank, values } => values
+                .percentile(*rank)
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            Accumulator::Distinct(seen) => seen.count().to_string(),
+            Accumulator::First(value) | Accumulator::Last(value) => {
+                value.clone().unwrap_or_default()
+            }
+            Accumulator::Mode(counts) => counts
+                .iter()
+                .max_by_key(|(_, (count, seen))| (*count, std::cmp::Reverse(*seen)))
+                .map(|(value, _)| value.clone())
+                .unwrap_or_default(),
+            Accumulator::Collect { separator, values } => values.join(separator),
         }
     }
 }
@@ -351,17 +963,236 @@ mod tests {
         assert_eq!(result, "a,4,2\nb,10,10\n");
     }
 
+    #[test]
+    fn infinities_are_skipped_like_non_numbers() {
+        let reducer = Summarize::new(
+            None,
+            vec![
+                Aggregate::Sum(field(1)),
+                Aggregate::Avg(field(1)),
+                Aggregate::Max(field(1), Compare::Numeric),
+            ],
+            ",",
+        );
+        let result = summarized(reducer, &["1", "inf", "3", "-inf", "NaN"]);
+        assert_eq!(result, "4,2,3\n");
+    }
+
     #[test]
     fn reports_the_smallest_and_largest_value() {
         let reducer = Summarize::new(
             None,
-            vec![Aggregate::Min(field(2)), Aggregate::Max(field(2))],
+            vec![
+                Aggregate::Min(field(2), Compare::Numeric),
+                Aggregate::Max(field(2), Compare::Numeric),
+            ],
             ",",
         );
         let result = summarized(reducer, &["a,3", "a,-1.5", "a,7"]);
         assert_eq!(result, "-1.5,7\n");
     }
 
+    #[test]
+    fn extremes_by_size_or_version_are_written_as_read() {
+        let reducer = Summarize::new(
+            None,
+            vec![
+                Aggregate::Min(field(1), Compare::Human),
+                Aggregate::Max(field(1), Compare::Human),
+                Aggregate::Max(field(2), Compare::Natural),
+            ],
+            ",",
+        );
+        //a value that is no size takes no part, as in the numeric --min
+        let result = summarized(reducer, &["1.5M,1.9.3", "-,1.10.0", "900K,1.2"]);
+        assert_eq!(result, "900K,1.5M,1.10.0\n");
+    }
+
+    #[test]
+    fn medians_and_percentiles_interpolate_between_ranks() {
+        let reducer = Summarize::new(
+            Some(field(1)),
+            vec![
+                Aggregate::Median(field(2), QuantileMethod::Exact),
+                Aggregate::Percentile(90.0, field(2), QuantileMethod::Exact),
+            ],
+            ",",
+        );
+        let lines = ["a,4", "b,x", "a,1", "a,3", "a,2"];
+        //a: 1 2 3 4, so the median lies halfway between 2 and 3
+        assert_eq!(
+            summarized(reducer, &lines),
+            "a,2.5,3.7
+b,,
+"
+        );
+    }
+
+    #[test]
+    fn text_aggregates_take_any_value_per_group() {
+        let reducer = Summarize::new(
+            Some(field(1)),
+            vec![
+                Aggregate::Distinct(field(2), DistinctMethod::Exact),
+                Aggregate::First(field(2)),
+                Aggregate::Last(field(2)),
+                Aggregate::Mode(field(2)),
+                Aggregate::Collect(field(2), "|".to_owned()),
+            ],
+            ",",
+        );
+        let lines = [
+            "mon,bob", "mon,ann", "tue,eve", "mon,ann", "mon,", "tue,bob",
+        ];
+        assert_eq!(
+            summarized(reducer, &lines),
+            "mon,2,bob,ann,ann,bob|ann|ann\ntue,2,eve,bob,eve,eve|bob\n"
+        );
+    }
+
+    #[test]
+    fn text_aggregates_of_no_values_are_empty() {
+        let reducer = Summarize::new(
+            None,
+            vec![
+                Aggregate::Distinct(field(2), DistinctMethod::Exact),
+                Aggregate::First(field(2)),
+                Aggregate::Mode(field(2)),
+                Aggregate::Collect(field(2), ";".to_owned()),
+            ],
+            ",",
+        );
+        //the empty second field is a missing value, not a distinct one
+        assert_eq!(summarized(reducer, &["a,", "b"]), "0,,,\n");
+    }
+
+    #[test]
+    fn approximate_distinct_counts_come_from_a_sketch() {
+        let method = DistinctMethod::Approximate { precision: 14 };
+        let reducer = Summarize::new(
+            Some(field(1)),
+            vec![Aggregate::Distinct(field(2), method)],
+            ",",
+        );
+        let lines = ["a,x", "b,x", "a,y", "a,x", "a,z"];
+        assert_eq!(summarized(reducer, &lines), "a,3\nb,1\n");
+    }
+
+    #[test]
+    fn histogram_lists_the_most_frequent_first() {
+        let reducer = Histogram::new(field(1), None, true, ",");
+        let lines = ["b", "a", "c", "a", "", "b", "a"];
+        assert_eq!(
+            reduced(reducer, &lines),
+            format!(
+                "a,3,50.0,{}\nb,2,33.3,{}\nc,1,16.7,{}\n",
+                "#".repeat(40),
+                "#".repeat(27),
+                "#".repeat(13)
+            )
+        );
+    }
+
+    #[test]
+    fn histogram_bins_numbers_in_order() {
+        let lines = ["1", "9", "x", "3", "10", "2"];
+        let reducer = Histogram::new(field(1), Some(Binning::Count(3)), false, ",");
+        assert_eq!(
+            reduced(reducer, &lines),
+            "1,4,3,60.0\n4,7,0,0.0\n7,10,2,40.0\n"
+        );
+        let reducer = Histogram::new(field(1), Some(Binning::Width(5.0)), false, ",");
+        assert_eq!(
+            reduced(reducer, &lines),
+            "0,5,3,60.0\n5,10,1,20.0\n10,15,1,20.0\n"
+        );
+        //no numbers, no bins
+        let reducer = Histogram::new(field(1), Some(Binning::Count(3)), false, ",");
+        assert_eq!(reduced(reducer, &["x"]), "");
+    }
+
+    #[test]
+    fn histogram_bins_skip_infinities() {
+        let lines = ["1", "inf", "2", "-inf", "3"];
+        let reducer = Histogram::new(field(1), Some(Binning::Count(2)), false, ",");
+        assert_eq!(reduced(reducer, &lines), "1,2,1,33.3\n2,3,2,66.7\n");
+        let reducer = Histogram::new(field(1), Some(Binning::Width(2.0)), false, ",");
+        assert_eq!(reduced(reducer, &lines), "0,2,1,33.3\n2,4,2,66.7\n");
+    }
+
+    #[test]
+    fn widely_spread_bins_list_only_those_holding_numbers() {
+        let lines = ["0", "1e12", "1e300"];
+        let reducer = Histogram::new(field(1), Some(Binning::Width(1.0)), false, ",");
+        //1e300 lies past any bin a float can tell from the next
+        assert_eq!(
+            reduced(reducer, &lines),
+            "0,1,1,50.0\n1000000000000,1000000000001,1,50.0\n"
+        );
+    }
+
+    #[test]
+    fn spreads_are_sample_statistics_by_default() {
+        let spreads = |estimator| {
+            vec![
+                Aggregate::Var(field(1), estimator),
+                Aggregate::StdDev(field(1), estimator),
+                Aggregate::Skew(field(1), estimator),
+            ]
+        };
+        let lines = ["2", "4", "4", "4", "5", "5", "7", "9"];
+        let close_to = |estimator, expected: [f64; 3]| {
+            let reducer = Summarize::new(None, spreads(estimator), ",");
+            let result = summarized(reducer, &lines);
+            let values = result
+                .trim()
+                .split(',')
+                .map(|value| value.parse::<f64>().unwrap());
+            for (value, expected) in values.zip(expected) {
+                assert!((value - expected).abs() < 1e-12, "{result}");
+            }
+        };
+        close_to(Estimator::Population, [4.0, 2.0, 0.65625]);
+        let variance = 32.0f64 / 7.0;
+        close_to(
+            Estimator::Sample,
+            [variance, variance.sqrt(), 0.8184875533567997],
+        );
+
+        //one number is no sample, and equal numbers have no skew
+        let reducer = Summarize::new(None, spreads(Estimator::Sample), ",");
+        assert_eq!(summarized(reducer, &["3"]), ",,\n");
+    }
+
+    #[test]
+    fn spreads_keep_their_precision_far_from_zero() {
+        let reducer = Summarize::new(None, vec![Aggregate::Var(field(1), Estimator::Sample)], ",");
+        //a naive sum of squares loses every digit of these to rounding
+        let lines = ["1000000004", "1000000007", "1000000013", "1000000016"];
+        assert_eq!(summarized(reducer, &lines), "30\n");
+    }
+
+    #[test]
+    fn approximate_percentiles_come_from_a_sketch() {
+        let reducer = Summarize::new(
+            None,
+            vec![Aggregate::Percentile(
+                50.0,
+                field(1),
+                QuantileMethod::Approximate,
+            )],
+            ",",
+        );
+        let lines: Vec<String> = (1..=5)
+            .map(|value| value.to_string())
+            .collect();
+        let lines: Vec<&str> = lines
+            .iter()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(summarized(reducer, &lines), "3\n");
+    }
+
     #[test]
     fn rows_without_a_number_take_no_part_in_the_statistics() {
         let reducer = Summarize::new(
@@ -383,7 +1214,10 @@ mod tests {
     fn a_group_without_any_number_has_nothing_to_report() {
         let reducer = Summarize::new(
             None,
-            vec![Aggregate::Min(field(2)), Aggregate::Avg(field(2))],
+            vec![
+                Aggregate::Min(field(2), Compare::Numeric),
+                Aggregate::Avg(field(2)),
+            ],
             ",",
         );
         assert_eq!(summarized(reducer, &["a,x"]), ",\n");
@@ -0,0 +1,245 @@
+//! Fixed-width schemas (`--schema`): a file naming the char ranges of a
+//! record layout, so column lists can say `amount` rather than `31-40`,
+//! and so the records can be turned into delimited fields (`--delimit`).
+//!
+//! One field per line — its name, its char range and optionally its
+//! type — separated by whitespace; blank lines and `#` comments are
+//! skipped:
+//!
+//! ```text
+//! # customer export
+//! id      1-8     number
+//! name    9-30
+//! amount  31-40   number
+//! ```
+
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::columns::{ColumnList, ColumnPart};
+
+/// What a field holds, which decides how its padding comes off when the
+/// record is delimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// Text, padded with blanks: the value is what lies between them.
+    Text,
+    /// A number, padded with blanks or leading zeros, as mainframe
+    /// exports write them: `-000012.50` is `-12.50`.
+    Number,
+}
+
+impl FieldKind {
+    /// The value of a field of this kind, its padding removed.
+    pub fn value(self, field: &str) -> String {
+        let field = field.trim();
+        match self {
+            FieldKind::Text => field.to_owned(),
+            FieldKind::Number => {
+                let digits = field.trim_start_matches(['-', '+']);
+                let sign = &field[..field.len() - digits.len()];
+                let unpadded = digits.trim_start_matches('0');
+                //a zero that is all there is before the point stays
+                let unpadded = match unpadded.len() < digits.len()
+                    && (unpadded.is_empty() || unpadded.starts_with('.'))
+                {
+                    true => &digits[digits.len() - unpadded.len() - 1..],
+                    false => unpadded,
+                };
+                format!("{sign}{unpadded}")
+            }
+        }
+    }
+}
+
+/// One named field of a record layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaField {
+    pub name: String,
+    pub columns: RangeInclusive<usize>,
+    pub kind: FieldKind,
+}
+
+/// A record layout: its fields, in the order the schema lists them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    fields: Vec<SchemaField>,
+}
+
+impl Schema {
+    /// Read and parse a schema file.
+    pub fn load(path: &Path) -> Result<Schema, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read schema `{}`: {e}", path.display()))?;
+        Schema::parse(&text)
+    }
+
+    /// Parse a schema, reporting the first line that is not a field.
+    pub fn parse(text: &str) -> Result<Schema, String> {
+        let mut fields: Vec<SchemaField> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line
+                .split_once('#')
+                .map_or(line, |(field, _)| field);
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let field = match words.as_slice() {
+                [] => continue,
+                [name, columns] => parse_field(name, columns, "text"),
+                [name, columns, kind] => parse_field(name, columns, kind),
+                _ => Err("expected <name> <from>-<to> [text|number]".to_owned()),
+            }
+            .map_err(|e| format!("Schema line {}: {e}", index + 1))?;
+            if fields
+                .iter()
+                .any(|known| known.name == field.name)
+            {
+                return Err(format!(
+                    "Schema line {}: field `{}` is defined twice",
+                    index + 1,
+                    field.name
+                ));
+            }
+            fields.push(field);
+        }
+        if fields.is_empty() {
+            return Err("The schema defines no fields".to_owned());
+        }
+        Ok(Schema { fields })
+    }
+
+    /// The fields, in the order listed.
+    pub fn fields(&self) -> &[SchemaField] {
+        &self.fields
+    }
+
+    /// The char range of the field of that name.
+    pub fn lookup(&self, name: &str) -> Option<RangeInclusive<usize>> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| field.columns.clone())
+    }
+
+    /// The kind of the field occupying exactly these chars; a range the
+    /// schema does not name holds text.
+    pub fn kind_of(&self, columns: &RangeInclusive<usize>) -> FieldKind {
+        self.fields
+            .iter()
+            .find(|field| field.columns == *columns)
+            .map_or(FieldKind::Text, |field| field.kind)
+    }
+
+    /// The name of the field occupying exactly these chars, if any.
+    pub fn name_of(&self, columns: &RangeInclusive<usize>) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|field| field.columns == *columns)
+            .map(|field| field.name.as_str())
+    }
+
+    /// Every field, as a column list in the order listed.
+    pub fn columns(&self) -> ColumnList {
+        ColumnList::with_names(
+            self.fields
+                .iter()
+                .map(|field| ColumnPart::Range(field.columns.clone()))
+                .collect(),
+        )
+    }
+}
+
+fn parse_field(name: &str, columns: &str, kind: &str) -> Result<SchemaField, String> {
+    let columns = match columns.split_once('-') {
+        Some((from, to)) => parse_position(from)?..=parse_position(to)?,
+        None => {
+            let position = parse_position(columns)?;
+            position..=position
+        }
+    };
+    if columns.start() > columns.end() {
+        return Err(format!("field `{name}` starts after it ends"));
+    }
+    let kind = match kind {
+        "text" => FieldKind::Text,
+        "number" => FieldKind::Number,
+        _ => return Err(format!("unknown type `{kind}`, expected text or number")),
+    };
+    Ok(SchemaField {
+        name: name.to_owned(),
+        columns,
+        kind,
+    })
+}
+
+fn parse_position(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) => Err("columns are 1-based, positions must be at least 1".to_owned()),
+        Ok(position) => Ok(position),
+        Err(_) => Err(format!("position `{value}` isn't a number")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &str = "# customer export\n\
+                          id      1-8     number\n\
+                          \n\
+                          name    9-30          # padded with blanks\n\
+                          flag    31\n";
+
+    #[test]
+    fn parses_names_ranges_and_kinds() {
+        let schema = Schema::parse(LAYOUT).unwrap();
+        assert_eq!(
+            schema.fields(),
+            [
+                SchemaField {
+                    name: "id".to_owned(),
+                    columns: 1..=8,
+                    kind: FieldKind::Number,
+                },
+                SchemaField {
+                    name: "name".to_owned(),
+                    columns: 9..=30,
+                    kind: FieldKind::Text,
+                },
+                SchemaField {
+                    name: "flag".to_owned(),
+                    columns: 31..=31,
+                    kind: FieldKind::Text,
+                },
+            ]
+        );
+        assert_eq!(schema.lookup("name"), Some(9..=30));
+        assert_eq!(schema.lookup("nope"), None);
+        assert_eq!(schema.kind_of(&(1..=8)), FieldKind::Number);
+        assert_eq!(schema.kind_of(&(1..=9)), FieldKind::Text);
+        assert_eq!(schema.columns().written(), [1..=8, 9..=30, 31..=31]);
+    }
+
+    #[test]
+    fn reports_the_line_at_fault() {
+        let error = Schema::parse("id 1-8\nname 9-x\n").unwrap_err();
+        assert!(error.starts_with("Schema line 2:"), "{error}");
+
+        assert!(Schema::parse("id 8-1").is_err());
+        assert!(Schema::parse("id 0-1").is_err());
+        assert!(Schema::parse("id 1-2 date").is_err());
+        assert!(Schema::parse("id 1-2\nid 3-4").is_err());
+        assert!(Schema::parse("# nothing\n").is_err());
+    }
+
+    #[test]
+    fn numbers_lose_their_padding() {
+        assert_eq!(FieldKind::Number.value("00012"), "12");
+        assert_eq!(FieldKind::Number.value("-000012.50"), "-12.50");
+        assert_eq!(FieldKind::Number.value("   7 "), "7");
+        assert_eq!(FieldKind::Number.value("0000"), "0");
+        assert_eq!(FieldKind::Number.value("000.5"), "0.5");
+        assert_eq!(FieldKind::Number.value(".5"), ".5");
+        assert_eq!(FieldKind::Text.value("  ACME Ltd   "), "ACME Ltd");
+    }
+}
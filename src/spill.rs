@@ -0,0 +1,255 @@
+//! Sorted runs of lines kept on disk, for a `--sort` of more lines than
+//! its `--buffer-size` holds: each bufferful is sorted and written out as
+//! a run, and the runs are merged back into one sorted stream at the end.
+//!
+//! The merge takes the least line of all the runs' heads, and of equal
+//! lines the one from the earliest run. Runs are cut from the input in
+//! order and each is sorted stably, so that is the line read first —
+//! the merged order is exactly the stable in-memory sort's.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+/// How many runs are merged at once: more would each hold a file open,
+/// so past this many the runs so far are merged into one first.
+pub const MERGE_WIDTH: usize = 64;
+
+/// The line terminators a run records, by their index.
+const TERMINATORS: [&str; 3] = ["", "\n", "\r\n"];
+
+/// A run of lines, sorted, in a file of its own that goes away with it.
+pub struct Run {
+    //held for its drop, which removes the file
+    _path: TempPath,
+    reader: BufReader<File>,
+}
+
+/// A file removed once it is no longer needed, however that comes about.
+struct TempPath(PathBuf);
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// A run being written: each line as the length of its content, its
+/// terminator's index and its content, since content may hold newlines
+/// of its own (`--quoted`).
+pub struct RunWriter {
+    path: TempPath,
+    writer: BufWriter<File>,
+}
+
+impl RunWriter {
+    /// Start a run in a new file in `dir`.
+    pub fn create(dir: &Path) -> io::Result<RunWriter> {
+        //one process may spill many runs, and several processes may
+        //share a directory: the pid and a count keep the names apart,
+        //and create_new refuses any file already there
+        static RUNS: AtomicU64 = AtomicU64::new(0);
+        let number = RUNS.fetch_add(1, AtomicOrdering::Relaxed);
+        let path = dir.join(format!(".ft-sort-{}-{number}.run", std::process::id()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|error| {
+                io::Error::new(
+                    error.kind(),
+                    format!("cannot spill the sort to {}: {error}", dir.display()),
+                )
+            })?;
+        Ok(RunWriter {
+            path: TempPath(path),
+            writer: BufWriter::new(file),
+        })
+    }
+
+    pub fn push(&mut self, content: &str, terminator: &str) -> io::Result<()> {
+        let terminator = TERMINATORS
+            .iter()
+            .position(|known| *known == terminator)
+            .expect("a line ends in a newline, a CRLF or nothing");
+        self.writer
+            .write_all(&(content.len() as u64).to_le_bytes())?;
+        self.writer
+            .write_all(&[terminator as u8])?;
+        self.writer
+            .write_all(content.as_bytes())
+    }
+
+    /// The run as written, to be read from its start. On a failure the
+    /// file goes, like that of a run given up half written.
+    pub fn finish(self) -> io::Result<Run> {
+        let mut file = self
+            .writer
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(Run {
+            _path: self.path,
+            reader: BufReader::new(file),
+        })
+    }
+}
+
+impl Run {
+    /// Write `lines`, in the order given, as a run in `dir`.
+    pub fn write<'a>(
+        dir: &Path,
+        lines: impl IntoIterator<Item = (&'a str, &'static str)>,
+    ) -> io::Result<Run> {
+        let mut writer = RunWriter::create(dir)?;
+        for (content, terminator) in lines {
+            writer.push(content, terminator)?;
+        }
+        writer.finish()
+    }
+
+    /// The next line of the run, or `None` once it is read to the end.
+    pub fn read(&mut self) -> io::Result<Option<(String, &'static str)>> {
+        let mut length = [0; 8];
+        match self.reader.read_exact(&mut length) {
+            Ok(()) => {}
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(error) => return Err(error),
+        }
+        let mut terminator = [0];
+        self.reader
+            .read_exact(&mut terminator)?;
+        let mut content = vec![0; u64::from_le_bytes(length) as usize];
+        self.reader.read_exact(&mut content)?;
+        let content = String::from_utf8(content)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        Ok(Some((content, TERMINATORS[terminator[0] as usize])))
+    }
+}
+
+/// Merge `runs`, each sorted by `key`, into one sorted stream handed to
+/// `take` line by line. Of lines with equal keys, the earlier run's
+/// come first.
+pub fn merge<K: Ord>(
+    mut runs: Vec<Run>,
+    key: impl Fn(&str) -> K,
+    mut take: impl FnMut(String, &'static str) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut heads = BinaryHeap::new();
+    for (index, run) in runs.iter_mut().enumerate() {
+        if let Some((content, terminator)) = run.read()? {
+            heads.push(Head {
+                key: key(&content),
+                run: index,
+                content,
+                terminator,
+            });
+        }
+    }
+    while let Some(head) = heads.pop() {
+        if let Some((content, terminator)) = runs[head.run].read()? {
+            heads.push(Head {
+                key: key(&content),
+                run: head.run,
+                content,
+                terminator,
+            });
+        }
+        take(head.content, head.terminator)?;
+    }
+    Ok(())
+}
+
+/// Merge `runs` into a single run in `dir`, when there are too many to
+/// keep open at once.
+pub fn merge_into<K: Ord>(dir: &Path, runs: Vec<Run>, key: impl Fn(&str) -> K) -> io::Result<Run> {
+    let mut writer = RunWriter::create(dir)?;
+    merge(runs, key, |content, terminator| {
+        writer.push(&content, terminator)
+    })?;
+    writer.finish()
+}
+
+/// The line a run is at, ready to be compared with the other runs'.
+struct Head<K> {
+    key: K,
+    run: usize,
+    content: String,
+    terminator: &'static str,
+}
+
+impl<K: Ord> Ord for Head<K> {
+    //reversed: the heap gives up its greatest, and the merge wants the
+    //least key, and of equal keys the earliest run
+    fn cmp(&self, other: &Head<K>) -> Ordering {
+        other
+            .key
+            .cmp(&self.key)
+            .then(other.run.cmp(&self.run))
+    }
+}
+
+impl<K: Ord> PartialOrd for Head<K> {
+    fn partial_cmp(&self, other: &Head<K>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> PartialEq for Head<K> {
+    fn eq(&self, other: &Head<K>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for Head<K> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(mut run: Run) -> Vec<(String, &'static str)> {
+        let mut lines = Vec::new();
+        while let Some(line) = run.read().unwrap() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    #[test]
+    fn a_run_reads_back_what_was_written() {
+        let dir = std::env::temp_dir();
+        let run = Run::write(&dir, [("a\nb", "\n"), ("", "\r\n"), ("c", "")]).unwrap();
+        let path = run._path.0.clone();
+        assert_eq!(
+            read_all(run),
+            [
+                ("a\nb".to_owned(), "\n"),
+                (String::new(), "\r\n"),
+                ("c".to_owned(), "")
+            ]
+        );
+        //the file went with the run
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn merging_keeps_equal_keys_in_run_order() {
+        let dir = std::env::temp_dir();
+        let runs = vec![
+            Run::write(&dir, [("1a", "\n"), ("3a", "\n")]).unwrap(),
+            Run::write(&dir, [("1b", "\n"), ("2b", "\n")]).unwrap(),
+            Run::write(&dir, [("1c", "\n")]).unwrap(),
+        ];
+        let first_char = |content: &str| content.chars().next();
+        let merged = merge_into(&dir, runs, first_char).unwrap();
+        let lines: Vec<String> = read_all(merged)
+            .into_iter()
+            .map(|(content, _)| content)
+            .collect();
+        assert_eq!(lines, ["1a", "1b", "1c", "2b", "3a"]);
+    }
+}
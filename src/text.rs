@@ -118,19 +118,6 @@ where
     rewrite_ranges(line, cols, map_within)
 }
 
-/// Replace every `find` occurrence with `replace`, but only within the
-/// column ranges; the rest of the line is left untouched. Each range is
-/// searched on its own, so a match straddling two of them is not
-/// replaced.
-pub fn replace_in_ranges(
-    line: &str,
-    find: &str,
-    replace: &str,
-    cols: &[RangeInclusive<usize>],
-) -> String {
-    map_ranges(line, cols, |within| within.replace(find, replace))
-}
-
 /// Rebuild the line, replacing the content of each column range with
 /// the result of `map_within` (the empty string removes it). Walks the
 /// line once, left to right, so the ranges must ascend; one starting
@@ -216,6 +203,17 @@ mod tests {
         assert_eq!(remove(20..=30), line_utf8);
     }
 
+    /// Replace every `find` with `replace` within the column ranges, as
+    /// `ReplaceInColumns` does for a char span.
+    fn replace_in_ranges(
+        line: &str,
+        find: &str,
+        replace: &str,
+        cols: &[RangeInclusive<usize>],
+    ) -> String {
+        map_ranges(line, cols, |within| within.replace(find, replace))
+    }
+
     #[test]
     fn test_replace_in_columns() {
         //pos:          123456789012345678901234
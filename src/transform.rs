@@ -9,10 +9,13 @@
 //! transform here instead of branching inside the processing loop.
 
 use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 
 use regex::{NoExpand, Regex, RegexBuilder};
 
-use crate::columns::ColumnSpan;
+use crate::columns::{ColumnSpan, Quoting};
+use crate::reduce::read_number;
+use crate::schema::Schema;
 use crate::text;
 
 /// What a transform made of a line.
@@ -236,11 +239,7 @@ fn squeeze_whitespace(within: &str) -> String {
 
 impl LineTransform for MapColumns {
     fn apply(&mut self, line: &str) -> LineOutcome {
-        LineOutcome::Replace(text::map_ranges(
-            line,
-            &self.span.write_ranges(line),
-            self.map,
-        ))
+        LineOutcome::Replace(self.span.map(line, self.map))
     }
 }
 
@@ -263,12 +262,10 @@ impl ReplaceInColumns {
 
 impl LineTransform for ReplaceInColumns {
     fn apply(&mut self, line: &str) -> LineOutcome {
-        LineOutcome::Replace(text::replace_in_ranges(
-            line,
-            &self.find,
-            &self.replace,
-            &self.span.write_ranges(line),
-        ))
+        LineOutcome::Replace(
+            self.span
+                .map(line, |within| within.replace(&self.find, &self.replace)),
+        )
     }
 }
 
@@ -301,15 +298,11 @@ impl ReplaceInColumnsIgnoreCase {
 
 impl LineTransform for ReplaceInColumnsIgnoreCase {
     fn apply(&mut self, line: &str) -> LineOutcome {
-        LineOutcome::Replace(text::map_ranges(
-            line,
-            &self.span.write_ranges(line),
-            |within| {
-                self.pattern
-                    .replace_all(within, NoExpand(&self.replace))
-                    .into_owned()
-            },
-        ))
+        LineOutcome::Replace(self.span.map(line, |within| {
+            self.pattern
+                .replace_all(within, NoExpand(&self.replace))
+                .into_owned()
+        }))
     }
 }
 
@@ -337,15 +330,70 @@ impl RegexReplaceInColumns {
 
 impl LineTransform for RegexReplaceInColumns {
     fn apply(&mut self, line: &str) -> LineOutcome {
-        LineOutcome::Replace(text::map_ranges(
-            line,
-            &self.span.write_ranges(line),
-            |within| {
-                self.pattern
-                    .replace_all(within, self.replacement.as_str())
+        LineOutcome::Replace(self.span.map(line, |within| {
+            self.pattern
+                .replace_all(within, self.replacement.as_str())
+                .into_owned()
+        }))
+    }
+}
+
+/// Turns a fixed-width record into delimited fields: each part of the
+/// span, in the order written, becomes one field, its padding taken off
+/// the way the schema's type for those chars says. A value holding the
+/// delimiter, a quote or a line break is quoted, so the fields read
+/// back as CSV.
+pub struct DelimitFields {
+    span: ColumnSpan,
+    schema: Schema,
+    delimiter: String,
+}
+
+impl DelimitFields {
+    pub fn new(span: impl Into<ColumnSpan>, schema: Schema, delimiter: String) -> DelimitFields {
+        DelimitFields {
+            span: span.into(),
+            schema,
+            delimiter,
+        }
+    }
+}
+
+impl LineTransform for DelimitFields {
+    fn apply(&mut self, line: &str) -> LineOutcome {
+        let fields: Vec<String> = self
+            .span
+            .read_ranges(line)
+            .iter()
+            .map(|columns| {
+                let value = self
+                    .schema
+                    .kind_of(columns)
+                    .value(text::select_columns(line, columns));
+                Quoting::Minimal
+                    .quote(&value, &self.delimiter)
                     .into_owned()
-            },
-        ))
+            })
+            .collect();
+        LineOutcome::Replace(fields.join(&self.delimiter))
+    }
+}
+
+/// Writes the row as a JSON object (`--jsonl` written as JSON): the
+/// columns of the span become its members, keyed by their key paths.
+pub struct WriteObjects {
+    span: ColumnSpan,
+}
+
+impl WriteObjects {
+    pub fn new(span: impl Into<ColumnSpan>) -> WriteObjects {
+        WriteObjects { span: span.into() }
+    }
+}
+
+impl LineTransform for WriteObjects {
+    fn apply(&mut self, line: &str) -> LineOutcome {
+        LineOutcome::Replace(self.span.object(line))
     }
 }
 
@@ -403,6 +451,288 @@ impl LineTransform for NumberLines {
     }
 }
 
+/// The state of a running column, one per `--group-by` key so that each
+/// group restarts from scratch; without a key every row shares one.
+struct PerGroup<T> {
+    key_span: Option<ColumnSpan>,
+    states: HashMap<String, T>,
+}
+
+impl<T: Default> PerGroup<T> {
+    fn new(key_span: Option<ColumnSpan>) -> PerGroup<T> {
+        PerGroup {
+            key_span,
+            states: HashMap::new(),
+        }
+    }
+
+    /// The state of the group a line belongs to.
+    fn of(&mut self, line: &str) -> &mut T {
+        let key = match &self.key_span {
+            Some(span) => span.select(line).into_owned(),
+            None => String::new(),
+        };
+        self.states.entry(key).or_default()
+    }
+}
+
+/// Appends the running total of a column to each row (`--cumsum`). Like
+/// [`NumberLines`], it totals the rows as processed; a value that is not
+/// a number leaves the total as it was, as it takes no part in a summary.
+pub struct CumulativeSum {
+    span: ColumnSpan,
+    separator: String,
+    totals: PerGroup<f64>,
+}
+
+impl CumulativeSum {
+    pub fn new(
+        span: ColumnSpan,
+        key_span: Option<ColumnSpan>,
+        separator: impl Into<String>,
+    ) -> CumulativeSum {
+        CumulativeSum {
+            span,
+            separator: separator.into(),
+            totals: PerGroup::new(key_span),
+        }
+    }
+}
+
+impl LineTransform for CumulativeSum {
+    fn apply(&mut self, line: &str) -> LineOutcome {
+        let total = self.totals.of(line);
+        if let Some(value) = read_number(&self.span, line) {
+            *total += value;
+        }
+        LineOutcome::Replace(format!("{line}{}{total}", self.separator))
+    }
+}
+
+/// Appends the difference between a column's number and the previous
+/// row's (`--delta`). The first row has nothing to differ from, and a
+/// row without a number neither has a difference nor becomes the one
+/// the next row is compared with: both get an empty field.
+pub struct Delta {
+    span: ColumnSpan,
+    separator: String,
+    previous: PerGroup<Option<f64>>,
+}
+
+impl Delta {
+    pub fn new(
+        span: ColumnSpan,
+        key_span: Option<ColumnSpan>,
+        separator: impl Into<String>,
+    ) -> Delta {
+        Delta {
+            span,
+            separator: separator.into(),
+            previous: PerGroup::new(key_span),
+        }
+    }
+}
+
+impl LineTransform for Delta {
+    fn apply(&mut self, line: &str) -> LineOutcome {
+        let previous = self.previous.of(line);
+        let current = read_number(&self.span, line);
+        let delta = match (*previous, current) {
+            (Some(previous), Some(current)) => (current - previous).to_string(),
+            _ => String::new(),
+        };
+        if current.is_some() {
+            *previous = current;
+        }
+        LineOutcome::Replace(format!("{line}{}{delta}", self.separator))
+    }
+}
+
+/// Appends each row's rank by a column's value (`--rank`), as SQL's
+/// `RANK()` numbers rows already in order: a row equal to the one before
+/// shares its rank, and the next different one takes its row count, so
+/// `a a b` ranks `1 1 3`. A transform sees the rows one at a time, so
+/// they must arrive sorted by the column for the ranks to mean anything
+/// — with `--sort`, it ranks them as they come out of the sort.
+pub struct Rank {
+    span: ColumnSpan,
+    separator: String,
+    ranks: PerGroup<Ranking>,
+}
+
+#[derive(Default)]
+struct Ranking {
+    rows: u64,
+    rank: u64,
+    previous: Option<String>,
+}
+
+impl Rank {
+    pub fn new(
+        span: ColumnSpan,
+        key_span: Option<ColumnSpan>,
+        separator: impl Into<String>,
+    ) -> Rank {
+        Rank {
+            span,
+            separator: separator.into(),
+            ranks: PerGroup::new(key_span),
+        }
+    }
+}
+
+impl LineTransform for Rank {
+    fn apply(&mut self, line: &str) -> LineOutcome {
+        let ranking = self.ranks.of(line);
+        let value = self.span.select(line);
+        ranking.rows += 1;
+        if ranking.previous.as_deref() != Some(&*value) {
+            ranking.rank = ranking.rows;
+            ranking.previous = Some(value.into_owned());
+        }
+        LineOutcome::Replace(format!("{line}{}{}", self.separator, ranking.rank))
+    }
+}
+
+/// The statistic a moving window reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Moving {
+    Avg,
+    Min,
+    Max,
+}
+
+/// Appends a statistic of a column's last `size` numbers to each row
+/// (`--moving-avg`, `--moving-min`, `--moving-max` over `--window`). The
+/// window slides over the numbers rather than the rows: a value that is
+/// not a number is skipped, as in a summary, and the row gets the
+/// statistic of the window as it stands. Until `size` numbers have come
+/// the window is simply shorter, so the first rows still get a value.
+pub struct MovingWindow {
+    span: ColumnSpan,
+    statistic: Moving,
+    size: usize,
+    separator: String,
+    windows: PerGroup<Window>,
+}
+
+impl MovingWindow {
+    pub fn new(
+        span: ColumnSpan,
+        statistic: Moving,
+        size: usize,
+        key_span: Option<ColumnSpan>,
+        separator: impl Into<String>,
+    ) -> MovingWindow {
+        MovingWindow {
+            span,
+            statistic,
+            size,
+            separator: separator.into(),
+            windows: PerGroup::new(key_span),
+        }
+    }
+}
+
+impl LineTransform for MovingWindow {
+    fn apply(&mut self, line: &str) -> LineOutcome {
+        let window = self.windows.of(line);
+        if let Some(value) = read_number(&self.span, line) {
+            window.push(value, self.size);
+        }
+        let value = match (self.statistic, window.values.is_empty()) {
+            (_, true) => String::new(),
+            (Moving::Avg, false) => {
+                let sum = window.sum + window.compensation;
+                (sum / window.values.len() as f64).to_string()
+            }
+            (Moving::Min, false) => window.least[0].1.to_string(),
+            (Moving::Max, false) => window.greatest[0].1.to_string(),
+        };
+        LineOutcome::Replace(format!("{line}{}{value}", self.separator))
+    }
+}
+
+/// The numbers of one moving window, kept so that each row's statistic
+/// comes at a constant cost however wide the window: their sum as it
+/// runs, and for the least and greatest the numbers that can still be
+/// one — each later number that is smaller (or greater) rules out the
+/// ones before it, so the front of the queue is the answer.
+#[derive(Default)]
+struct Window {
+    values: VecDeque<f64>,
+    //how many numbers have come, which dates each one in the queues
+    count: usize,
+    sum: f64,
+    //the low bits the sum lost, Neumaier's compensation: the numbers
+    //are added and later taken away, and the rounding of each would
+    //otherwise build up over a long input
+    compensation: f64,
+    least: VecDeque<(usize, f64)>,
+    greatest: VecDeque<(usize, f64)>,
+}
+
+impl Window {
+    /// Slide the window over one more number, keeping at most `size`.
+    fn push(&mut self, value: f64, size: usize) {
+        if self.values.len() == size {
+            if let Some(oldest) = self.values.pop_front() {
+                self.add(-oldest);
+            }
+            let first = self.count - size;
+            for queue in [&mut self.least, &mut self.greatest] {
+                if queue
+                    .front()
+                    .is_some_and(|(index, _)| *index == first)
+                {
+                    queue.pop_front();
+                }
+            }
+        }
+        self.values.push_back(value);
+        self.add(value);
+        //a sum past the largest float cannot have numbers taken away
+        //again, so while it overflows it is summed afresh
+        if !self.sum.is_finite() {
+            self.sum = 0.0;
+            self.compensation = 0.0;
+            let values = std::mem::take(&mut self.values);
+            values
+                .iter()
+                .for_each(|value| self.add(*value));
+            self.values = values;
+        }
+        while self
+            .least
+            .back()
+            .is_some_and(|(_, last)| *last >= value)
+        {
+            self.least.pop_back();
+        }
+        self.least
+            .push_back((self.count, value));
+        while self
+            .greatest
+            .back()
+            .is_some_and(|(_, last)| *last <= value)
+        {
+            self.greatest.pop_back();
+        }
+        self.greatest
+            .push_back((self.count, value));
+        self.count += 1;
+    }
+
+    fn add(&mut self, value: f64) {
+        let sum = self.sum + value;
+        self.compensation += match self.sum.abs() >= value.abs() {
+            true => (self.sum - sum) + value,
+            false => (value - sum) + self.sum,
+        };
+        self.sum = sum;
+    }
+}
+
 /// Splits the line at every occurrence of a separator, one row in,
 /// several rows out (`tr , '\n'`, but only on the rows being processed).
 pub struct SplitLines {
@@ -714,6 +1044,95 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cumsum_totals_per_group_and_skips_non_numbers() {
+        let mut transform =
+            CumulativeSum::new(field_span(",", 2..=2), Some(field_span(",", 1..=1)), ",");
+        assert_eq!(applied(&mut transform, "a,1"), "a,1,1");
+        assert_eq!(applied(&mut transform, "b,5"), "b,5,5");
+        assert_eq!(applied(&mut transform, "a,2.5"), "a,2.5,3.5");
+        assert_eq!(applied(&mut transform, "a,n/a"), "a,n/a,3.5");
+        assert_eq!(applied(&mut transform, "a,inf"), "a,inf,3.5");
+        assert_eq!(applied(&mut transform, "b,-1"), "b,-1,4");
+    }
+
+    #[test]
+    fn delta_differs_from_the_previous_number() {
+        let mut transform = Delta::new(field_span(",", 1..=1), None, ",");
+        assert_eq!(applied(&mut transform, "10"), "10,");
+        assert_eq!(applied(&mut transform, "13"), "13,3");
+        assert_eq!(applied(&mut transform, "x"), "x,");
+        assert_eq!(applied(&mut transform, "11"), "11,-2");
+    }
+
+    #[test]
+    fn rank_shares_a_rank_between_equal_rows() {
+        let mut transform = Rank::new(field_span(",", 2..=2), Some(field_span(",", 1..=1)), ",");
+        let ranked: Vec<String> = ["a,9", "a,9", "b,9", "a,7", "a,5", "a,5", "b,8"]
+            .iter()
+            .map(|line| applied(&mut transform, line))
+            .collect();
+        assert_eq!(
+            ranked,
+            [
+                "a,9,1", "a,9,1", "b,9,1", "a,7,3", "a,5,4", "a,5,4", "b,8,2"
+            ]
+        );
+    }
+
+    #[test]
+    fn moving_window_covers_the_last_numbers() {
+        let lines = ["1", "3", "x", "5", "10", "-4"];
+        let moved = |statistic| {
+            let mut transform = MovingWindow::new(field_span(",", 1..=1), statistic, 3, None, ",");
+            lines
+                .iter()
+                .map(|line| applied(&mut transform, line))
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            moved(Moving::Avg),
+            ["1,1", "3,2", "x,2", "5,3", "10,6", "-4,3.6666666666666665"]
+        );
+        assert_eq!(
+            moved(Moving::Min),
+            ["1,1", "3,1", "x,1", "5,1", "10,3", "-4,-4"]
+        );
+        assert_eq!(
+            moved(Moving::Max),
+            ["1,1", "3,3", "x,3", "5,5", "10,10", "-4,10"]
+        );
+    }
+
+    #[test]
+    fn moving_window_slides_per_group() {
+        let span = field_span(",", 2..=2);
+        let mut transform =
+            MovingWindow::new(span, Moving::Max, 2, Some(field_span(",", 1..=1)), ",");
+        assert_eq!(applied(&mut transform, "a,"), "a,,");
+        assert_eq!(applied(&mut transform, "a,9"), "a,9,9");
+        assert_eq!(applied(&mut transform, "b,1"), "b,1,1");
+        assert_eq!(applied(&mut transform, "a,2"), "a,2,9");
+        assert_eq!(applied(&mut transform, "a,3"), "a,3,3");
+    }
+
+    #[test]
+    fn moving_window_keeps_its_sum_exact() {
+        //the large number leaves the window, and with it every trace
+        let mut transform = MovingWindow::new(field_span(",", 1..=1), Moving::Avg, 2, None, ",");
+        for line in ["1e17", "0.1", "0.2", "0.3"] {
+            applied(&mut transform, line);
+        }
+        assert_eq!(applied(&mut transform, "0.4"), "0.4,0.35");
+        //an infinity is no number, so the window stands as it was
+        assert_eq!(applied(&mut transform, "inf"), "inf,0.35");
+        //and numbers overflowing the sum leave no trace either
+        for line in ["1e308", "1e308", "1"] {
+            applied(&mut transform, line);
+        }
+        assert_eq!(applied(&mut transform, "2"), "2,1.5");
+    }
+
     #[test]
     fn wrap_expands_a_long_line_into_several() {
         let mut transform = WrapLines::new(3);
@@ -246,6 +246,170 @@ fn number_numbers_the_output_rows() {
     assert_eq!(stdout, "1\ta\n2\tc\n");
 }
 
+#[test]
+fn running_columns_restart_per_group() {
+    let input = TempFile::new(
+        "running",
+        "day,acct,amt\nmon,a,5\nmon,b,3\ntue,a,2\ntue,a,7\n",
+    );
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "--group-by",
+        "acct",
+        "--cumsum",
+        "amt",
+        "--delta",
+        "amt",
+        "--rank",
+        "day",
+        input.path_str(),
+    ]);
+    assert_eq!(
+        stdout,
+        "day,acct,amt\nmon,a,5,5,,1\nmon,b,3,3,,1\ntue,a,2,7,-3,2\ntue,a,7,14,5,2\n"
+    );
+
+    //a reordering would scramble the totals, and a summary leaves no rows
+    for args in [
+        &["--cumsum", "3", "-s"][..],
+        &["--delta", "3", "--count"],
+        &["--rank", "3", "--tac"],
+    ] {
+        let output = run_ft(&[&["-F", ","], args, &[input.path_str()]].concat());
+        assert!(!output.status.success(), "{args:?}");
+    }
+}
+
+#[test]
+fn rank_follows_a_sort() {
+    let input = TempFile::new("rank-sort", "id,score\na,1\nb,x\nc,3\nd,4\ne,3\n");
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "-s",
+        "-n",
+        "--reverse",
+        "--sort-key",
+        "score",
+        "--rank",
+        "score",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "id,score\nd,4,1\nc,3,2\ne,3,2\na,1,4\nb,x,5\n");
+}
+
+#[test]
+fn moving_statistics_over_the_last_numbers() {
+    let input = TempFile::new("moving", "t,ms\n1,10\n2,30\n3,x\n4,20\n5,60\n");
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "--moving-avg",
+        "ms",
+        "--moving-min",
+        "ms",
+        "--window",
+        "2",
+        input.path_str(),
+    ]);
+    //the non-number is skipped: the window still holds 10 and 30
+    assert_eq!(
+        stdout,
+        "t,ms\n1,10,10,10\n2,30,20,10\n3,x,20,10\n4,20,25,20\n5,60,40,20\n"
+    );
+
+    for args in [
+        &["--moving-max", "2"][..],
+        &["--window", "2", "--cumsum", "2"],
+        &["--moving-max", "2", "--window", "0"],
+    ] {
+        let output = run_ft(&[&["-F", ","], args, &[input.path_str()]].concat());
+        assert!(!output.status.success(), "{args:?}");
+    }
+}
+
+#[test]
+fn histogram_counts_values_or_bins() {
+    let input = TempFile::new(
+        "histogram",
+        "path,ms\n/a,12\n/b,250\n/a,40\n/c,99\n/a,180\n/b,7\n",
+    );
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "--histogram",
+        "path",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "/a,3,50.0\n/b,2,33.3\n/c,1,16.7\n");
+
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "--histogram",
+        "ms",
+        "--bin-width",
+        "100",
+        "--output-format",
+        "csv",
+        input.path_str(),
+    ]);
+    assert_eq!(
+        stdout,
+        "from,to,count,percent\n0,100,4,66.7\n100,200,1,16.7\n200,300,1,16.7\n"
+    );
+
+    for args in [
+        &["--histogram", "1", "--count"][..],
+        &["--bins", "2"],
+        &["--histogram", "2", "--bins", "2", "--bin-width", "5"],
+        &["--histogram", "2", "--bin-width", "0"],
+        &["--histogram", "2", "--bins", "100000000000"],
+    ] {
+        let output = run_ft(&[&["-F", ","], args, &[input.path_str()]].concat());
+        assert!(!output.status.success(), "{args:?}");
+    }
+}
+
+#[test]
+fn top_rows_per_group() {
+    let input = TempFile::new(
+        "top",
+        "path,ms\n/a,12\n/b,250\n/a,40\n/c,99\n/a,180\n/b,7\n",
+    );
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "-s",
+        "-n",
+        "--reverse",
+        "--sort-key",
+        "ms",
+        "--top",
+        "2",
+        "--group-by",
+        "path",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "path,ms\n/a,180\n/a,40\n/b,250\n/b,7\n/c,99\n");
+
+    for args in [
+        &["--top", "2"][..],
+        &["-s", "--top", "0"],
+        &["-s", "--top", "2", "-u"],
+    ] {
+        let output = run_ft(&[&["-F", ","], args, &[input.path_str()]].concat());
+        assert!(!output.status.success(), "{args:?}");
+    }
+}
+
 #[test]
 fn title_case_and_squeeze_clean_up_text() {
     let input = TempFile::new("tidy", "  hello   WIDE world  \n");
@@ -330,6 +494,135 @@ fn min_and_max_report_the_extremes() {
     assert_eq!(stdout, "-1.5,7\n");
 }
 
+#[test]
+fn median_and_percentiles_per_group() {
+    let input = TempFile::new("percentiles", "a,1\nb,10\na,4\na,2\na,3\n");
+    let stdout = run_ft_stdout(&[
+        "-F",
+        ",",
+        "--group-by",
+        "1",
+        "--median",
+        "2",
+        "--percentile",
+        "100:2",
+        "--percentile",
+        "0:2",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "a,2.5,4,1\nb,10,10,10\n");
+    let stdout = run_ft_stdout(&[
+        "-F",
+        ",",
+        "--approximate",
+        "--median",
+        "2",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "3\n");
+}
+
+#[test]
+fn a_percentile_needs_a_percentage_and_columns() {
+    let input = TempFile::new("percentile-invalid", "a,1\n");
+    for value in ["95", "x:2", "101:2"] {
+        let output = run_ft(&["-F", ",", "--percentile", value, input.path_str()]);
+        assert_eq!(output.status.code(), Some(2), "{value}");
+    }
+    let output = run_ft(&["-F", ",", "--approximate", "--sum", "2", input.path_str()]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn spreads_per_group_sample_or_population() {
+    let input = TempFile::new("spreads", "a,1\nb,5\na,3\nb,5\na,5\n");
+    let stdout = run_ft_stdout(&[
+        "-F",
+        ",",
+        "--group-by",
+        "1",
+        "--stddev",
+        "2",
+        "--var",
+        "2",
+        "--skew",
+        "2",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "a,2,4,0\nb,0,0,\n");
+    let stdout = run_ft_stdout(&["-F", ",", "--population", "--var", "2", input.path_str()]);
+    assert_eq!(stdout, "2.56\n");
+    let output = run_ft(&["-F", ",", "--population", "--sum", "2", input.path_str()]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn distinct_users_per_day() {
+    let input = TempFile::new("distinct", "day,user\nmon,bob\nmon,ann\ntue,eve\nmon,bob\n");
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "--group-by",
+        "day",
+        "--distinct",
+        "user",
+        "--mode",
+        "user",
+        "--collect",
+        "user",
+        ";",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "mon,2,bob,bob;ann;bob\ntue,1,eve,eve\n");
+}
+
+#[test]
+fn approximate_distinct_counts_per_group() {
+    let rows: String = (0..3000)
+        .map(|row| format!("{},{}\n", row % 2, row % 1000))
+        .collect();
+    let input = TempFile::new("distinct-approximate", &rows);
+    let stdout = run_ft_stdout(&[
+        "-F",
+        ",",
+        "--group-by",
+        "1",
+        "--distinct",
+        "2",
+        "--approximate",
+        input.path_str(),
+    ]);
+    let counts: Vec<f64> = stdout
+        .lines()
+        .map(|line| line[2..].parse().unwrap())
+        .collect();
+    //500 values each; the default precision is off by 0.8% typically
+    assert_eq!(counts.len(), 2);
+    for count in counts {
+        assert!((count - 500.0).abs() < 15.0, "{stdout}");
+    }
+
+    for args in [
+        &["--distinct", "2", "--precision", "12"][..],
+        &["--approximate", "--distinct", "2", "--precision", "3"],
+        &["--approximate", "--median", "2", "--precision", "12"],
+    ] {
+        let output = run_ft(&[&["-F", ","], args, &[input.path_str()]].concat());
+        assert!(!output.status.success(), "{args:?}");
+    }
+}
+
+#[test]
+fn collect_needs_columns_and_a_separator() {
+    let input = TempFile::new("collect-invalid", "a,1\n");
+    //the separator is not optional: a missing one would take the file
+    let output = run_ft(&["-F", ",", input.path_str(), "--collect", "2"]);
+    assert!(!output.status.success());
+    let output = run_ft(&["-F", ",", "--collect", "0", ";", input.path_str()]);
+    assert!(!output.status.success());
+}
+
 #[test]
 fn group_by_requires_a_summary() {
     let input = TempFile::new("group-alone", "a,1\n");
@@ -490,6 +783,147 @@ fn unique_key_is_independent_of_cols() {
     assert_eq!(stdout, "a,X\nb,Z\n");
 }
 
+#[test]
+fn sort_keys_compare_in_order() {
+    //by name, then by the size, largest first; then natural, folded
+    let input = TempFile::new("sort-keys", "b,3,v1.10\na,7,V1.9\nb,12,v1.2\na,7,v1.10\n");
+    let stdout = run_ft_stdout(&[
+        "-s",
+        "-F",
+        ",",
+        "--sort-key",
+        "1",
+        "--sort-key",
+        "2:nr",
+        "--sort-key",
+        "3:Vf",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "a,7,V1.9\na,7,v1.10\nb,12,v1.2\nb,3,v1.10\n");
+}
+
+#[test]
+fn version_sort_orders_digit_runs_as_numbers() {
+    let input = TempFile::new("version-sort", "ft-1.10.0\nft-1.9.3\nft-1.2\n");
+    let stdout = run_ft_stdout(&["-s", "--version-sort", input.path_str()]);
+    assert_eq!(stdout, "ft-1.2\nft-1.9.3\nft-1.10.0\n");
+}
+
+#[test]
+fn human_numeric_sort_orders_sizes_for_top_and_max() {
+    let input = TempFile::new("human-sort", "a 2M\nb 900K\nc 1.5G\nd 12K\n");
+    let stdout = run_ft_stdout(&[
+        "-s",
+        "--human-numeric-sort",
+        "--reverse",
+        "-F",
+        " ",
+        "--sort-key",
+        "2",
+        "--top",
+        "2",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "c 1.5G\na 2M\n");
+
+    //the extremes are written as read
+    let stdout = run_ft_stdout(&[
+        "-F",
+        " ",
+        "--human-numeric-sort",
+        "--min",
+        "2",
+        "--max",
+        "2",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "12K 1.5G\n");
+}
+
+#[test]
+fn version_sort_requires_something_to_compare() {
+    let input = TempFile::new("version-sort-alone", "a\n");
+    let output = run_ft(&["--version-sort", input.path_str()]);
+    assert!(!output.status.success());
+    let output = run_ft(&["-s", "-n", "--version-sort", input.path_str()]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn collated_sort_puts_letters_in_alphabetical_order() {
+    let input = TempFile::new("collate", "Zofia\nŁukasz\nadam\nLena\nÉmile\n");
+    let stdout = run_ft_stdout(&["-s", input.path_str()]);
+    assert_eq!(stdout, "Lena\nZofia\nadam\nÉmile\nŁukasz\n");
+    let stdout = run_ft_stdout(&["-s", "--collate", "uca", input.path_str()]);
+    assert_eq!(stdout, "adam\nÉmile\nLena\nŁukasz\nZofia\n");
+}
+
+#[test]
+fn folded_unique_treats_case_variants_as_duplicates() {
+    let input = TempFile::new("collate-unique", "Foo\nfoo\nbar\nFOO\n");
+    let stdout = run_ft_stdout(&["-u", "--collate", "fold", input.path_str()]);
+    assert_eq!(stdout, "Foo\nbar\n");
+    let stdout = run_ft_stdout(&["-s", "-u", "--collate", "fold", input.path_str()]);
+    assert_eq!(stdout, "bar\nFoo\n");
+}
+
+#[test]
+fn collate_requires_sort_or_unique() {
+    let input = TempFile::new("collate-alone", "a\n");
+    let output = run_ft(&["--collate", "uca", input.path_str()]);
+    assert!(!output.status.success());
+    let output = run_ft(&["-s", "--collate", "locale", input.path_str()]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn buffer_size_spills_the_sort_to_the_temp_dir_and_cleans_up() {
+    let lines: String = (0..2000)
+        .map(|i| format!("{}\t{i}\n", (i * 7919) % 1000))
+        .collect();
+    let input = TempFile::new("buffer-size", &lines);
+    let args = ["-s", "-n", "-F", "\t", "--sort-key", "1", "-u"];
+    let in_memory = run_ft_stdout(&[&args[..], &[input.path_str()]].concat());
+
+    let dir = std::env::temp_dir().join(format!("ft-test-{}-spill", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let spilled = run_ft_stdout(
+        &[
+            &args[..],
+            &["--buffer-size", "1K", "--temp-dir", dir.to_str().unwrap()],
+            &[input.path_str()],
+        ]
+        .concat(),
+    );
+    let left_behind = fs::read_dir(&dir).unwrap().count();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(spilled, in_memory);
+    assert_eq!(left_behind, 0);
+}
+
+#[test]
+fn buffer_size_must_be_a_size() {
+    let input = TempFile::new("buffer-size-invalid", "a\n");
+    for args in [
+        &["-s", "--buffer-size", "lots"][..],
+        &["-s", "--buffer-size", "0"],
+        &["--buffer-size", "1G"],
+        &["-s", "--top", "1", "--buffer-size", "1G"],
+        &["-s", "--temp-dir", "/tmp"],
+    ] {
+        let output = run_ft(&[args, &[input.path_str()]].concat());
+        assert!(!output.status.success(), "{args:?}");
+    }
+}
+
+#[test]
+fn sort_key_rejects_unknown_modifiers() {
+    let input = TempFile::new("sort-key-modifier", "a\n");
+    let output = run_ft(&["-s", "--sort-key", "1:q", input.path_str()]);
+    assert!(!output.status.success());
+}
+
 #[test]
 fn sort_key_requires_sort() {
     let input = TempFile::new("sort-key-alone", "a\n");
@@ -598,15 +1032,90 @@ fn delete_removes_end_relative_rows() {
 }
 
 #[test]
-fn columns_reject_end_relative_ranges() {
-    let output = run_ft(&["-C", "~2-~1", "input.txt"]);
+fn end_relative_rows_stream_a_long_input() {
+    let input: String = (1..=10_000)
+        .map(|n| format!("{n}\n"))
+        .collect();
+    let output = run_ft_with_stdin(&["-R", "~3-~2,5"], &input);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n9998\n9999\n");
+}
+
+#[test]
+fn rows_accept_steps() {
+    let input = TempFile::new("rows-stepped", "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n");
+    assert_eq!(run_ft_stdout(&["-R", "0~3", input.path_str()]), "3\n6\n9\n");
+    assert_eq!(
+        run_ft_stdout(&["-R", "2-8:3", input.path_str()]),
+        "2\n5\n8\n"
+    );
+    assert_eq!(
+        run_ft_stdout(&["-d", "-R", "~4-~1:2", input.path_str()]),
+        "1\n2\n3\n4\n5\n6\n8\n10\n"
+    );
+}
+
+#[test]
+fn invalid_step_is_rejected() {
+    let output = run_ft(&["-R", "1-10:0", "input.txt"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("step"));
+}
+
+const BLOCKS: &str = "a\nBEGIN\nb\nEND\nc\nBEGIN\nd\nEND\ne\n";
+
+#[test]
+fn rows_accept_pattern_ranges() {
+    let input = TempFile::new("rows-pattern", BLOCKS);
+    //every block, markers included
+    assert_eq!(
+        run_ft_stdout(&["-R", "/BEGIN/-/END/", input.path_str()]),
+        "BEGIN\nb\nEND\nBEGIN\nd\nEND\n"
+    );
+    //offsets shift the bounds past the markers
+    assert_eq!(
+        run_ft_stdout(&["-R", "/BEGIN/+1-/END/-1", input.path_str()]),
+        "b\nd\n"
+    );
+}
+
+#[test]
+fn from_and_to_bound_the_rows_by_pattern() {
+    let input = TempFile::new("rows-from-to", BLOCKS);
+    assert_eq!(
+        run_ft_stdout(&["--from", "/BEGIN/", "--to", "/END/", input.path_str()]),
+        "BEGIN\nb\nEND\nBEGIN\nd\nEND\n"
+    );
+    //without --to the first block never ends
+    assert_eq!(
+        run_ft_stdout(&["--from", "/c/", input.path_str()]),
+        "c\nBEGIN\nd\nEND\ne\n"
+    );
+}
+
+#[test]
+fn delete_removes_pattern_blocks() {
+    let input = TempFile::new("delete-rows-pattern", BLOCKS);
+    let stdout = run_ft_stdout(&["-d", "-R", "/BEGIN/-/END/", input.path_str()]);
+    assert_eq!(stdout, "a\nc\ne\n");
+}
+
+#[test]
+fn invalid_row_pattern_is_rejected() {
+    let output = run_ft(&["-R", "/[/", "input.txt"]);
     assert!(!output.status.success());
     assert!(
-        String::from_utf8_lossy(&output.stderr).contains("end-relative"),
-        "stderr should mention end-relative values"
+        String::from_utf8_lossy(&output.stderr).contains("row pattern"),
+        "stderr should point at the row pattern"
     );
 }
 
+#[test]
+fn columns_reject_an_inverted_end_relative_range() {
+    let output = run_ft(&["-C", "~1-~2", "input.txt"]);
+    assert!(!output.status.success());
+}
+
 #[test]
 fn delete_removes_a_list_of_row_ranges() {
     let input = TempFile::new("delete-rows-list", INPUT);
@@ -651,6 +1160,62 @@ fn fields_mode_delete_removes_field_and_delimiter() {
     assert_eq!(stdout, "one,three\nuno,tres\n");
 }
 
+#[test]
+fn fields_mode_counts_back_from_the_last_field_of_each_line() {
+    let input = TempFile::new("fields-from-end", "a,b,c\nd,e\nf,g,h,i\n");
+    let stdout = run_ft_stdout(&["-F", ",", "-C", "~1", input.path_str()]);
+    assert_eq!(stdout, "c\ne\ni\n");
+}
+
+#[test]
+fn fields_mode_deletes_all_but_the_last_two_fields() {
+    let input = TempFile::new("fields-delete-from-end", "a,b,c\nd,e\nf,g,h,i\n");
+    let stdout = run_ft_stdout(&["-d", "-F", ",", "-C", "-~3", input.path_str()]);
+    assert_eq!(stdout, "b,c\nd,e\nh,i\n");
+}
+
+#[test]
+fn columns_count_back_from_the_last_char_of_each_line() {
+    let input = TempFile::new("cols-from-end", "abcd\nxy\n");
+    let stdout = run_ft_stdout(&["--upper", "-C", "~2-", input.path_str()]);
+    assert_eq!(stdout, "abCD\nXY\n");
+}
+
+#[test]
+fn whitespace_fields_address_aligned_columns() {
+    let input = TempFile::new(
+        "fields-whitespace",
+        "  PID TTY      CMD\n    1 pts/0    bash\n  123 pts/0    ps aux\n",
+    );
+    let stdout = run_ft_stdout(&["-F", " ", "-C", "3,1", input.path_str()]);
+    assert_eq!(stdout, "CMD PID\nbash 1\nps 123\n");
+}
+
+#[test]
+fn fields_regex_splits_on_every_match() {
+    let input = TempFile::new("fields-regex", "a; b,c\nd ,e\n");
+    let stdout = run_ft_stdout(&[
+        "--fields-regex",
+        " *[;,] *",
+        "--output-delimiter",
+        "|",
+        "-C",
+        "3,2",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "c|b\ne\n");
+}
+
+#[test]
+fn invalid_fields_regex_is_rejected() {
+    let output = run_ft(&["--fields-regex", "[", "-C", "1", "input.txt"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("field regex"),
+        "stderr should point at the field regex"
+    );
+}
+
 #[test]
 fn fields_mode_sorts_by_field_key() {
     let input = TempFile::new("fields-sort", "x;30\ny;4\nz;19\n");
@@ -1144,6 +1709,458 @@ fn grep_invert_keeps_non_matching_rows() {
     assert_eq!(stdout, "delta foo\ncharlie foo\n");
 }
 
+const LOG: &str = "a\nb\nERROR 1\nc\nd\ne\nERROR 2\nf\n";
+
+#[test]
+fn grep_context_shows_the_rows_around_matches() {
+    let input = TempFile::new("grep-context", LOG);
+    let stdout = run_ft_stdout(&["-g", "ERROR", "-B", "1", "-A", "1", input.path_str()]);
+    assert_eq!(stdout, "b\nERROR 1\nc\n--\ne\nERROR 2\nf\n");
+
+    let stdout = run_ft_stdout(&["-g", "ERROR", "--context", "2", input.path_str()]);
+    assert_eq!(stdout, "a\nb\nERROR 1\nc\nd\ne\nERROR 2\nf\n");
+}
+
+#[test]
+fn grep_context_is_deleted_with_its_match() {
+    let input = TempFile::new("grep-context-delete", LOG);
+    let stdout = run_ft_stdout(&["-d", "-g", "ERROR", "-A", "1", input.path_str()]);
+    assert_eq!(stdout, "a\nb\nd\ne\n");
+}
+
+const PRICES: &str = "name,price,qty\nb,2,5\na,10,1\nb,3,2\n";
+
+#[test]
+fn header_names_the_columns() {
+    let input = TempFile::new("header-names", PRICES);
+    assert_eq!(
+        run_ft_stdout(&["--header", "-F", ",", "-C", "qty,name", input.path_str()]),
+        "qty,name\n5,b\n1,a\n2,b\n"
+    );
+    assert_eq!(
+        run_ft_stdout(&[
+            "--header",
+            "-F",
+            ",",
+            "--group-by",
+            "name",
+            "--sum",
+            "qty",
+            input.path_str()
+        ]),
+        "b,7\na,1\n"
+    );
+}
+
+#[test]
+fn header_passes_through_a_sort() {
+    let input = TempFile::new("header-sort", PRICES);
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "-s",
+        "-n",
+        "--sort-key",
+        "price",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "name,price,qty\nb,2,5\nb,3,2\na,10,1\n");
+}
+
+#[test]
+fn only_the_first_input_header_is_kept() {
+    let first = TempFile::new("header-first", PRICES);
+    let second = TempFile::new("header-second", "name,price,qty\nc,1,1\n");
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "-s",
+        "-n",
+        "--sort-key",
+        "price",
+        first.path_str(),
+        second.path_str(),
+    ]);
+    assert_eq!(stdout, "name,price,qty\nc,1,1\nb,2,5\nb,3,2\na,10,1\n");
+
+    //stdin among the inputs has its header skipped too
+    let output = run_ft_with_stdin(
+        &["--header", "-F", ",", "-C", "name", first.path_str(), "-"],
+        "name,price,qty\nd,4,4\n",
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "name\nb\na\nb\nd\n"
+    );
+}
+
+#[test]
+fn unknown_column_name_fails() {
+    let input = TempFile::new("header-unknown", PRICES);
+    let output = run_ft(&["--header", "-F", ",", "-C", "cost", input.path_str()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No column named `cost`"));
+}
+
+const COMMENTS: &str = "id,\"note\nin two lines\",n\n2,\"a, b\nc\",1\n1,short,2\n";
+
+#[test]
+fn quoted_records_span_lines() {
+    let input = TempFile::new("multiline-sort", COMMENTS);
+    let stdout = run_ft_stdout(&[
+        "--header",
+        "-F",
+        ",",
+        "--quoted",
+        "-s",
+        "--sort-key",
+        "id",
+        input.path_str(),
+    ]);
+    //the header's own name spans two lines, and each record moves whole
+    assert_eq!(
+        stdout,
+        "id,\"note\nin two lines\",n\n1,short,2\n2,\"a, b\nc\",1\n"
+    );
+}
+
+#[test]
+fn in_place_sorts_multiline_records_whole() {
+    let input = TempFile::new("multiline-in-place", COMMENTS);
+    let stdout = run_ft_stdout(&[
+        "-i",
+        "-F",
+        ",",
+        "--quoted",
+        "-s",
+        "--sort-key",
+        "1",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "");
+    let rewritten = fs::read_to_string(input.path_str()).expect("input file vanished");
+    assert_eq!(
+        rewritten,
+        "1,short,2\n2,\"a, b\nc\",1\nid,\"note\nin two lines\",n\n"
+    );
+}
+
+#[test]
+fn quote_keeps_a_projection_valid_csv() {
+    let input = TempFile::new("quote-minimal", "id,\"a; b\",\"c, d\"\n");
+    let stdout = run_ft_stdout(&[
+        "-F",
+        ",",
+        "--quoted",
+        "--output-delimiter",
+        ";",
+        "--quote",
+        "minimal",
+        "-C",
+        "1-3",
+        input.path_str(),
+    ]);
+    //the `;` inside field 2 now needs quotes, the `,` in field 3 no more
+    assert_eq!(stdout, "id;\"a; b\";c, d\n");
+}
+
+#[test]
+fn quote_protects_a_replaced_delimiter() {
+    let input = TempFile::new("quote-replace", "a,b,c\n");
+    let stdout = run_ft_stdout(&[
+        "-F",
+        ",",
+        "--quote",
+        "minimal",
+        "-C",
+        "2",
+        "-f",
+        "b",
+        "-r",
+        "x,y",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "a,\"x,y\",c\n");
+}
+
+#[test]
+fn unquote_strips_the_quotes() {
+    let input = TempFile::new("unquote", "\"a\",\"say \"\"hi\"\"\"\n");
+    let stdout = run_ft_stdout(&[
+        "-F",
+        ",",
+        "--quoted",
+        "--unquote",
+        "--output-delimiter",
+        "\t",
+        "-C",
+        "1-",
+        input.path_str(),
+    ]);
+    assert_eq!(stdout, "a\tsay \"hi\"\n");
+}
+
+const LAYOUT: &str = "# customer export\nid 1-5 number\nname 6-14\namount 15-21 number\n";
+const RECORDS: &str = "00042ACME Ltd -0012.5\n00007Bolt     0000001\n";
+
+#[test]
+fn schema_names_fixed_width_columns() {
+    let schema = TempFile::new("schema-names.schema", LAYOUT);
+    let input = TempFile::new("schema-names", RECORDS);
+    assert_eq!(
+        run_ft_stdout(&[
+            "--schema",
+            schema.path_str(),
+            "-C",
+            "name",
+            "--upper",
+            input.path_str()
+        ]),
+        "00042ACME LTD -0012.5\n00007BOLT     0000001\n"
+    );
+}
+
+#[test]
+fn delimit_turns_records_into_fields() {
+    let schema = TempFile::new("schema-delimit.schema", LAYOUT);
+    let input = TempFile::new("schema-delimit", RECORDS);
+    assert_eq!(
+        run_ft_stdout(&[
+            "--schema",
+            schema.path_str(),
+            "--delimit",
+            ",",
+            input.path_str()
+        ]),
+        "42,ACME Ltd,-12.5\n7,Bolt,1\n"
+    );
+    assert_eq!(
+        run_ft_stdout(&[
+            "--schema",
+            schema.path_str(),
+            "--delimit",
+            "\t",
+            "-C",
+            "amount,id",
+            input.path_str()
+        ]),
+        "-12.5\t42\n1\t7\n"
+    );
+}
+
+#[test]
+fn unknown_schema_field_fails() {
+    let schema = TempFile::new("schema-unknown.schema", LAYOUT);
+    let input = TempFile::new("schema-unknown", RECORDS);
+    let output = run_ft(&[
+        "--schema",
+        schema.path_str(),
+        "-C",
+        "cost",
+        input.path_str(),
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No field named `cost`"));
+}
+
+#[test]
+fn malformed_schema_is_rejected() {
+    let schema = TempFile::new("schema-malformed.schema", "id 1-5\nname six\n");
+    let input = TempFile::new("schema-malformed", RECORDS);
+    let output = run_ft(&["--schema", schema.path_str(), input.path_str()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Schema line 2"));
+}
+
+const EVENTS: &str = "{\"user\": {\"id\": 7}, \"level\": \"warn\", \"ms\": 30}\n\
+                      {\"user\": {\"id\": 3}, \"level\": \"error\", \"ms\": 120}\n\
+                      {\"user\": {\"id\": 7}, \"level\": \"error\", \"ms\": 5}\n";
+
+#[test]
+fn jsonl_addresses_members_by_key_path() {
+    let input = TempFile::new("jsonl-paths", EVENTS);
+    assert_eq!(
+        run_ft_stdout(&["--jsonl", "-C", ".user.id,.ms", input.path_str()]),
+        "7\t30\n3\t120\n7\t5\n"
+    );
+    assert_eq!(
+        run_ft_stdout(&[
+            "--jsonl",
+            "--group-by",
+            ".user.id",
+            "--sum",
+            ".ms",
+            input.path_str()
+        ]),
+        "7\t35\n3\t120\n"
+    );
+}
+
+#[test]
+fn jsonl_rewrites_values_in_place() {
+    let input = TempFile::new("jsonl-rewrite", EVENTS);
+    let stdout = run_ft_stdout(&[
+        "--jsonl",
+        "-g",
+        "error",
+        "-C",
+        ".level",
+        "-f",
+        "error",
+        "-r",
+        "E\"1",
+        input.path_str(),
+    ]);
+    assert_eq!(
+        stdout,
+        "{\"user\": {\"id\": 3}, \"level\": \"E\\\"1\", \"ms\": 120}\n\
+         {\"user\": {\"id\": 7}, \"level\": \"E\\\"1\", \"ms\": 5}\n"
+    );
+    assert_eq!(
+        run_ft_stdout(&["--jsonl", "-d", "-C", ".user", "-R", "1", input.path_str()])
+            .lines()
+            .next(),
+        Some("{\"level\": \"warn\", \"ms\": 30}")
+    );
+}
+
+#[test]
+fn output_format_names_several_inputs_after_the_first_header() {
+    let first = TempFile::new("format-first", PRICES);
+    let second = TempFile::new("format-second", "name,price,qty\nc,1,1\n");
+    let args = ["--header", "-F", ",", "-C", "name,price", "--output-format"];
+    let files = [first.path_str(), second.path_str()];
+    assert_eq!(
+        run_ft_stdout(&[&args[..], &["jsonl"], &files].concat()),
+        "{\"name\":\"b\",\"price\":2}\n{\"name\":\"a\",\"price\":10}\n\
+         {\"name\":\"b\",\"price\":3}\n{\"name\":\"c\",\"price\":1}\n"
+    );
+    //no header among the rows to stop the prices aligning right
+    assert_eq!(
+        run_ft_stdout(&[&args[..], &["table"], &files].concat()),
+        "name  price\nb         2\na        10\nb         3\nc         1\n"
+    );
+}
+
+#[test]
+fn output_format_jsonl_names_fields_after_the_header() {
+    let input = TempFile::new("jsonl-output", PRICES);
+    assert_eq!(
+        run_ft_stdout(&[
+            "--header",
+            "-F",
+            ",",
+            "--output-format",
+            "jsonl",
+            "-C",
+            "name,qty",
+            input.path_str()
+        ]),
+        "{\"name\":\"b\",\"qty\":5}\n{\"name\":\"a\",\"qty\":1}\n{\"name\":\"b\",\"qty\":2}\n"
+    );
+    let events = TempFile::new("jsonl-output-events", EVENTS);
+    assert_eq!(
+        run_ft_stdout(&[
+            "--jsonl",
+            "--output-format",
+            "jsonl",
+            "-C",
+            ".user.id,.level",
+            "-R",
+            "2",
+            events.path_str()
+        ]),
+        "{\"user.id\":3,\"level\":\"error\"}\n"
+    );
+}
+
+#[test]
+fn output_format_renders_a_table() {
+    let input = TempFile::new("markdown-output", PRICES);
+    assert_eq!(
+        run_ft_stdout(&[
+            "-F",
+            ",",
+            "--header",
+            "-C",
+            "qty,name",
+            "--output-format",
+            "markdown",
+            input.path_str()
+        ]),
+        "| qty | name |\n| --- | --- |\n| 5 | b |\n| 1 | a |\n| 2 | b |\n"
+    );
+    let quoted = TempFile::new("html-output", "a,\"<x>, & y\"\n");
+    assert_eq!(
+        run_ft_stdout(&[
+            "-F",
+            ",",
+            "--quoted",
+            "--output-format",
+            "html",
+            quoted.path_str()
+        ]),
+        "<table>\n<tbody>\n<tr><td>a</td><td>&lt;x&gt;, &amp; y</td></tr>\n</tbody>\n</table>\n"
+    );
+}
+
+#[test]
+fn output_format_renders_a_summary() {
+    let input = TempFile::new("summary-output", PRICES);
+    assert_eq!(
+        run_ft_stdout(&[
+            "-F",
+            ",",
+            "--header",
+            "--group-by",
+            "name",
+            "--sum",
+            "qty",
+            "--output-format",
+            "json",
+            input.path_str()
+        ]),
+        "[\n{\"name\":\"b\",\"sum(qty)\":7},\n{\"name\":\"a\",\"sum(qty)\":1}\n]\n"
+    );
+    assert_eq!(
+        run_ft_stdout(&["--count", "--output-format", "tsv", input.path_str()]),
+        "count\n4\n"
+    );
+}
+
+#[test]
+fn output_format_table_aligns_the_columns() {
+    let input = TempFile::new("table-output", "city,visits\n東京,7\nOslo,12\n東京,5\n");
+    assert_eq!(
+        run_ft_stdout(&[
+            "-F",
+            ",",
+            "--header",
+            "--group-by",
+            "city",
+            "--sum",
+            "visits",
+            "--output-format",
+            "table",
+            input.path_str()
+        ]),
+        "city  sum(visits)\n東京           12\nOslo           12\n"
+    );
+}
+
+#[test]
+fn unknown_output_format_fails() {
+    let input = TempFile::new("unknown-format", PRICES);
+    let output = run_ft(&["--output-format", "xml", input.path_str()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("expected json, jsonl"),
+        "the error should list the formats"
+    );
+}
+
 #[test]
 fn help_explains_examples_and_exit_codes() {
     let stdout = run_ft_stdout(&["--help"]);